@@ -0,0 +1,183 @@
+use crate::prelude::*;
+
+// 2d tree stored as an implicit balanced tree,
+// the median of each slice of `nodes` is the splitting node
+// with the left and right halves being the children
+#[derive(Debug, Clone)]
+pub struct KdTree {
+	points: Vec<Pos2>,
+	nodes: Vec<usize>,
+}
+
+impl KdTree {
+	#[must_use]
+	pub fn new(points: &[Pos2]) -> Self {
+		let mut nodes: Vec<usize> = (0..points.len()).collect();
+		build(points, &mut nodes, 0);
+		Self {
+			points: points.to_vec(),
+			nodes,
+		}
+	}
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.points.len()
+	}
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.points.is_empty()
+	}
+	#[must_use]
+	pub fn points(&self) -> &[Pos2] {
+		&self.points
+	}
+	// returns (index, squared distance) of the closest point
+	#[must_use]
+	pub fn nearest(&self, point: Pos2) -> Option<(usize, f64)> {
+		self.nearest_k(point, 1).first().copied()
+	}
+	// returns (index, squared distance) of the k closest points sorted by distance
+	#[must_use]
+	pub fn nearest_k(&self, point: Pos2, k: usize) -> Vec<(usize, f64)> {
+		let mut best = Vec::with_capacity(k + 1);
+		if k != 0 {
+			self.search(&self.nodes, 0, point, k, &mut best);
+		}
+		best
+	}
+	// returns (index, squared distance) of all points within radius in no particular order
+	#[must_use]
+	pub fn within_radius(&self, point: Pos2, radius: f64) -> Vec<(usize, f64)> {
+		let mut found = Vec::new();
+		self.search_radius(&self.nodes, 0, point, radius * radius, &mut found);
+		found
+	}
+	fn search(
+		&self,
+		nodes: &[usize],
+		depth: usize,
+		point: Pos2,
+		k: usize,
+		best: &mut Vec<(usize, f64)>,
+	) {
+		if nodes.is_empty() {
+			return;
+		}
+		let mid = nodes.len() / 2;
+		let i = nodes[mid];
+		let d_sq = (self.points[i] - point).magnitude_squared();
+
+		// insert into sorted list of best candidates
+		if best.len() < k || d_sq < best[best.len() - 1].1 {
+			let pos = best.partition_point(|v| v.1 <= d_sq);
+			best.insert(pos, (i, d_sq));
+			best.truncate(k);
+		}
+
+		let axis = depth % 2;
+		let diff = point[axis] - self.points[i][axis];
+		let (near, far) = if diff < 0.0 {
+			(&nodes[..mid], &nodes[mid + 1..])
+		} else {
+			(&nodes[mid + 1..], &nodes[..mid])
+		};
+
+		self.search(near, depth + 1, point, k, best);
+		// only check other side if the splitting plane is closer than the worst candidate
+		if best.len() < k || diff * diff < best[best.len() - 1].1 {
+			self.search(far, depth + 1, point, k, best);
+		}
+	}
+	fn search_radius(
+		&self,
+		nodes: &[usize],
+		depth: usize,
+		point: Pos2,
+		r_sq: f64,
+		found: &mut Vec<(usize, f64)>,
+	) {
+		if nodes.is_empty() {
+			return;
+		}
+		let mid = nodes.len() / 2;
+		let i = nodes[mid];
+		let d_sq = (self.points[i] - point).magnitude_squared();
+		if d_sq <= r_sq {
+			found.push((i, d_sq));
+		}
+
+		let axis = depth % 2;
+		let diff = point[axis] - self.points[i][axis];
+		if diff < 0.0 || diff * diff <= r_sq {
+			self.search_radius(&nodes[..mid], depth + 1, point, r_sq, found);
+		}
+		if diff >= 0.0 || diff * diff <= r_sq {
+			self.search_radius(&nodes[mid + 1..], depth + 1, point, r_sq, found);
+		}
+	}
+}
+
+fn build(points: &[Pos2], nodes: &mut [usize], depth: usize) {
+	if nodes.len() <= 1 {
+		return;
+	}
+	let axis = depth % 2;
+	let mid = nodes.len() / 2;
+	nodes.select_nth_unstable_by(mid, |&a, &b| float_cmp(points[a][axis], points[b][axis]));
+
+	let (left, right) = nodes.split_at_mut(mid);
+	build(points, left, depth + 1);
+	build(points, &mut right[1..], depth + 1);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn test_points() -> Vec<Pos2> {
+		// deterministic scattering of points
+		(0..200)
+			.map(|i| {
+				let i = f64::from(i);
+				Pos2::new((i * 12.9898).sin() * 10.0, (i * 78.233).cos() * 10.0)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn nearest_matches_brute_force() {
+		let points = test_points();
+		let tree = KdTree::new(&points);
+
+		for query in [
+			Pos2::new(0.0, 0.0),
+			Pos2::new(3.3, -7.1),
+			Pos2::new(-12.0, 4.0),
+			Pos2::new(9.9, 9.9),
+		] {
+			let mut brute: Vec<(usize, f64)> = points
+				.iter()
+				.enumerate()
+				.map(|(i, p)| (i, (p - query).magnitude_squared()))
+				.collect();
+			brute.sort_by(|a, b| float_cmp(a.1, b.1));
+
+			let nearest = tree.nearest_k(query, 5);
+			assert_eq!(nearest.len(), 5);
+			for (a, b) in nearest.iter().zip(brute.iter()) {
+				assert!((a.1 - b.1).abs() < 1e-12);
+			}
+
+			let mut radius = tree.within_radius(query, 3.0);
+			radius.sort_by(|a, b| float_cmp(a.1, b.1));
+			let brute_radius: Vec<_> = brute.iter().filter(|v| v.1 <= 9.0).collect();
+			assert_eq!(radius.len(), brute_radius.len());
+		}
+	}
+
+	#[test]
+	fn empty_tree() {
+		let tree = KdTree::new(&[]);
+		assert!(tree.nearest(Pos2::new(1.0, 1.0)).is_none());
+	}
+}
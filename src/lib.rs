@@ -13,11 +13,15 @@ extern crate alloc;
 #[macro_use]
 extern crate std;
 
+pub mod kd_tree;
 pub mod path_planning;
 pub mod path_tracking;
+pub mod scan_matching;
+pub mod sensors;
 
 #[cfg(feature = "no_std")]
 pub mod no_std_stuff {
+	pub use alloc::collections::{BTreeSet, BinaryHeap};
 	pub use alloc::vec::Vec;
 	pub use nalgebra::{ComplexField, RealField};
 }
@@ -25,7 +29,7 @@ pub mod no_std_stuff {
 pub mod prelude {
 	use core::cmp::Ordering;
 
-	pub use nalgebra::{Point2, Point3, Rotation2, Vector2, Vector3};
+	pub use nalgebra::{Matrix2, Matrix3, Point2, Point3, Rotation2, Vector2, Vector3};
 
 	#[derive(Debug, Copy, Clone, PartialEq)]
 	pub enum Error {
@@ -37,9 +41,13 @@ pub mod prelude {
 	pub type Vec3 = Vector3<f64>;
 	pub type Pos2 = Point2<f64>;
 	pub type Pos3 = Point3<f64>;
+	pub type Mat2 = Matrix2<f64>;
+	pub type Mat3 = Matrix3<f64>;
 
 	#[cfg(feature = "no_std")]
 	pub use crate::no_std_stuff::*;
+	#[cfg(not(feature = "no_std"))]
+	pub use std::collections::{BTreeSet, BinaryHeap};
 
 	#[derive(Debug, Copy, Clone, PartialEq)]
 	#[must_use]
@@ -81,12 +89,33 @@ pub mod prelude {
 		pub fn ray_from_local(&self, other: Self) -> Self {
 			other.rotated(self.angle).translated(self.pos.coords)
 		}
+		#[must_use]
+		pub fn point_to_local(&self, point: Pos2) -> Pos2 {
+			Rotation2::new(-self.angle) * (point - self.pos.coords)
+		}
+		#[must_use]
+		pub fn point_from_local(&self, point: Pos2) -> Pos2 {
+			Rotation2::new(self.angle) * point + self.pos.coords
+		}
         #[must_use]
         pub fn at(&self, t: f64) -> Pos2 {
             self.pos + t * Vec2::new(self.angle.cos(), self.angle.sin())
         }
 	}
 
+	// map angle to [-pi, pi]
+	pub(crate) fn map_angle(angle: f64) -> f64 {
+		use core::f64::consts::{PI, TAU};
+
+		let mut angle = angle % TAU;
+		if angle > PI {
+			angle -= TAU;
+		} else if angle < -PI {
+			angle += TAU;
+		}
+		angle
+	}
+
 	#[allow(clippy::float_cmp)]
 	pub(crate) fn float_cmp(a: f64, b: f64) -> Ordering {
 		if a < b {
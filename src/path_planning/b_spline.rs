@@ -0,0 +1,263 @@
+use super::parametric_curve::{CurveError, ParametricCurve};
+use crate::prelude::*;
+
+// b-spline over the knot vector, defined for t in [knots[degree], knots[control points]]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BSpline {
+	degree: usize,
+	knots: Vec<f64>,
+	control_points: Vec<Pos2>,
+	// control points of the first and second derivatives
+	// which use the knot vector without the first and last one or two knots
+	first: Vec<Vec2>,
+	second: Vec<Vec2>,
+}
+
+impl BSpline {
+	// knots must be non-decreasing with control_points.len() + degree + 1 values
+	pub fn new(control_points: &[Pos2], knots: &[f64], degree: usize) -> Result<Self, CurveError> {
+		if degree == 0 {
+			return Err(CurveError::InvalidDegree);
+		}
+		if control_points.len() <= degree {
+			return Err(CurveError::NotEnoughPoints);
+		}
+		if knots.len() != control_points.len() + degree + 1 || knots.windows(2).any(|w| w[1] < w[0])
+		{
+			return Err(CurveError::InvalidInput);
+		}
+		if knots[degree] >= knots[control_points.len()] {
+			return Err(CurveError::InvalidInput);
+		}
+
+		let points: Vec<Vec2> = control_points.iter().map(|p| p.coords).collect();
+		let first = derivative_points(&points, knots, degree);
+		let second = if degree > 1 {
+			derivative_points(&first, &knots[1..knots.len() - 1], degree - 1)
+		} else {
+			Vec::new()
+		};
+		Ok(Self {
+			degree,
+			knots: knots.to_vec(),
+			control_points: control_points.to_vec(),
+			first,
+			second,
+		})
+	}
+	// evenly spaced knots, the curve doesn't pass through the end control points
+	pub fn uniform(control_points: &[Pos2], degree: usize) -> Result<Self, CurveError> {
+		let knots: Vec<f64> = (0..control_points.len() + degree + 1)
+			.map(|i| i as f64)
+			.collect();
+		Self::new(control_points, &knots, degree)
+	}
+	// end knots repeated so the curve starts and ends at the end control points, t in [0, 1]
+	pub fn clamped(control_points: &[Pos2], degree: usize) -> Result<Self, CurveError> {
+		if control_points.len() <= degree {
+			return Err(CurveError::NotEnoughPoints);
+		}
+		let inner = control_points.len() - degree;
+		let knots: Vec<f64> = (0..control_points.len() + degree + 1)
+			.map(|i| (i.saturating_sub(degree).min(inner)) as f64 / inner as f64)
+			.collect();
+		Self::new(control_points, &knots, degree)
+	}
+	#[must_use]
+	pub fn degree(&self) -> usize {
+		self.degree
+	}
+	#[must_use]
+	pub fn knots(&self) -> &[f64] {
+		&self.knots
+	}
+	#[must_use]
+	pub fn control_points(&self) -> &[Pos2] {
+		&self.control_points
+	}
+	// boehm's algorithm, the curve is unchanged
+	pub fn insert_knot(&self, t: f64) -> Result<Self, CurveError> {
+		let (start, end) = self.domain();
+		if t < start || t > end {
+			return Err(CurveError::OutOfRange);
+		}
+		let (p, u) = (self.degree, &self.knots);
+		let k = span(u, p, self.control_points.len(), t);
+
+		let mut points = Vec::with_capacity(self.control_points.len() + 1);
+		points.extend_from_slice(&self.control_points[..=k - p]);
+		for i in k - p + 1..=k {
+			let alpha = (t - u[i]) / (u[i + p] - u[i]);
+			let (a, b) = (self.control_points[i - 1], self.control_points[i]);
+			points.push(a + (b - a) * alpha);
+		}
+		points.extend_from_slice(&self.control_points[k..]);
+
+		let mut knots = self.knots.clone();
+		knots.insert(k + 1, t);
+		Self::new(&points, &knots, p)
+	}
+	// the two halves share a control point at t and keep their original parameters
+	pub fn split(&self, t: f64) -> Result<(Self, Self), CurveError> {
+		let (start, end) = self.domain();
+		if t <= start || t >= end {
+			return Err(CurveError::OutOfRange);
+		}
+		let mut spline = self.clone();
+		while spline.knots.iter().filter(|&&u| u == t).count() < self.degree {
+			spline = spline.insert_knot(t)?;
+		}
+		let m = spline
+			.knots
+			.iter()
+			.position(|&u| u == t)
+			.unwrap_or_default();
+		let p = self.degree;
+
+		let mut left_knots = spline.knots[..m + p].to_vec();
+		left_knots.push(t);
+		let mut right_knots = Vec::from([t]);
+		right_knots.extend_from_slice(&spline.knots[m..]);
+
+		Ok((
+			Self::new(&spline.control_points[..m], &left_knots, p)?,
+			Self::new(&spline.control_points[m - 1..], &right_knots, p)?,
+		))
+	}
+}
+
+impl ParametricCurve for BSpline {
+	fn domain(&self) -> (f64, f64) {
+		(
+			self.knots[self.degree],
+			self.knots[self.control_points.len()],
+		)
+	}
+	fn evaluate_unchecked(&self, t: f64) -> Pos2 {
+		let points: Vec<Vec2> = self.control_points.iter().map(|p| p.coords).collect();
+		de_boor(&points, &self.knots, self.degree, t).into()
+	}
+	fn derivative(&self, t: f64) -> Vec2 {
+		de_boor(
+			&self.first,
+			&self.knots[1..self.knots.len() - 1],
+			self.degree - 1,
+			t,
+		)
+	}
+	fn second_derivative(&self, t: f64) -> Vec2 {
+		if self.degree < 2 {
+			return Vec2::zeros();
+		}
+		de_boor(
+			&self.second,
+			&self.knots[2..self.knots.len() - 2],
+			self.degree - 2,
+			t,
+		)
+	}
+}
+
+// index k of the knot span with knots[k] <= t < knots[k + 1] inside the domain
+fn span(knots: &[f64], degree: usize, n: usize, t: f64) -> usize {
+	knots[..n]
+		.partition_point(|&u| u <= t)
+		.saturating_sub(1)
+		.clamp(degree, n - 1)
+}
+
+fn de_boor(points: &[Vec2], knots: &[f64], degree: usize, t: f64) -> Vec2 {
+	let n = points.len();
+	let k = span(knots, degree, n, t);
+	let mut d: Vec<Vec2> = points[k - degree..=k].to_vec();
+	for r in 1..=degree {
+		for j in (r..=degree).rev() {
+			let (lo, hi) = (knots[j + k - degree], knots[j + 1 + k - r]);
+			let alpha = if hi > lo { (t - lo) / (hi - lo) } else { 0.0 };
+			d[j] = d[j - 1] * (1.0 - alpha) + d[j] * alpha;
+		}
+	}
+	d[degree]
+}
+
+fn derivative_points(points: &[Vec2], knots: &[f64], degree: usize) -> Vec<Vec2> {
+	let p = degree as f64;
+	points
+		.windows(2)
+		.enumerate()
+		.map(|(i, w)| {
+			let dt = knots[i + degree + 1] - knots[i + 1];
+			if dt > 0.0 {
+				(w[1] - w[0]) * p / dt
+			} else {
+				Vec2::zeros()
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn control_points() -> [Pos2; 6] {
+		[
+			Pos2::new(0.0, 0.0),
+			Pos2::new(1.0, 2.0),
+			Pos2::new(3.0, 3.0),
+			Pos2::new(4.0, 0.0),
+			Pos2::new(6.0, -1.0),
+			Pos2::new(7.0, 1.0),
+		]
+	}
+
+	#[test]
+	fn clamped_and_uniform() {
+		let points = control_points();
+		let clamped = BSpline::clamped(&points, 3).unwrap();
+		assert_eq!(clamped.domain(), (0.0, 1.0));
+		assert!((clamped.evaluate(0.0).unwrap() - points[0]).magnitude() < 1e-12);
+		assert!((clamped.evaluate(1.0).unwrap() - points[5]).magnitude() < 1e-12);
+
+		// a uniform cubic b-spline starts at (p0 + 4 p1 + p2) / 6
+		let uniform = BSpline::uniform(&points, 3).unwrap();
+		let (start, _) = uniform.domain();
+		let expected = (points[0].coords + 4.0 * points[1].coords + points[2].coords) / 6.0;
+		assert!((uniform.evaluate_unchecked(start).coords - expected).magnitude() < 1e-12);
+
+		assert!(BSpline::clamped(&points[..3], 3).is_err());
+	}
+
+	#[test]
+	fn derivatives() {
+		let spline = BSpline::clamped(&control_points(), 3).unwrap();
+		let h = 1e-6;
+		for t in [0.1, 0.45, 0.8] {
+			let numerical =
+				(spline.evaluate_unchecked(t + h) - spline.evaluate_unchecked(t - h)) / (2.0 * h);
+			assert!((spline.derivative(t) - numerical).magnitude() < 1e-5);
+			let numerical = (spline.derivative(t + h) - spline.derivative(t - h)) / (2.0 * h);
+			assert!((spline.second_derivative(t) - numerical).magnitude() < 1e-4);
+		}
+	}
+
+	#[test]
+	fn knot_insertion_and_split() {
+		let spline = BSpline::clamped(&control_points(), 3).unwrap();
+		let inserted = spline.insert_knot(0.4).unwrap();
+		assert_eq!(inserted.control_points().len(), 7);
+
+		let (left, right) = spline.split(0.6).unwrap();
+		for i in 0..=10 {
+			let t = i as f64 / 10.0;
+			let expected = spline.evaluate_unchecked(t);
+			let p = if t <= 0.6 {
+				left.evaluate_unchecked(t)
+			} else {
+				right.evaluate_unchecked(t)
+			};
+			assert!((p - expected).magnitude() < 1e-12);
+			assert!((inserted.evaluate_unchecked(t) - expected).magnitude() < 1e-12);
+		}
+	}
+}
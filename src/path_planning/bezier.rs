@@ -0,0 +1,201 @@
+use super::parametric_curve::{CurveError, ParametricCurve};
+use crate::prelude::*;
+
+// bezier curve of any degree over t in [0, 1]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bezier {
+	control_points: Vec<Pos2>,
+	// control points of the first and second derivatives
+	first: Vec<Vec2>,
+	second: Vec<Vec2>,
+}
+
+impl Bezier {
+	pub fn new(control_points: &[Pos2]) -> Result<Self, CurveError> {
+		if control_points.len() < 2 {
+			return Err(CurveError::NotEnoughPoints);
+		}
+		Ok(Self::from_points(control_points))
+	}
+	pub fn cubic(control_points: [Pos2; 4]) -> Self {
+		Self::from_points(&control_points)
+	}
+	pub fn quintic(control_points: [Pos2; 6]) -> Self {
+		Self::from_points(&control_points)
+	}
+	fn from_points(control_points: &[Pos2]) -> Self {
+		let points: Vec<Vec2> = control_points.iter().map(|p| p.coords).collect();
+		let first = hodograph(&points);
+		let second = hodograph(&first);
+		Self {
+			control_points: control_points.to_vec(),
+			first,
+			second,
+		}
+	}
+	// cubic bezier leaving start and arriving at end along their headings
+	// offset is the distance of the inner control points from the ends
+	pub fn between_rays(start: Ray, end: Ray, offset: f64) -> Result<Self, CurveError> {
+		if offset <= 0.0 {
+			return Err(CurveError::InvalidInput);
+		}
+		let (d0, d1) = (direction(start.angle), direction(end.angle));
+		Ok(Self::cubic([
+			start.pos,
+			start.pos + offset * d0,
+			end.pos - offset * d1,
+			end.pos,
+		]))
+	}
+	// quintic bezier between rays with zero curvature at both ends
+	pub fn quintic_between_rays(start: Ray, end: Ray, offset: f64) -> Result<Self, CurveError> {
+		if offset <= 0.0 {
+			return Err(CurveError::InvalidInput);
+		}
+		let (d0, d1) = (direction(start.angle), direction(end.angle));
+		Ok(Self::quintic([
+			start.pos,
+			start.pos + offset * d0,
+			start.pos + 2.0 * offset * d0,
+			end.pos - 2.0 * offset * d1,
+			end.pos - offset * d1,
+			end.pos,
+		]))
+	}
+	#[must_use]
+	pub fn degree(&self) -> usize {
+		self.control_points.len() - 1
+	}
+	#[must_use]
+	pub fn control_points(&self) -> &[Pos2] {
+		&self.control_points
+	}
+	// de casteljau's algorithm, the intermediate points give the control points of both halves
+	pub fn split(&self, t: f64) -> Result<(Self, Self), CurveError> {
+		if !(0.0..=1.0).contains(&t) {
+			return Err(CurveError::OutOfRange);
+		}
+		let mut points = self.control_points.clone();
+		let mut left = Vec::with_capacity(points.len());
+		let mut right = Vec::with_capacity(points.len());
+		left.push(points[0]);
+		right.push(points[points.len() - 1]);
+		for n in (1..points.len()).rev() {
+			for i in 0..n {
+				points[i] = points[i] + (points[i + 1] - points[i]) * t;
+			}
+			left.push(points[0]);
+			right.push(points[n - 1]);
+		}
+		right.reverse();
+		Ok((Self::from_points(&left), Self::from_points(&right)))
+	}
+}
+
+impl ParametricCurve for Bezier {
+	fn domain(&self) -> (f64, f64) {
+		(0.0, 1.0)
+	}
+	fn evaluate_unchecked(&self, t: f64) -> Pos2 {
+		de_casteljau(self.control_points.iter().map(|p| p.coords).collect(), t).into()
+	}
+	fn derivative(&self, t: f64) -> Vec2 {
+		de_casteljau(self.first.clone(), t)
+	}
+	fn second_derivative(&self, t: f64) -> Vec2 {
+		de_casteljau(self.second.clone(), t)
+	}
+}
+
+fn direction(angle: f64) -> Vec2 {
+	Vec2::new(angle.cos(), angle.sin())
+}
+
+// control points of the derivative
+fn hodograph(points: &[Vec2]) -> Vec<Vec2> {
+	let n = points.len().saturating_sub(1) as f64;
+	points.windows(2).map(|w| n * (w[1] - w[0])).collect()
+}
+
+fn de_casteljau(mut points: Vec<Vec2>, t: f64) -> Vec2 {
+	if points.is_empty() {
+		return Vec2::zeros();
+	}
+	for n in (1..points.len()).rev() {
+		for i in 0..n {
+			points[i] = points[i].lerp(&points[i + 1], t);
+		}
+	}
+	points[0]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::path_planning::ArcLength;
+
+	#[test]
+	fn between_rays() {
+		let start = Ray::new(Pos2::new(0.0, 0.0), 0.0);
+		let end = Ray::new(Pos2::new(4.0, 3.0), 1.2);
+		for curve in [
+			Bezier::between_rays(start, end, 1.5).unwrap(),
+			Bezier::quintic_between_rays(start, end, 1.0).unwrap(),
+		] {
+			assert!((curve.evaluate(0.0).unwrap() - start.pos).magnitude() < 1e-12);
+			assert!((curve.evaluate(1.0).unwrap() - end.pos).magnitude() < 1e-12);
+			assert!(curve.heading(0.0).abs() < 1e-12);
+			assert!((curve.heading(1.0) - 1.2).abs() < 1e-12);
+			assert!(curve.evaluate(1.1).is_err());
+		}
+		let quintic = Bezier::quintic_between_rays(start, end, 1.0).unwrap();
+		assert!(quintic.curvature(0.0).abs() < 1e-12 && quintic.curvature(1.0).abs() < 1e-12);
+	}
+
+	#[test]
+	fn derivatives_and_split() {
+		let curve = Bezier::cubic([
+			Pos2::new(0.0, 0.0),
+			Pos2::new(1.0, 2.0),
+			Pos2::new(3.0, -1.0),
+			Pos2::new(4.0, 1.0),
+		]);
+		let h = 1e-6;
+		let t = 0.3;
+		let numerical =
+			(curve.evaluate_unchecked(t + h) - curve.evaluate_unchecked(t - h)) / (2.0 * h);
+		assert!((curve.derivative(t) - numerical).magnitude() < 1e-6);
+		let numerical = (curve.derivative(t + h) - curve.derivative(t - h)) / (2.0 * h);
+		assert!((curve.second_derivative(t) - numerical).magnitude() < 1e-6);
+
+		let (left, right) = curve.split(0.4).unwrap();
+		for i in 0..=10 {
+			let u = i as f64 / 10.0;
+			let p = curve.evaluate_unchecked(0.4 * u);
+			assert!((left.evaluate_unchecked(u) - p).magnitude() < 1e-12);
+			let p = curve.evaluate_unchecked(0.4 + 0.6 * u);
+			assert!((right.evaluate_unchecked(u) - p).magnitude() < 1e-12);
+		}
+	}
+
+	#[test]
+	fn arc_length() {
+		// straight line with unevenly spaced control points
+		let curve = Bezier::cubic([
+			Pos2::new(0.0, 0.0),
+			Pos2::new(0.5, 0.0),
+			Pos2::new(1.0, 0.0),
+			Pos2::new(6.0, 0.0),
+		]);
+		let arc_length = ArcLength::new(&curve, 32);
+		assert!((arc_length.length() - 6.0).abs() < 1e-9);
+		let t = arc_length.parameter(&curve, 2.5);
+		assert!((curve.evaluate_unchecked(t).x - 2.5).abs() < 1e-9);
+
+		let points = curve.get_points(0.5);
+		assert_eq!(points.len(), 13);
+		for w in points.windows(2) {
+			assert!(((w[1].pos - w[0].pos).magnitude() - 0.5).abs() < 1e-9);
+		}
+	}
+}
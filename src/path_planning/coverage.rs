@@ -0,0 +1,810 @@
+use super::{
+	curved_paths::{self, dubins::Dubins, PathSegmentType},
+	polygon::{area, contains, cross},
+};
+use crate::prelude::*;
+use core::f64::consts::{FRAC_PI_2, PI, TAU};
+
+// references:
+// https://doi.org/10.1023/A:1008958800904 (Choset, boustrophedon decomposition)
+// https://doi.org/10.1016/j.robot.2013.09.004 (Galceran & Carreras, survey)
+// --------
+// the region is rotated so the sweep lines are vertical and cut into slabs at
+// every vertex, each slab is a stack of trapezoids between polygon edges
+// trapezoids in neighbouring slabs join into one cell unless the free space
+// splits or merges there, so each cell is crossed by a sweep line exactly once
+// sweep lines are inset by half the spacing and consecutive lines are joined
+// by dubins paths, with a spacing under twice the turning radius these are
+// bulb turns that reach past the ends of the lines
+// cells are visited depth first and the route moves between them through the
+// middle of the boundaries they share, backtracking through cells that have
+// already been swept when the next cell doesn't touch the last one
+// the ends of lines either side of a turn are pulled back from walls that
+// slant across the sweep until the turn fits, every dubins path is checked
+// against the boundary and holes and turns that still don't fit fail
+// spiral rings take their corners as fillets and only use dubins paths to step
+// in a ring or past a corner too tight for the turning radius
+// --------
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CoverageError {
+	InvalidInput,
+	// a dubins path couldn't join two legs of the route inside the region
+	TurnNotFound,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CoveragePlanner {
+	// distance between sweep lines or spiral rings, i.e. the tool width
+	pub spacing: f64,
+	// heading of the sweep lines
+	pub angle: f64,
+	pub max_curve: f64,
+}
+
+impl Default for CoveragePlanner {
+	fn default() -> Self {
+		Self {
+			spacing: 0.5,
+			angle: 0.0,
+			max_curve: 1.0,
+		}
+	}
+}
+
+// start, segments normalised by 1 / max_curve and end of a piece of the route
+type Leg = (Ray, Vec<PathSegmentType>, Ray);
+// poses passed through on the way to a sweep line and its start and end
+type Sweep = (Vec<Ray>, [Pos2; 2]);
+
+// in the sweep frame, between x[0] and x[1]
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Trapezoid {
+	x: [f64; 2],
+	lower: [f64; 2],
+	upper: [f64; 2],
+}
+
+impl Trapezoid {
+	fn at(&self, x: f64) -> (f64, f64) {
+		let t = (x - self.x[0]) / (self.x[1] - self.x[0]);
+		(
+			self.lower[0] + (self.lower[1] - self.lower[0]) * t,
+			self.upper[0] + (self.upper[1] - self.upper[0]) * t,
+		)
+	}
+}
+
+impl CoveragePlanner {
+	// boustrophedon cells, counter clockwise, of the boundary minus the holes
+	pub fn cells(
+		&self,
+		boundary: &[Pos2],
+		holes: &[Vec<Pos2>],
+	) -> Result<Vec<Vec<Pos2>>, CoverageError> {
+		let (cells, _) = self.decompose(boundary, holes)?;
+		let to_world = Rotation2::new(self.angle - FRAC_PI_2);
+		Ok(cells
+			.iter()
+			.map(|cell| {
+				let first = cell[0];
+				let mut polygon = Vec::from([Pos2::new(first.x[0], first.lower[0])]);
+				polygon.extend(cell.iter().map(|t| Pos2::new(t.x[1], t.lower[1])));
+				polygon.extend(cell.iter().rev().map(|t| Pos2::new(t.x[1], t.upper[1])));
+				polygon.push(Pos2::new(first.x[0], first.upper[0]));
+				// sides that shrink to a point
+				polygon.dedup_by(|a, b| (*a - *b).magnitude() < 1e-9);
+				if polygon.len() > 1 && (polygon[0] - polygon[polygon.len() - 1]).magnitude() < 1e-9
+				{
+					polygon.pop();
+				}
+				polygon.iter().map(|&p| to_world * p).collect()
+			})
+			.collect())
+	}
+	// start and end of each sweep line in the order they're driven, ends are
+	// pulled back from slanted walls until the turns between lines fit
+	pub fn sweep_lines(
+		&self,
+		boundary: &[Pos2],
+		holes: &[Vec<Pos2>],
+	) -> Result<Vec<[Pos2; 2]>, CoverageError> {
+		Ok(self
+			.route(boundary, holes)?
+			.into_iter()
+			.map(|(_, line)| line)
+			.collect())
+	}
+	// each sweep line with the poses passed through to reach it from the last
+	// cell swept, empty within a cell
+	fn route(&self, boundary: &[Pos2], holes: &[Vec<Pos2>]) -> Result<Vec<Sweep>, CoverageError> {
+		if self.max_curve <= 0.0 {
+			return Err(CoverageError::InvalidInput);
+		}
+		let (cells, adjacent) = self.decompose(boundary, holes)?;
+
+		// depth first from the leftmost cell, recording the cells backtracked
+		// through since the last one swept
+		let mut order = Vec::from([(0, Vec::new())]);
+		let mut visited = Vec::from([false]).repeat(cells.len());
+		visited[0] = true;
+		let mut branch = Vec::from([0]);
+		let mut passed: Vec<usize> = Vec::new();
+		while let Some(&i) = branch.last() {
+			if let Some(&j) = adjacent[i].iter().find(|&&j| !visited[j]) {
+				visited[j] = true;
+				// straight across from the last cell swept when it touches this one
+				let swept = passed.first().copied().unwrap_or(i);
+				if adjacent[swept].contains(&j) {
+					passed = Vec::from([swept]);
+				}
+				passed.push(j);
+				order.push((j, core::mem::take(&mut passed)));
+				branch.push(j);
+			} else {
+				let done = branch.pop();
+				if passed.is_empty() {
+					passed.extend(done);
+				}
+				passed.extend(branch.last());
+			}
+		}
+
+		// middle of the boundary between touching cells heading from one to the other
+		let portal = |a: usize, b: usize| {
+			let (first, last) = (cells[a][0], cells[a][cells[a].len() - 1]);
+			let (next_first, next_last) = (cells[b][0], cells[b][cells[b].len() - 1]);
+			let (x, lower, upper, heading) = if (last.x[1] - next_first.x[0]).abs() < 1e-9 {
+				(
+					last.x[1],
+					last.lower[1].max(next_first.lower[0]),
+					last.upper[1].min(next_first.upper[0]),
+					0.0,
+				)
+			} else {
+				(
+					first.x[0],
+					first.lower[0].max(next_last.lower[1]),
+					first.upper[0].min(next_last.upper[1]),
+					PI,
+				)
+			};
+			Ray::new(Pos2::new(x, 0.5 * (lower + upper)), heading)
+		};
+
+		// pose at a point on a line in the world frame
+		let rotation = Rotation2::new(self.angle - FRAC_PI_2);
+		let to_world = |p: Pos2, line: [Pos2; 2]| {
+			let d = line[1] - line[0];
+			// lines in cells narrower than the spacing are single points
+			let heading = if d.magnitude() > 0.0 {
+				d.y.atan2(d.x)
+			} else {
+				FRAC_PI_2
+			};
+			Ray::new(rotation * p, heading + self.angle - FRAC_PI_2)
+		};
+
+		let half = 0.5 * self.spacing;
+		let mut route: Vec<Sweep> = Vec::new();
+		for (i, chain) in order {
+			let transit: Vec<Ray> = chain.windows(2).map(|w| portal(w[0], w[1])).collect();
+			let cell = &cells[i];
+			let (left, right) = (cell[0].x[0], cell[cell.len() - 1].x[1]);
+			// rotating into the sweep frame can leave the width a rounding error over
+			let n = ((right - left) / self.spacing - 1e-9).ceil().max(1.0) as usize;
+			let mut cell_lines: Vec<[Pos2; 2]> = (0..n)
+				.map(|k| {
+					let x = left + (k as f64 + 0.5) * (right - left) / n as f64;
+					let t = cell
+						.iter()
+						.find(|t| x <= t.x[1])
+						.unwrap_or(&cell[cell.len() - 1]);
+					let (lower, upper) = t.at(x);
+					let (lower, upper) = if upper - lower > self.spacing {
+						(lower + half, upper - half)
+					} else {
+						(0.5 * (lower + upper), 0.5 * (lower + upper))
+					};
+					[Pos2::new(x, lower), Pos2::new(x, upper)]
+				})
+				.collect();
+
+			// start from whichever corner is closest to where the last cell ended
+			let from = transit.last().map_or_else(
+				|| route.last().map_or(cell_lines[0][0], |l| l.1[1]),
+				|v| v.pos,
+			);
+			let corners = [
+				cell_lines[0][0],
+				cell_lines[0][1],
+				cell_lines[n - 1][0],
+				cell_lines[n - 1][1],
+			];
+			let nearest = (0..4)
+				.min_by(|&a, &b| {
+					float_cmp(
+						(corners[a] - from).magnitude(),
+						(corners[b] - from).magnitude(),
+					)
+				})
+				.unwrap_or(0);
+			if nearest >= 2 {
+				cell_lines.reverse();
+			}
+			for (k, line) in cell_lines.iter_mut().enumerate() {
+				if (k % 2 == 1) != (nearest % 2 == 1) {
+					line.swap(0, 1);
+				}
+			}
+			// pull back the ends either side of each turn, the further out first,
+			// until the turn stays inside the region
+			let step = 0.05 * self.spacing;
+			for k in 1..n {
+				loop {
+					let (end, next) = (cell_lines[k - 1], cell_lines[k]);
+					if self
+						.turn(
+							to_world(end[1], end),
+							to_world(next[0], next),
+							boundary,
+							holes,
+						)
+						.is_some()
+					{
+						break;
+					}
+					let (d_end, d_next) = (end[1] - end[0], next[1] - next[0]);
+					let outward = d_end.y.signum();
+					let (a, b) = (outward * end[1].y, outward * next[0].y);
+					let mut trimmed = false;
+					if a >= b && d_end.magnitude() > step {
+						cell_lines[k - 1][1] -= d_end.normalize() * step;
+						trimmed = true;
+					}
+					if b >= a && d_next.magnitude() > step {
+						cell_lines[k][0] += d_next.normalize() * step;
+						trimmed = true;
+					}
+					if !trimmed {
+						break;
+					}
+				}
+			}
+			let mut transit = Some(transit);
+			route.extend(
+				cell_lines
+					.into_iter()
+					.map(|line| (transit.take().unwrap_or_default(), line)),
+			);
+		}
+
+		Ok(route
+			.into_iter()
+			.map(|(transit, l)| {
+				(
+					transit
+						.iter()
+						.map(|v| Ray::new(rotation * v.pos, v.angle + self.angle - FRAC_PI_2))
+						.collect(),
+					[rotation * l[0], rotation * l[1]],
+				)
+			})
+			.collect())
+	}
+	// sweep lines joined by dubins turns, segments are normalised by 1 / max_curve
+	// as for curved_paths::get_points
+	pub fn lawnmower(
+		&self,
+		boundary: &[Pos2],
+		holes: &[Vec<Pos2>],
+	) -> Result<(Ray, Vec<PathSegmentType>), CoverageError> {
+		let mut legs: Vec<Leg> = Vec::new();
+		for (transit, l) in self.route(boundary, holes)? {
+			legs.extend(transit.into_iter().map(|v| (v, Vec::new(), v)));
+			let d = l[1] - l[0];
+			// lines in cells narrower than the spacing are single points
+			let heading = if d.magnitude() > 0.0 {
+				d.y.atan2(d.x)
+			} else {
+				self.angle
+			};
+			legs.push((
+				Ray::new(l[0], heading),
+				Vec::from([PathSegmentType::Straight(d.magnitude() * self.max_curve)]),
+				Ray::new(l[1], heading),
+			));
+		}
+		self.join(&legs, boundary, holes)
+	}
+	// rings inset by half the spacing then a spacing at a time, joined by dubins
+	// turns, the boundary has to be convex
+	pub fn spiral(&self, boundary: &[Pos2]) -> Result<(Ray, Vec<PathSegmentType>), CoverageError> {
+		if boundary.len() < 3 || self.spacing <= 0.0 || self.max_curve <= 0.0 {
+			return Err(CoverageError::InvalidInput);
+		}
+		let mut polygon = Vec::from(boundary);
+		if area(&polygon) < 0.0 {
+			polygon.reverse();
+		}
+		let n = polygon.len();
+		if (0..n).any(|i| {
+			let (a, b, c) = (polygon[i], polygon[(i + 1) % n], polygon[(i + 2) % n]);
+			cross(b - a, c - b) < -1e-9
+		}) {
+			return Err(CoverageError::InvalidInput);
+		}
+
+		let radius = 1.0 / self.max_curve;
+		let mut legs: Vec<Leg> = Vec::new();
+		let mut position = polygon[0];
+		for k in 0.. {
+			let mut ring = inset(&polygon, (k as f64 + 0.5) * self.spacing);
+			if ring.len() < 3 {
+				break;
+			}
+			let first = (0..ring.len())
+				.min_by(|&a, &b| {
+					float_cmp(
+						(ring[a] - position).magnitude(),
+						(ring[b] - position).magnitude(),
+					)
+				})
+				.unwrap_or(0);
+			ring.rotate_left(first);
+
+			let m = ring.len();
+			let sides: Vec<Vec2> = (0..m).map(|i| ring[(i + 1) % m] - ring[i]).collect();
+			let heading = |i: usize| sides[i].y.atan2(sides[i].x);
+			// turn into each side and how much of the sides either side it uses
+			let turns: Vec<f64> = (0..m)
+				.map(|i| {
+					let before = sides[(i + m - 1) % m];
+					cross(before, sides[i]).atan2(before.dot(&sides[i]))
+				})
+				.collect();
+			let cut: Vec<f64> = turns.iter().map(|t| radius * (0.5 * t).tan()).collect();
+			// corners too tight for a fillet end the leg and get a dubins turn
+			let fits = |i: usize| {
+				2.0 * cut[i] <= sides[i].magnitude().min(sides[(i + m - 1) % m].magnitude())
+			};
+			let trim = |i: usize, side: usize| cut[i].min(0.5 * sides[side].magnitude());
+
+			let start = trim(0, 0);
+			let mut leg = (
+				Ray::new(ring[0] + sides[0].normalize() * start, heading(0)),
+				Vec::new(),
+				Ray::ZERO,
+			);
+			for i in 0..m {
+				let (start, end) = (trim(i, i), trim((i + 1) % m, i));
+				let length = sides[i].magnitude() - start - end;
+				leg.1
+					.push(PathSegmentType::Straight(length * self.max_curve));
+				let end_point = ring[(i + 1) % m] - sides[i].normalize() * end;
+				if i + 1 < m && fits(i + 1) {
+					leg.1.push(PathSegmentType::Left(turns[i + 1]));
+				} else {
+					leg.2 = Ray::new(end_point, heading(i));
+					let next = (i + 1) % m;
+					let next_start = ring[next] + sides[next].normalize() * trim(next, next);
+					legs.push(core::mem::replace(
+						&mut leg,
+						(Ray::new(next_start, heading(next)), Vec::new(), Ray::ZERO),
+					));
+				}
+			}
+			position = ring[0];
+		}
+		if legs.is_empty() {
+			// narrower than the spacing
+			return Err(CoverageError::InvalidInput);
+		}
+		self.join(&legs, boundary, &[])
+	}
+	// cells as trapezoids in the sweep frame and which cells touch
+	#[allow(clippy::type_complexity)]
+	fn decompose(
+		&self,
+		boundary: &[Pos2],
+		holes: &[Vec<Pos2>],
+	) -> Result<(Vec<Vec<Trapezoid>>, Vec<Vec<usize>>), CoverageError> {
+		if boundary.len() < 3 || holes.iter().any(|h| h.len() < 3) || self.spacing <= 0.0 {
+			return Err(CoverageError::InvalidInput);
+		}
+		let to_sweep = Rotation2::new(FRAC_PI_2 - self.angle);
+		let polygons: Vec<Vec<Pos2>> = core::iter::once(boundary)
+			.chain(holes.iter().map(Vec::as_slice))
+			.map(|p| p.iter().map(|&v| to_sweep * v).collect())
+			.collect();
+		let edges: Vec<(Pos2, Pos2)> = polygons
+			.iter()
+			.flat_map(|p| p.iter().copied().zip(p.iter().copied().cycle().skip(1)))
+			.collect();
+		let mut xs: Vec<f64> = polygons.iter().flatten().map(|p| p.x).collect();
+		xs.sort_by(|a, b| float_cmp(*a, *b));
+		xs.dedup_by(|a, b| *a - *b < 1e-9);
+
+		let mut cells: Vec<Vec<Trapezoid>> = Vec::new();
+		let mut adjacent: Vec<Vec<usize>> = Vec::new();
+		// cell of each trapezoid in the last slab
+		let mut last: Vec<(Trapezoid, usize)> = Vec::new();
+		for w in xs.windows(2) {
+			let (xa, xb) = (w[0], w[1]);
+			let middle = 0.5 * (xa + xb);
+			let mut crossings: Vec<[f64; 3]> = edges
+				.iter()
+				.filter(|(a, b)| (a.x < middle) != (b.x < middle))
+				.map(|&(a, b)| {
+					let y = |x: f64| a.y + (b.y - a.y) * (x - a.x) / (b.x - a.x);
+					[y(xa), y(middle), y(xb)]
+				})
+				.collect();
+			if !crossings.len().is_multiple_of(2) {
+				return Err(CoverageError::InvalidInput);
+			}
+			crossings.sort_by(|a, b| float_cmp(a[1], b[1]));
+			let slab: Vec<Trapezoid> = crossings
+				.chunks(2)
+				.map(|c| Trapezoid {
+					x: [xa, xb],
+					lower: [c[0][0], c[0][2]],
+					upper: [c[1][0], c[1][2]],
+				})
+				.collect();
+
+			let touches = |a: &Trapezoid, b: &Trapezoid| {
+				a.upper[1].min(b.upper[0]) - a.lower[1].max(b.lower[0]) > 1e-9
+			};
+			let mut next = Vec::new();
+			for t in slab.iter().copied() {
+				let left: Vec<usize> = (0..last.len())
+					.filter(|&i| touches(&last[i].0, &t))
+					.collect();
+				// carry on the cell when the free space doesn't split or merge here
+				let continues = left.len() == 1
+					&& slab.iter().filter(|n| touches(&last[left[0]].0, n)).count() == 1;
+				let cell = if continues {
+					last[left[0]].1
+				} else {
+					cells.push(Vec::new());
+					adjacent.push(Vec::new());
+					let cell = cells.len() - 1;
+					for &i in &left {
+						let other = last[i].1;
+						adjacent[other].push(cell);
+						adjacent[cell].push(other);
+					}
+					cell
+				};
+				cells[cell].push(t);
+				next.push((t, cell));
+			}
+			last = next;
+		}
+		if cells.is_empty() {
+			return Err(CoverageError::InvalidInput);
+		}
+		Ok((cells, adjacent))
+	}
+	fn join(
+		&self,
+		legs: &[Leg],
+		boundary: &[Pos2],
+		holes: &[Vec<Pos2>],
+	) -> Result<(Ray, Vec<PathSegmentType>), CoverageError> {
+		let mut segments = Vec::new();
+		for (i, (_, leg, end)) in legs.iter().enumerate() {
+			segments.extend(
+				leg.iter()
+					.filter(|s| !matches!(s, PathSegmentType::Straight(d) if *d < 1e-12)),
+			);
+			let Some(&(next, _, _)) = legs.get(i + 1) else {
+				break;
+			};
+			if (next.pos - end.pos).magnitude() < 1e-9
+				&& map_angle(next.angle - end.angle).abs() < 1e-9
+			{
+				continue;
+			}
+			let turn = self
+				.turn(*end, next, boundary, holes)
+				.ok_or(CoverageError::TurnNotFound)?;
+			segments.extend(turn);
+		}
+		Ok((legs[0].0, segments))
+	}
+	// dubins path between two poses, None if it leaves the region
+	fn turn(
+		&self,
+		from: Ray,
+		to: Ray,
+		boundary: &[Pos2],
+		holes: &[Vec<Pos2>],
+	) -> Option<Vec<PathSegmentType>> {
+		let radius = 1.0 / self.max_curve;
+		let turn: Vec<PathSegmentType> = Dubins::new(from, to, self.max_curve)
+			.ok()?
+			.path
+			.segments()
+			.into_iter()
+			.filter(|segment| match *segment {
+				// rounding can turn an arc that isn't needed into a full circle
+				PathSegmentType::Left(a) | PathSegmentType::Right(a) => {
+					(1e-9..=TAU - 1e-9).contains(&a)
+				}
+				PathSegmentType::Straight(d) => d >= 1e-12,
+				_ => true,
+			})
+			.collect();
+		(turn.is_empty()
+			|| curved_paths::get_points(from, &turn, radius, 0.1 * radius.min(self.spacing))
+				.iter()
+				.all(|v| is_free(boundary, holes, v.0.pos)))
+		.then_some(turn)
+	}
+}
+
+// inside the boundary and outside the holes, allowing for rounding on their edges
+fn is_free(boundary: &[Pos2], holes: &[Vec<Pos2>], point: Pos2) -> bool {
+	let on_edge = |polygon: &[Pos2]| {
+		let n = polygon.len();
+		(0..n).any(|i| {
+			let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+			let t = ((point - a).dot(&(b - a)) / (b - a).magnitude_squared()).clamp(0.0, 1.0);
+			(a + (b - a) * t - point).magnitude() < 1e-6
+		})
+	};
+	(contains(boundary, point) || on_edge(boundary))
+		&& holes.iter().all(|h| !contains(h, point) || on_edge(h))
+}
+
+// a convex counter clockwise polygon with every edge moved in by the distance,
+// clipping the polygon by each moved edge in turn
+fn inset(polygon: &[Pos2], distance: f64) -> Vec<Pos2> {
+	let n = polygon.len();
+	let mut ring = Vec::from(polygon);
+	for i in 0..n {
+		let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+		let edge = (b - a).normalize();
+		let normal = Vec2::new(-edge.y, edge.x);
+		let side = |p: Pos2| normal.dot(&(p - a)) - distance;
+
+		let mut clipped = Vec::new();
+		for (j, &p) in ring.iter().enumerate() {
+			let q = ring[(j + 1) % ring.len()];
+			let (sp, sq) = (side(p), side(q));
+			if sp >= 0.0 {
+				clipped.push(p);
+			}
+			if (sp >= 0.0) != (sq >= 0.0) {
+				clipped.push(p + (q - p) * (sp / (sp - sq)));
+			}
+		}
+		clipped.dedup_by(|p, q| (*p - *q).magnitude() < 1e-9);
+		if clipped.len() > 1 && (clipped[0] - clipped[clipped.len() - 1]).magnitude() < 1e-9 {
+			clipped.pop();
+		}
+		ring = clipped;
+		if ring.len() < 3 {
+			return Vec::new();
+		}
+	}
+	ring
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::path_planning::curved_paths::get_points;
+	use core::f64::consts::FRAC_PI_4;
+
+	fn square(min: f64, max: f64) -> Vec<Pos2> {
+		Vec::from([
+			Pos2::new(min, min),
+			Pos2::new(max, min),
+			Pos2::new(max, max),
+			Pos2::new(min, max),
+		])
+	}
+
+	fn distance_to_segment(p: Pos2, a: Pos2, b: Pos2) -> f64 {
+		let ab = b - a;
+		let t = if ab.magnitude() > 0.0 {
+			((p - a).dot(&ab) / ab.magnitude_squared()).clamp(0.0, 1.0)
+		} else {
+			0.0
+		};
+		(p - (a + ab * t)).magnitude()
+	}
+
+	// heading is continuous and every leg is driven
+	fn check_route(start: Ray, segments: &[PathSegmentType], radius: f64, stops: &[Pos2]) {
+		let points = get_points(start, segments, radius, 0.05);
+		for w in points.windows(2) {
+			let turn = (w[1].0.angle - w[0].0.angle).abs();
+			assert!(turn <= 0.1 / radius + 1e-9);
+			assert!((w[1].0.pos - w[0].0.pos).magnitude() <= 0.1 + 1e-9);
+		}
+		for &stop in stops {
+			assert!(points.iter().any(|p| (p.0.pos - stop).magnitude() < 1e-6));
+		}
+	}
+
+	#[test]
+	fn boustrophedon_lawnmower() {
+		let boundary = square(0.0, 10.0);
+		let holes = [square(4.0, 6.0)];
+		let planner = CoveragePlanner {
+			spacing: 1.0,
+			max_curve: 2.5,
+			..Default::default()
+		};
+
+		// split either side of the hole, above and below it
+		let cells = planner.cells(&boundary, &holes).unwrap();
+		assert_eq!(cells.len(), 4);
+		assert!(cells.iter().all(|c| area(c) > 0.0));
+		let total: f64 = cells.iter().map(|c| area(c)).sum();
+		assert!((total - 96.0).abs() < 1e-9);
+
+		let lines = planner.sweep_lines(&boundary, &holes).unwrap();
+		for l in &lines {
+			// along the sweep angle, outside the hole
+			assert!((l[1].y - l[0].y).abs() < 1e-9);
+			assert!(!((4.0..=6.0).contains(&l[0].y) && (3.5..6.5).contains(&l[0].x)));
+		}
+		// everything at least half a spacing from the walls is swept
+		for i in 0..=36 {
+			for j in 0..=36 {
+				let p = Pos2::new(0.5 + i as f64 * 0.25, 0.5 + j as f64 * 0.25);
+				if (3.5..=6.5).contains(&p.x) && (3.5..=6.5).contains(&p.y) {
+					continue;
+				}
+				let d = lines
+					.iter()
+					.map(|l| distance_to_segment(p, l[0], l[1]))
+					.fold(f64::INFINITY, f64::min);
+				assert!(d <= 0.5 + 1e-9);
+			}
+		}
+
+		// bulb turns wider than the spacing don't fit past the outer lines
+		let wide = CoveragePlanner {
+			max_curve: 1.0,
+			..planner
+		};
+		assert_eq!(
+			wide.lawnmower(&boundary, &holes).unwrap_err(),
+			CoverageError::TurnNotFound
+		);
+		let (start, segments) = planner.lawnmower(&boundary, &holes).unwrap();
+		let stops: Vec<Pos2> = lines.iter().flatten().copied().collect();
+		check_route(start, &segments, 0.4, &stops);
+		assert!(get_points(start, &segments, 0.4, 0.05)
+			.iter()
+			.all(|p| is_free(&boundary, &holes, p.0.pos)));
+
+		// tilted sweeps
+		let tilted = CoveragePlanner {
+			angle: FRAC_PI_4,
+			..planner
+		};
+		for l in tilted.sweep_lines(&boundary, &holes).unwrap() {
+			let d = l[1] - l[0];
+			assert!(d.magnitude() < 1e-9 || cross(d, Vec2::new(1.0, 1.0)).abs() < 1e-9);
+		}
+		assert_eq!(
+			planner.cells(&boundary[..2], &[]).unwrap_err(),
+			CoverageError::InvalidInput
+		);
+	}
+
+	#[test]
+	fn backtracks_between_holes() {
+		let boundary = Vec::from([
+			Pos2::new(0.0, 0.0),
+			Pos2::new(20.0, 0.0),
+			Pos2::new(20.0, 10.0),
+			Pos2::new(0.0, 10.0),
+		]);
+		let holes = [
+			Vec::from([
+				Pos2::new(4.0, 4.0),
+				Pos2::new(6.0, 4.0),
+				Pos2::new(6.0, 6.0),
+				Pos2::new(4.0, 6.0),
+			]),
+			Vec::from([
+				Pos2::new(14.0, 4.0),
+				Pos2::new(16.0, 4.0),
+				Pos2::new(16.0, 6.0),
+				Pos2::new(14.0, 6.0),
+			]),
+		];
+		let planner = CoveragePlanner {
+			spacing: 1.0,
+			max_curve: 2.5,
+			..Default::default()
+		};
+		assert_eq!(planner.cells(&boundary, &holes).unwrap().len(), 5);
+
+		// one of the cells between the holes is only reached back through the
+		// cell they both open onto
+		let route = planner.route(&boundary, &holes).unwrap();
+		assert!(route.iter().any(|(transit, _)| transit.len() == 2));
+		for (transit, _) in &route {
+			assert!(transit.iter().all(|v| is_free(&boundary, &holes, v.pos)));
+		}
+
+		let (start, segments) = planner.lawnmower(&boundary, &holes).unwrap();
+		let stops: Vec<Pos2> = route.iter().flat_map(|(_, l)| *l).collect();
+		check_route(start, &segments, 0.4, &stops);
+		assert!(get_points(start, &segments, 0.4, 0.05)
+			.iter()
+			.all(|p| is_free(&boundary, &holes, p.0.pos)));
+	}
+
+	#[test]
+	fn slanted_edges() {
+		let boundary = [
+			Pos2::new(0.0, 0.0),
+			Pos2::new(10.0, 0.0),
+			Pos2::new(5.0, 8.0),
+		];
+		let planner = CoveragePlanner {
+			spacing: 1.0,
+			angle: 0.3,
+			max_curve: 4.0,
+		};
+		let lines = planner.sweep_lines(&boundary, &[]).unwrap();
+		let (start, segments) = planner.lawnmower(&boundary, &[]).unwrap();
+		let stops: Vec<Pos2> = lines.iter().flatten().copied().collect();
+		check_route(start, &segments, 0.25, &stops);
+		assert!(get_points(start, &segments, 0.25, 0.05)
+			.iter()
+			.all(|p| is_free(&boundary, &[], p.0.pos)));
+
+		// ends are only pulled back as far as the turns need
+		let wall = |p: Pos2| {
+			(0..3)
+				.map(|i| distance_to_segment(p, boundary[i], boundary[(i + 1) % 3]))
+				.fold(f64::INFINITY, f64::min)
+		};
+		assert!(lines.iter().flatten().all(|&p| wall(p) < 1.0));
+	}
+
+	#[test]
+	fn spiral() {
+		let planner = CoveragePlanner {
+			spacing: 1.0,
+			max_curve: 2.0,
+			..Default::default()
+		};
+		let boundary = square(0.0, 6.0);
+		let (start, segments) = planner.spiral(&boundary).unwrap();
+		// rings half a spacing, one and a half and two and a half in
+		assert_eq!(start.pos, Pos2::new(1.0, 0.5));
+		let stops = [
+			Pos2::new(5.0, 0.5),
+			Pos2::new(4.0, 1.5),
+			Pos2::new(3.0, 2.5),
+		];
+		check_route(start, &segments, 0.5, &stops);
+
+		let points = get_points(start, &segments, 0.5, 0.05);
+		assert!(points.iter().all(|p| (0.0..=6.0).contains(&p.0.pos.x)));
+		assert!(points.iter().all(|p| (0.0..=6.0).contains(&p.0.pos.y)));
+
+		let concave = [
+			Pos2::new(0.0, 0.0),
+			Pos2::new(4.0, 0.0),
+			Pos2::new(2.0, 1.0),
+			Pos2::new(4.0, 4.0),
+		];
+		assert_eq!(
+			planner.spiral(&concave).unwrap_err(),
+			CoverageError::InvalidInput
+		);
+	}
+}
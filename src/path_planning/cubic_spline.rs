@@ -0,0 +1,363 @@
+use super::parametric_curve::{ArcLength, ParametricCurve};
+use crate::prelude::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SplineError {
+	NotEnoughPoints,
+	DuplicatePoints,
+	OutOfRange,
+	// x and y have different lengths
+	LengthMismatch,
+}
+
+// natural cubic spline, each segment i is
+// y = a[i] + b[i] dx + c[i] dx^2 + d[i] dx^3 where dx = x - x[i]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubicSpline1D {
+	x: Vec<f64>,
+	a: Vec<f64>,
+	b: Vec<f64>,
+	c: Vec<f64>,
+	d: Vec<f64>,
+}
+
+impl CubicSpline1D {
+	// x must be strictly increasing
+	pub fn new(x: &[f64], y: &[f64]) -> Result<Self, SplineError> {
+		let n = x.len();
+		if y.len() != n {
+			return Err(SplineError::LengthMismatch);
+		}
+		if n < 2 {
+			return Err(SplineError::NotEnoughPoints);
+		}
+		let h: Vec<f64> = x.windows(2).map(|w| w[1] - w[0]).collect();
+		if h.iter().any(|&v| v <= 0.0) {
+			return Err(SplineError::DuplicatePoints);
+		}
+
+		// solve the tridiagonal system for c with the thomas algorithm
+		// natural end conditions c[0] = c[n - 1] = 0
+		let mut diag = Vec::with_capacity(n);
+		let mut rhs = Vec::with_capacity(n);
+		diag.push(1.0);
+		rhs.push(0.0);
+		for i in 1..n - 1 {
+			diag.push(2.0 * (h[i - 1] + h[i]));
+			rhs.push(3.0 * ((y[i + 1] - y[i]) / h[i] - (y[i] - y[i - 1]) / h[i - 1]));
+		}
+		diag.push(1.0);
+		rhs.push(0.0);
+
+		// lower[i] multiplies c[i - 1] and upper[i] multiplies c[i + 1] in row i
+		let lower = |i: usize| if i == n - 1 { 0.0 } else { h[i - 1] };
+		let upper = |i: usize| if i == 0 { 0.0 } else { h[i] };
+
+		for i in 1..n {
+			let m = lower(i) / diag[i - 1];
+			diag[i] -= m * upper(i - 1);
+			rhs[i] -= m * rhs[i - 1];
+		}
+		let mut c = Vec::new();
+		c.resize(n, 0.0);
+		c[n - 1] = rhs[n - 1] / diag[n - 1];
+		for i in (0..n - 1).rev() {
+			c[i] = (rhs[i] - upper(i) * c[i + 1]) / diag[i];
+		}
+
+		let mut b = Vec::with_capacity(n - 1);
+		let mut d = Vec::with_capacity(n - 1);
+		for i in 0..n - 1 {
+			b.push((y[i + 1] - y[i]) / h[i] - h[i] * (c[i + 1] + 2.0 * c[i]) / 3.0);
+			d.push((c[i + 1] - c[i]) / (3.0 * h[i]));
+		}
+
+		Ok(Self {
+			x: x.to_vec(),
+			a: y.to_vec(),
+			b,
+			c,
+			d,
+		})
+	}
+	fn segment(&self, x: f64) -> (usize, f64) {
+		let i = self
+			.x
+			.partition_point(|&v| v <= x)
+			.saturating_sub(1)
+			.min(self.x.len() - 2);
+		(i, x - self.x[i])
+	}
+	// values outside of the knots are extrapolated from the end segments
+	#[must_use]
+	pub fn evaluate_unchecked(&self, x: f64) -> f64 {
+		let (i, dx) = self.segment(x);
+		self.a[i] + self.b[i] * dx + self.c[i] * dx * dx + self.d[i] * dx * dx * dx
+	}
+	#[must_use]
+	pub fn derivative(&self, x: f64) -> f64 {
+		let (i, dx) = self.segment(x);
+		self.b[i] + 2.0 * self.c[i] * dx + 3.0 * self.d[i] * dx * dx
+	}
+	#[must_use]
+	pub fn second_derivative(&self, x: f64) -> f64 {
+		let (i, dx) = self.segment(x);
+		2.0 * self.c[i] + 6.0 * self.d[i] * dx
+	}
+	#[must_use]
+	pub fn third_derivative(&self, x: f64) -> f64 {
+		let (i, _) = self.segment(x);
+		6.0 * self.d[i]
+	}
+}
+
+// number of intervals of the chord parameter between waypoints used for arc length
+const ARC_LENGTH_INTERVALS: usize = 16;
+
+// splines of x and y over the cumulative straight line distance between
+// waypoints, which is close to but not exactly arc length
+#[derive(Debug, Clone, PartialEq)]
+struct ChordSpline {
+	x: CubicSpline1D,
+	y: CubicSpline1D,
+}
+
+impl ParametricCurve for ChordSpline {
+	fn domain(&self) -> (f64, f64) {
+		(self.x.x[0], self.x.x[self.x.x.len() - 1])
+	}
+	fn evaluate_unchecked(&self, t: f64) -> Pos2 {
+		Pos2::new(self.x.evaluate_unchecked(t), self.y.evaluate_unchecked(t))
+	}
+	fn derivative(&self, t: f64) -> Vec2 {
+		Vec2::new(self.x.derivative(t), self.y.derivative(t))
+	}
+	fn second_derivative(&self, t: f64) -> Vec2 {
+		Vec2::new(self.x.second_derivative(t), self.y.second_derivative(t))
+	}
+}
+
+// interpolates waypoints parameterised by arc length s
+// the splines are fitted over the straight line distance between waypoints
+// then reparameterised with a table of arc lengths
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubicSpline2D {
+	s: Vec<f64>,
+	curve: ChordSpline,
+	arc_length: ArcLength,
+}
+
+impl CubicSpline2D {
+	pub fn new(waypoints: &[Pos2]) -> Result<Self, SplineError> {
+		if waypoints.len() < 2 {
+			return Err(SplineError::NotEnoughPoints);
+		}
+		let mut t = Vec::with_capacity(waypoints.len());
+		t.push(0.0);
+		for w in waypoints.windows(2) {
+			t.push(t[t.len() - 1] + (w[1] - w[0]).magnitude());
+		}
+		let x: Vec<f64> = waypoints.iter().map(|p| p.x).collect();
+		let y: Vec<f64> = waypoints.iter().map(|p| p.y).collect();
+
+		let curve = ChordSpline {
+			x: CubicSpline1D::new(&t, &x)?,
+			y: CubicSpline1D::new(&t, &y)?,
+		};
+		// the third derivative jumps at the waypoints so they are in the table
+		let mut parameters = Vec::with_capacity(ARC_LENGTH_INTERVALS * t.len());
+		for w in t.windows(2) {
+			parameters.extend(
+				(0..ARC_LENGTH_INTERVALS)
+					.map(|i| w[0] + (w[1] - w[0]) * i as f64 / ARC_LENGTH_INTERVALS as f64),
+			);
+		}
+		parameters.push(t[t.len() - 1]);
+		let arc_length = ArcLength::from_parameters(&curve, parameters);
+		Ok(Self {
+			s: t.iter().map(|&t| arc_length.distance(&curve, t)).collect(),
+			curve,
+			arc_length,
+		})
+	}
+	#[must_use]
+	pub fn length(&self) -> f64 {
+		self.arc_length.length()
+	}
+	// arc length of each waypoint
+	#[must_use]
+	pub fn knots(&self) -> &[f64] {
+		&self.s
+	}
+	// parameter of the chord splines at s
+	// beyond the ends the splines are extrapolated at the speed at the end
+	fn parameter(&self, s: f64) -> f64 {
+		let (start, end) = self.curve.domain();
+		if s < 0.0 {
+			start + s / self.curve.derivative(start).magnitude()
+		} else if s > self.length() {
+			end + (s - self.length()) / self.curve.derivative(end).magnitude()
+		} else {
+			self.arc_length.parameter(&self.curve, s)
+		}
+	}
+	pub fn evaluate(&self, s: f64) -> Result<Pos2, SplineError> {
+		if s < 0.0 || s > self.length() {
+			return Err(SplineError::OutOfRange);
+		}
+		Ok(self.evaluate_unchecked(s))
+	}
+	#[must_use]
+	pub fn evaluate_unchecked(&self, s: f64) -> Pos2 {
+		self.curve.evaluate_unchecked(self.parameter(s))
+	}
+	pub fn ray(&self, s: f64) -> Result<Ray, SplineError> {
+		Ok(Ray::new(self.evaluate(s)?, self.heading(s)))
+	}
+	// derivative with respect to s, the unit tangent
+	#[must_use]
+	pub fn derivative(&self, s: f64) -> Vec2 {
+		self.curve.derivative(self.parameter(s)).normalize()
+	}
+	// the component of the second derivative with respect to the chord
+	// parameter along the tangent only changes the speed
+	#[must_use]
+	pub fn second_derivative(&self, s: f64) -> Vec2 {
+		let t = self.parameter(s);
+		let (d, dd) = (self.curve.derivative(t), self.curve.second_derivative(t));
+		let v_sq = d.magnitude_squared();
+		(dd - d * (dd.dot(&d) / v_sq)) / v_sq
+	}
+	#[must_use]
+	pub fn heading(&self, s: f64) -> f64 {
+		self.curve.heading(self.parameter(s))
+	}
+	#[must_use]
+	pub fn curvature(&self, s: f64) -> f64 {
+		self.curve.curvature(self.parameter(s))
+	}
+	// derivative of curvature with respect to s
+	#[must_use]
+	pub fn curvature_derivative(&self, s: f64) -> f64 {
+		let t = self.parameter(s);
+		let (c, x, y) = (&self.curve, &self.curve.x, &self.curve.y);
+		let (d, dd) = (c.derivative(t), c.second_derivative(t));
+		let ddd = Vec2::new(x.third_derivative(t), y.third_derivative(t));
+		let v_sq = d.magnitude_squared();
+		let cross = d.perp(&dd);
+
+		// with respect to t then divided by the speed
+		(d.perp(&ddd) * v_sq - 3.0 * cross * d.dot(&dd)) / (v_sq * v_sq * v_sq)
+	}
+	#[must_use]
+	pub fn get_points(&self, step_size: f64) -> Vec<Ray> {
+		let mut points = Vec::new();
+		if step_size <= 0.0 {
+			return points;
+		}
+		let mut s = 0.0;
+		while s < self.length() {
+			points.push(Ray::new(self.evaluate_unchecked(s), self.heading(s)));
+			s += step_size;
+		}
+		let end = self.length();
+		points.push(Ray::new(self.evaluate_unchecked(end), self.heading(end)));
+		points
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use core::f64::consts::PI;
+
+	#[test]
+	fn interpolates_waypoints() {
+		let waypoints = [
+			Pos2::new(-2.5, 0.7),
+			Pos2::new(0.0, -6.0),
+			Pos2::new(2.5, 5.0),
+			Pos2::new(5.0, 6.5),
+			Pos2::new(7.5, 0.0),
+			Pos2::new(3.0, 5.0),
+			Pos2::new(-1.0, -2.0),
+		];
+		let spline = CubicSpline2D::new(&waypoints).unwrap();
+		for (&s, p) in spline.knots().iter().zip(waypoints) {
+			assert!((spline.evaluate(s).unwrap() - p).magnitude() < 1e-9);
+		}
+		assert!(spline.evaluate(spline.length() + 0.1).is_err());
+		assert!(spline.evaluate(-0.1).is_err());
+	}
+
+	#[test]
+	fn unit_speed() {
+		let waypoints = [
+			Pos2::new(-2.5, 0.7),
+			Pos2::new(0.0, -6.0),
+			Pos2::new(2.5, 5.0),
+			Pos2::new(5.0, 6.5),
+			Pos2::new(7.5, 0.0),
+			Pos2::new(3.0, 5.0),
+			Pos2::new(-1.0, -2.0),
+		];
+		let spline = CubicSpline2D::new(&waypoints).unwrap();
+		let h = 1e-5;
+		let steps = 20_000;
+		let mut integrated = 0.0;
+		for i in 0..steps {
+			let s = spline.length() * f64::from(i) / f64::from(steps);
+			let next = spline.length() * f64::from(i + 1) / f64::from(steps);
+			integrated +=
+				(spline.evaluate_unchecked(next) - spline.evaluate_unchecked(s)).magnitude();
+			if i % 100 == 50 {
+				let numerical = (spline.evaluate_unchecked(s + h)
+					- spline.evaluate_unchecked(s - h))
+					/ (2.0 * h);
+				assert!((numerical.magnitude() - 1.0).abs() < 1e-5);
+				assert!((spline.derivative(s) - numerical).magnitude() < 1e-4);
+			}
+		}
+		assert!((spline.length() - integrated).abs() < 1e-4);
+	}
+
+	#[test]
+	fn circle_curvature() {
+		let r = 5.0;
+		let waypoints: Vec<Pos2> = (0..=36)
+			.map(|i| {
+				let theta = f64::from(i) * PI / 36.0;
+				Pos2::new(r * theta.cos(), r * theta.sin())
+			})
+			.collect();
+		let spline = CubicSpline2D::new(&waypoints).unwrap();
+		// chord length would be 5e-3 short
+		assert!((spline.length() - PI * r).abs() < 1e-3);
+		// between knots since the third derivative jumps at them
+		let s = 0.45 * spline.length();
+
+		assert!((spline.curvature(s) - 1.0 / r).abs() < 1e-3);
+		let h = 1e-5;
+		let numerical = (spline.curvature(s + h) - spline.curvature(s - h)) / (2.0 * h);
+		assert!((spline.curvature_derivative(s) - numerical).abs() < 1e-5);
+		// moving counter-clockwise so heading is tangent to the circle
+		let ray = spline.ray(s).unwrap();
+		assert!((ray.angle - 0.95 * PI).abs() < 1e-3);
+	}
+
+	#[test]
+	fn invalid_waypoints() {
+		assert_eq!(
+			CubicSpline2D::new(&[Pos2::new(0.0, 0.0)]),
+			Err(SplineError::NotEnoughPoints)
+		);
+		assert_eq!(
+			CubicSpline2D::new(&[Pos2::new(0.0, 0.0), Pos2::new(0.0, 0.0)]),
+			Err(SplineError::DuplicatePoints)
+		);
+		assert_eq!(
+			CubicSpline1D::new(&[0.0, 1.0, 2.0], &[0.0, 1.0]),
+			Err(SplineError::LengthMismatch)
+		);
+	}
+}
@@ -0,0 +1,381 @@
+use super::PathSegmentType;
+use crate::{path_planning::parametric_curve::GAUSS_LEGENDRE, prelude::*};
+use core::f64::consts::{FRAC_PI_2, PI};
+use nalgebra::Complex;
+
+// references:
+// Numerical Recipes (3rd edition) 6.8.1
+// https://en.wikipedia.org/wiki/Euler_spiral
+
+// below this the fresnel integrals are evaluated with their power series
+// above it with a continued fraction
+const SERIES_LIMIT: f64 = 1.5;
+
+// fresnel arguments above this lose too much precision when subtracted
+const MAX_FRESNEL_ARGUMENT: f64 = 50.0;
+// change in heading over each piece when integrating numerically
+const MAX_PIECE_ANGLE: f64 = 0.25;
+
+// fresnel integrals C(x) and S(x) using the normalised definition
+// C(x) = integral of cos(pi t^2 / 2) from 0 to x
+// S(x) = integral of sin(pi t^2 / 2) from 0 to x
+#[must_use]
+pub fn fresnel_integrals(x: f64) -> (f64, f64) {
+	let ax = x.abs();
+	let (c, s) = if ax < f64::MIN_POSITIVE.sqrt() {
+		(ax, 0.0)
+	} else if ax <= SERIES_LIMIT {
+		fresnel_series(ax)
+	} else {
+		fresnel_continued_fraction(ax)
+	};
+	if x < 0.0 {
+		(-c, -s)
+	} else {
+		(c, s)
+	}
+}
+
+fn fresnel_series(x: f64) -> (f64, f64) {
+	let fact = FRAC_PI_2 * x * x;
+	let (mut sum_c, mut sum_s) = (x, 0.0);
+	let mut term = x;
+	let mut sign = 1.0;
+	// terms alternate between S and C
+	for k in 1..100 {
+		term *= fact / k as f64;
+		let n = (2 * k + 1) as f64;
+		if k % 2 == 1 {
+			sum_s += sign * term / n;
+			sign = -sign;
+		} else {
+			sum_c += sign * term / n;
+		}
+		if term / n < f64::EPSILON * sum_c.abs().max(sum_s.abs()) {
+			break;
+		}
+	}
+	(sum_c, sum_s)
+}
+
+// modified lentz's method for the continued fraction of the complementary error function
+fn fresnel_continued_fraction(x: f64) -> (f64, f64) {
+	let tiny = 1e-300;
+	let pix2 = PI * x * x;
+	let mut b = Complex::new(1.0, -pix2);
+	let mut cc = Complex::new(1.0 / tiny, 0.0);
+	let mut d = Complex::new(1.0, 0.0) / b;
+	let mut h = d;
+	let mut n = -1.0;
+	for _ in 0..100 {
+		n += 2.0;
+		let a = -n * (n + 1.0);
+		b += Complex::new(4.0, 0.0);
+		d = Complex::new(1.0, 0.0) / (d * a + b);
+		cc = b + Complex::new(a, 0.0) / cc;
+		let del = cc * d;
+		h *= del;
+		if (del.re - 1.0).abs() + del.im.abs() < f64::EPSILON {
+			break;
+		}
+	}
+	h *= Complex::new(x, -x);
+	let (sin, cos) = (0.5 * pix2).sin_cos();
+	let cs = Complex::new(0.5, 0.5) * (Complex::new(1.0, 0.0) - Complex::new(cos, sin) * h);
+	(cs.re, cs.im)
+}
+
+// pose after travelling a signed distance along a clothoid that starts at the origin
+// facing along the x axis with the given curvature and rate of change of curvature (sharpness)
+pub fn clothoid_point(curvature: f64, sharpness: f64, distance: f64) -> Ray {
+	let heading = curvature * distance + 0.5 * sharpness * distance * distance;
+
+	// phase error from treating the clothoid as an arc is below rounding error
+	if (sharpness * distance * distance).abs() < 1e-12 {
+		let pos = if (curvature * distance).abs() < 1e-12 {
+			Vec2::new(distance, 0.5 * curvature * distance * distance)
+		} else {
+			Vec2::new(heading.sin(), 1.0 - heading.cos()) / curvature
+		};
+		return Ray::new(pos.into(), heading);
+	}
+
+	// nearly circular clothoids have fresnel arguments too large to difference accurately
+	if curvature.abs() > MAX_FRESNEL_ARGUMENT * (PI * sharpness.abs()).sqrt() {
+		return Ray::new(integrate(curvature, sharpness, distance, 0).into(), heading);
+	}
+
+	// complete the square so the integral is a difference of fresnel integrals
+	// negative sharpness is the mirror image of positive sharpness with negated curvature
+	let (k, c, mirror) = if sharpness < 0.0 {
+		(-curvature, -sharpness, -1.0)
+	} else {
+		(curvature, sharpness, 1.0)
+	};
+	let scale = (PI / c).sqrt();
+	let (c0, s0) = fresnel_integrals(k / (PI * c).sqrt());
+	let (c1, s1) = fresnel_integrals((c * distance + k) / (PI * c).sqrt());
+	let (sin, cos) = (k * k / (2.0 * c)).sin_cos();
+	let (dc, ds) = (c1 - c0, s1 - s0);
+	let pos = Vec2::new(cos * dc + sin * ds, mirror * (cos * ds - sin * dc)) * scale;
+
+	Ray::new(pos.into(), heading)
+}
+
+// integral of t^power * (cos(theta), sin(theta)) along the clothoid
+// 5 point gauss-legendre quadrature over pieces with a small change in heading
+fn integrate(curvature: f64, sharpness: f64, distance: f64, power: i32) -> Vec2 {
+	let max_curvature = curvature
+		.abs()
+		.max((curvature + sharpness * distance).abs());
+	let pieces = (distance.abs() * max_curvature / MAX_PIECE_ANGLE)
+		.ceil()
+		.max(1.0);
+	let h = distance / pieces;
+
+	let mut sum = Vec2::zeros();
+	for i in 0..pieces as usize {
+		let mid = (i as f64 + 0.5) * h;
+		for (x, w) in GAUSS_LEGENDRE {
+			let t = mid + 0.5 * h * x;
+			let theta = curvature * t + 0.5 * sharpness * t * t;
+			sum += w * t.powi(power) * Vec2::new(theta.cos(), theta.sin());
+		}
+	}
+	sum * 0.5 * h
+}
+
+// references:
+// https://doi.org/10.1002/mma.3114 (Bertolazzi & Frego, G1 fitting with clothoids)
+
+// initial guess for A fitted over the whole range of angles
+const GUESS_COEFFICIENTS: [f64; 6] = [
+	2.989_696_028_701_907,
+	0.716_228_953_608_281,
+	-0.458_969_738_821_509,
+	-0.502_821_153_340_377,
+	0.261_062_141_752_652,
+	-0.045_854_475_238_709,
+];
+const FIT_TOLERANCE: f64 = 1e-12;
+const FIT_ITERATIONS: usize = 20;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClothoidCurve {
+	pub start: Ray,
+	// curvature at the start
+	pub curvature: f64,
+	// rate of change of curvature with distance
+	pub sharpness: f64,
+	pub length: f64,
+}
+
+impl ClothoidCurve {
+	// the unique clothoid matching position and heading at both ends
+	// found with newton's method on a single angle equation
+	pub fn g1_fit(start: Ray, end: Ray) -> Result<Self, Error> {
+		let offset = end.pos - start.pos;
+		let r = offset.magnitude();
+		if r == 0.0 {
+			return Err(Error::PathNotFound);
+		}
+		let phi = offset.y.atan2(offset.x);
+		let phi0 = map_angle(start.angle - phi);
+		let phi1 = map_angle(end.angle - phi);
+		let delta = phi1 - phi0;
+
+		let (x, y) = (phi0 / PI, phi1 / PI);
+		let (xy, x2, y2) = (x * y, x * x, y * y);
+		let cf = GUESS_COEFFICIENTS;
+		let mut a = (phi0 + phi1)
+			* (cf[0]
+				+ xy * (cf[1] + xy * cf[2])
+				+ (cf[3] + xy * cf[4]) * (x2 + y2)
+				+ cf[5] * (x2 * x2 + y2 * y2));
+
+		// the heading along the normalised clothoid is a t^2 + (delta - a) t + phi0 for t in [0, 1]
+		// the curve ends on the chord when the integral of sin(heading) is zero
+		let chord = |a: f64, power: i32| {
+			let v = integrate(delta - a, 2.0 * a, 1.0, power);
+			let (sin, cos) = phi0.sin_cos();
+			Vec2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+		};
+		let mut converged = false;
+		for _ in 0..FIT_ITERATIONS {
+			let g = chord(a, 0).y;
+			if g.abs() < FIT_TOLERANCE {
+				converged = true;
+				break;
+			}
+			let dg = chord(a, 2).x - chord(a, 1).x;
+			if dg == 0.0 {
+				break;
+			}
+			a -= g / dg;
+		}
+		if !converged {
+			return Err(Error::NaNInCalculation);
+		}
+
+		let length = r / chord(a, 0).x;
+		if !length.is_finite() || length <= 0.0 {
+			return Err(Error::NaNInCalculation);
+		}
+		Ok(Self {
+			start,
+			curvature: (delta - a) / length,
+			sharpness: 2.0 * a / (length * length),
+			length,
+		})
+	}
+	pub fn evaluate(&self, s: f64) -> Ray {
+		self.start
+			.ray_from_local(clothoid_point(self.curvature, self.sharpness, s))
+	}
+	pub fn end(&self) -> Ray {
+		self.evaluate(self.length)
+	}
+	#[must_use]
+	pub fn curvature_at(&self, s: f64) -> f64 {
+		self.curvature + self.sharpness * s
+	}
+	// segment for curved_paths::get_points, normalised by min_radius
+	#[must_use]
+	pub fn segment(&self, min_radius: f64) -> PathSegmentType {
+		PathSegmentType::Clothoid {
+			length: self.length / min_radius,
+			curvature: self.curvature * min_radius,
+			sharpness: self.sharpness * min_radius * min_radius,
+		}
+	}
+	#[must_use]
+	pub fn get_points(&self, step_size: f64) -> Vec<Ray> {
+		if step_size <= 0.0 {
+			return Vec::new();
+		}
+		let steps = (self.length / step_size).ceil().max(1.0) as usize;
+		(0..=steps)
+			.map(|i| self.evaluate(self.length * i as f64 / steps as f64))
+			.collect()
+	}
+}
+
+// G1 continuous clothoid spline through poses
+pub fn clothoid_spline(waypoints: &[Ray]) -> Result<Vec<ClothoidCurve>, Error> {
+	waypoints
+		.windows(2)
+		.map(|w| ClothoidCurve::g1_fit(w[0], w[1]))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fresnel_values() {
+		// values from Abramowitz and Stegun table 7.7
+		for (x, c, s) in [
+			(0.5, 0.492_344_225_871_446_3, 0.064_732_432_859_999_29),
+			(1.0, 0.779_893_400_376_822_8, 0.438_259_147_390_354_8),
+			(2.0, 0.488_253_406_075_340_8, 0.343_415_678_363_698_2),
+			(5.0, 0.563_631_188_704_012_6, 0.499_191_381_917_708_6),
+		] {
+			let (fc, fs) = fresnel_integrals(x);
+			assert!((fc - c).abs() < 1e-12 && (fs - s).abs() < 1e-12);
+			assert_eq!(fresnel_integrals(-x), (-fc, -fs));
+		}
+	}
+
+	#[test]
+	fn clothoid_matches_integration() {
+		for (k, c, l) in [
+			(0.0, 1.0, 2.0),
+			(0.5, -0.3, 3.0),
+			(-1.0, 0.2, -2.5),
+			(0.7, 1e-9, 4.0),
+			(2.0, 1e-3, 3.0),
+			(0.0, 0.0, 1.5),
+		] {
+			// simpson's rule on x'(s) = cos(theta), y'(s) = sin(theta)
+			let n = 2000;
+			let h = l / n as f64;
+			let mut expected = Vec2::zeros();
+			for i in 0..=n {
+				let s = i as f64 * h;
+				let theta = k * s + 0.5 * c * s * s;
+				let w = if i == 0 || i == n {
+					1.0
+				} else if i % 2 == 1 {
+					4.0
+				} else {
+					2.0
+				};
+				expected += w * Vec2::new(theta.cos(), theta.sin());
+			}
+			expected *= h / 3.0;
+
+			let point = clothoid_point(k, c, l);
+			assert!((point.pos.coords - expected).magnitude() < 1e-9);
+			assert!((point.angle - (k * l + 0.5 * c * l * l)).abs() < 1e-12);
+		}
+	}
+
+	#[test]
+	fn g1_fit() {
+		let cases = [
+			(
+				Ray::new(Pos2::new(0.0, 0.0), 0.0),
+				Ray::new(Pos2::new(5.0, 0.0), 0.0),
+			),
+			(
+				Ray::new(Pos2::new(1.0, 2.0), 0.3),
+				Ray::new(Pos2::new(4.0, 6.0), 2.0),
+			),
+			(
+				Ray::new(Pos2::new(0.0, 0.0), 2.5),
+				Ray::new(Pos2::new(-1.0, -3.0), -0.7),
+			),
+			(
+				Ray::new(Pos2::new(2.0, -1.0), -3.0),
+				Ray::new(Pos2::new(2.5, 4.0), 3.1),
+			),
+		];
+		for (start, end) in cases {
+			let curve = ClothoidCurve::g1_fit(start, end).unwrap();
+			let fitted = curve.end();
+			assert!((fitted.pos - end.pos).magnitude() < 1e-9);
+			assert!(map_angle(fitted.angle - end.angle).abs() < 1e-9);
+		}
+
+		// a straight line and a circular arc are special cases
+		let line = ClothoidCurve::g1_fit(cases[0].0, cases[0].1).unwrap();
+		assert!((line.length - 5.0).abs() < 1e-9);
+		assert!(line.curvature.abs() < 1e-9 && line.sharpness.abs() < 1e-9);
+		assert_eq!(line.get_points(1.0).len(), 6);
+		assert!(line.get_points(0.0).is_empty());
+		let arc = ClothoidCurve::g1_fit(
+			Ray::new(Pos2::new(2.0, 0.0), FRAC_PI_2),
+			Ray::new(Pos2::new(0.0, 2.0), PI),
+		)
+		.unwrap();
+		assert!((arc.curvature - 0.5).abs() < 1e-9 && arc.sharpness.abs() < 1e-9);
+		assert!((arc.length - PI).abs() < 1e-9);
+	}
+
+	#[test]
+	fn spline_is_g1() {
+		let waypoints = [
+			Ray::new(Pos2::new(0.0, 0.0), 0.0),
+			Ray::new(Pos2::new(3.0, 1.0), 0.5),
+			Ray::new(Pos2::new(5.0, 4.0), 1.5),
+			Ray::new(Pos2::new(4.0, 7.0), 2.5),
+		];
+		let spline = clothoid_spline(&waypoints).unwrap();
+		for (curve, end) in spline.iter().zip(&waypoints[1..]) {
+			let fitted = curve.end();
+			assert!((fitted.pos - end.pos).magnitude() < 1e-9);
+			assert!(map_angle(fitted.angle - end.angle).abs() < 1e-9);
+		}
+	}
+}
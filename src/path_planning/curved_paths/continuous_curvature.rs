@@ -0,0 +1,486 @@
+use super::{
+	clothoid::clothoid_point,
+	get_point_value,
+	reeds_shepp::ReedsSheppSegments,
+	PathSegmentType::{self, Clothoid, Left, Right, Straight},
+};
+use crate::{path_planning::curved_paths, prelude::*};
+use core::f64::consts::TAU;
+
+// references:
+// https://doi.org/10.1109/TRO.2004.824780 (Fraichard & Scheuer)
+// --------
+// continuous curvature (CC) paths replace every circular arc of a Dubins or
+// Reeds-Shepp path with a CC turn: a clothoid from zero to maximum curvature,
+// an arc and a clothoid back to zero curvature
+//
+// the start and end of a CC turn lie on a circle around the centre of the arc
+// with the heading at the same angle to the circle, so the turn is a rotation
+// about that centre and paths can be found from circle geometry as in Dubins
+//
+// everything here is normalised so the maximum curvature is 1
+//
+// a CC turn has a minimum deflection of max_curve^2 / max_sharpness, smaller
+// changes of heading use a symmetric pair of clothoids that stops short of
+// maximum curvature, so nearly straight manoeuvres are a pair, a straight and a
+// pair rather than a CC turn the long way round
+// --------
+
+// (steering, gear) with left and forward positive
+type Turn = (f64, f64);
+
+const ENDPOINT_TOLERANCE: f64 = 1e-6;
+// intervals searched for each way of splitting the heading change between two pairs
+const PAIR_SAMPLES: usize = 32;
+
+#[derive(Debug, Copy, Clone)]
+struct CcTurn {
+	// deflection of the two clothoids
+	min_deflection: f64,
+	// centre of a forward left turn relative to its start
+	centre: Vec2,
+}
+
+impl CcTurn {
+	fn new(min_deflection: f64) -> Self {
+		let end = clothoid_point(0.0, 1.0 / min_deflection, min_deflection);
+		Self {
+			min_deflection,
+			centre: end.pos.coords + Vec2::new(-end.angle.sin(), end.angle.cos()),
+		}
+	}
+	// a turn ending at a pose is the mirror image of one starting there
+	fn centre(&self, pose: Ray, (steer, gear): Turn, at_end: bool) -> Pos2 {
+		let x = if at_end { -gear } else { gear } * self.centre.x;
+		pose.point_from_local(Pos2::new(x, steer * self.centre.y))
+	}
+	// centre of the second turn relative to the first in the frame of the pose between them
+	fn junction(&self, (s1, g1): Turn, (s2, g2): Turn) -> Vec2 {
+		Vec2::new((g1 + g2) * self.centre.x, (s2 - s1) * self.centre.y)
+	}
+	// change in heading is steer * gear * deflection
+	fn segments(&self, (steer, gear): Turn, heading_change: f64) -> [PathSegmentType; 3] {
+		let m = self.min_deflection;
+		let mut deflection = map_to_2pi(steer * gear * heading_change);
+		while deflection < m {
+			deflection += TAU;
+		}
+		let turn = [
+			Clothoid {
+				length: m,
+				curvature: 0.0,
+				sharpness: 1.0 / m,
+			},
+			Left(deflection - m),
+			Clothoid {
+				length: m,
+				curvature: 1.0,
+				sharpness: -1.0 / m,
+			},
+		];
+		let turn = if steer < 0.0 { turn.reflect() } else { turn };
+		if gear < 0.0 {
+			turn.timeflip()
+		} else {
+			turn
+		}
+	}
+	// symmetric pair of clothoids at maximum sharpness for deflections below the
+	// minimum of a CC turn, so the peak curvature stays below the maximum
+	fn pair(&self, deflection: f64) -> Vec<PathSegmentType> {
+		if deflection == 0.0 {
+			return Vec::new();
+		}
+		let m = self.min_deflection;
+		let length = (deflection.abs() * m).sqrt();
+		let pair = [
+			Clothoid {
+				length,
+				curvature: 0.0,
+				sharpness: 1.0 / m,
+			},
+			Clothoid {
+				length,
+				curvature: length / m,
+				sharpness: -1.0 / m,
+			},
+		];
+		Vec::from(if deflection < 0.0 {
+			pair.reflect()
+		} else {
+			pair
+		})
+	}
+	// pair - straight - pair, including the plain straight line
+	// the heading change is split between the pairs so that the goal lies on
+	// the line of the straight, found by bisection on the offset across it
+	fn psp(&self, goal: Ray, gear: f64) -> Vec<Vec<PathSegmentType>> {
+		// reversing is driving forwards to the mirror image of the goal
+		let goal = if gear < 0.0 {
+			Ray::new(Pos2::new(-goal.pos.x, goal.pos.y), -goal.angle)
+		} else {
+			goal
+		};
+		let m = self.min_deflection;
+		let heading = map_angle(goal.angle);
+		let (low, high) = ((heading - m).max(-m), (heading + m).min(m));
+		if low > high {
+			return Vec::new();
+		}
+
+		// offset of the goal (along, across) the straight
+		let offset = |d1: f64| {
+			let first = path_end(&self.pair(d1));
+			let second = path_end(&self.pair(heading - d1));
+			let rest = goal.pos - first.point_from_local(second.pos);
+			let direction = Vec2::new(d1.cos(), d1.sin());
+			(direction.dot(&rest), direction.perp(&rest))
+		};
+
+		let mut splits = Vec::new();
+		let mut last = (low, offset(low).1);
+		for i in 1..=PAIR_SAMPLES {
+			let d1 = low + (high - low) * i as f64 / PAIR_SAMPLES as f64;
+			let next = (d1, offset(d1).1);
+			if last.1 == 0.0 {
+				splits.push(last.0);
+			} else if last.1 * next.1 < 0.0 {
+				let (mut a, mut b) = (last, next);
+				for _ in 0..60 {
+					let mid = 0.5 * (a.0 + b.0);
+					let mid = (mid, offset(mid).1);
+					if mid.1 * a.1 > 0.0 {
+						a = mid;
+					} else {
+						b = mid;
+					}
+				}
+				splits.push(0.5 * (a.0 + b.0));
+			}
+			last = next;
+		}
+		if last.1 == 0.0 {
+			splits.push(last.0);
+		}
+
+		splits
+			.into_iter()
+			.filter_map(|d1| {
+				let along = offset(d1).0;
+				if along < -ENDPOINT_TOLERANCE {
+					return None;
+				}
+				let mut path = self.pair(d1);
+				if along > 0.0 {
+					path.push(Straight(along));
+				}
+				path.extend(self.pair(heading - d1));
+				if gear < 0.0 {
+					path.iter_mut().for_each(|v| *v = v.timeflip());
+				}
+				(!path.is_empty()).then_some(path)
+			})
+			.collect()
+	}
+	// turn - straight - turn
+	// the straight is tangent to both turns so the centres are offset by a
+	// fixed amount across the straight and by its length along it
+	fn tst(&self, goal: Ray, t1: Turn, straight_gear: f64, t3: Turn) -> Vec<Vec<PathSegmentType>> {
+		let c1 = self.centre(Ray::ZERO, t1, false);
+		let c3 = self.centre(goal, t3, true);
+		let offset = c3 - c1;
+		let d_sq = offset.magnitude_squared();
+
+		let across = (t3.0 - t1.0) * self.centre.y;
+		if across * across > d_sq {
+			return Vec::new();
+		}
+		let along_abs = (d_sq - across * across).sqrt();
+
+		let mut paths = Vec::new();
+		for along in [along_abs, -along_abs] {
+			let length = straight_gear * (along - (t1.1 + t3.1) * self.centre.x);
+			if length < 0.0 {
+				continue;
+			}
+			let heading = offset.y.atan2(offset.x) - across.atan2(along);
+
+			let mut path = Vec::with_capacity(7);
+			path.extend(self.segments(t1, heading));
+			path.push(Straight(straight_gear * length));
+			path.extend(self.segments(t3, goal.angle - heading));
+			paths.push(path);
+		}
+		paths
+	}
+	// turn - turn - turn
+	// the middle centre is at a fixed distance from the other two
+	fn ttt(&self, goal: Ray, t1: Turn, t2: Turn, t3: Turn) -> Vec<Vec<PathSegmentType>> {
+		let (j12, j23) = (self.junction(t1, t2), self.junction(t2, t3));
+		let (r1, r2) = (j12.magnitude(), j23.magnitude());
+		// consecutive turns on the same circle
+		if r1 < 1e-9 || r2 < 1e-9 {
+			return Vec::new();
+		}
+		let c1 = self.centre(Ray::ZERO, t1, false);
+		let c3 = self.centre(goal, t3, true);
+
+		let offset = c3 - c1;
+		let d = offset.magnitude();
+		if d < 1e-9 || d > r1 + r2 || d < (r1 - r2).abs() {
+			return Vec::new();
+		}
+		let a = (r1 * r1 - r2 * r2 + d * d) / (2.0 * d);
+		let h = (r1 * r1 - a * a).max(0.0).sqrt();
+		let base = c1 + offset * (a / d);
+		let normal = Vec2::new(-offset.y, offset.x) / d;
+
+		[base + normal * h, base - normal * h]
+			.into_iter()
+			.map(|c2| {
+				let h1 = (c2 - c1).y.atan2((c2 - c1).x) - j12.y.atan2(j12.x);
+				let h2 = (c3 - c2).y.atan2((c3 - c2).x) - j23.y.atan2(j23.x);
+				let mut path = Vec::with_capacity(9);
+				path.extend(self.segments(t1, h1));
+				path.extend(self.segments(t2, h2 - h1));
+				path.extend(self.segments(t3, goal.angle - h2));
+				path
+			})
+			.collect()
+	}
+}
+
+fn segment_length(segment: PathSegmentType) -> f64 {
+	match segment {
+		Left(v) | Right(v) | Straight(v) | Clothoid { length: v, .. } => v,
+		PathSegmentType::Nill => 0.0,
+	}
+}
+
+// pose at the end of a path in the normalised frame
+fn path_end(path: &[PathSegmentType]) -> Ray {
+	path.iter().fold(Ray::ZERO, |pose, &segment| {
+		get_point_value(segment, pose, segment_length(segment), 1.0).0
+	})
+}
+
+// shortest CC path in the normalised frame of the start
+fn shortest_path(
+	start: Ray,
+	end: Ray,
+	max_curve: f64,
+	max_sharpness: f64,
+	gears: &[f64],
+) -> Result<Vec<PathSegmentType>, Error> {
+	if max_curve <= 0.0 || max_sharpness <= 0.0 {
+		return Err(Error::PathNotFound);
+	}
+	let turn = CcTurn::new(max_curve * max_curve / max_sharpness);
+	let goal = start.ray_to_local(end).scaled(max_curve);
+
+	let mut candidates = Vec::new();
+	for &g1 in gears {
+		for &g2 in gears {
+			for &g3 in gears {
+				for s1 in [-1.0, 1.0] {
+					for s2 in [-1.0, 1.0] {
+						for s3 in [-1.0, 1.0] {
+							candidates.extend(turn.ttt(goal, (s1, g1), (s2, g2), (s3, g3)));
+						}
+						candidates.extend(turn.tst(goal, (s1, g1), g2, (s2, g3)));
+					}
+				}
+			}
+		}
+		candidates.extend(turn.psp(goal, g1));
+	}
+
+	// discard any path that doesn't reach the goal due to numerical error
+	let reaches_goal = |path: &Vec<PathSegmentType>| {
+		let end = path_end(path);
+		(end.pos - goal.pos).magnitude() < ENDPOINT_TOLERANCE
+			&& map_angle(end.angle - goal.angle).abs() < ENDPOINT_TOLERANCE
+	};
+
+	candidates
+		.into_iter()
+		.filter(reaches_goal)
+		.map(|v| (v.iter().map(ReedsSheppSegments::distance).sum::<f64>(), v))
+		.min_by(|x, y| float_cmp(x.0, y.0))
+		.map(|v| v.1)
+		.ok_or(Error::PathNotFound)
+}
+
+fn map_to_2pi(angle: f64) -> f64 {
+	let val = angle % TAU;
+	if val < 0.0 {
+		TAU + val
+	} else {
+		val
+	}
+}
+
+// forwards only continuous curvature path
+#[derive(Debug)]
+pub struct CcDubins {
+	pub start: Ray,
+	pub end: Ray,
+	// normalised by the minimum radius
+	pub segments: Vec<PathSegmentType>,
+	pub max_curve: f64,
+	// maximum rate of change of curvature with distance
+	pub max_sharpness: f64,
+}
+
+impl CcDubins {
+	pub fn new(start: Ray, end: Ray, max_curve: f64, max_sharpness: f64) -> Result<Self, Error> {
+		Ok(Self {
+			start,
+			end,
+			segments: shortest_path(start, end, max_curve, max_sharpness, &[1.0])?,
+			max_curve,
+			max_sharpness,
+		})
+	}
+	#[must_use]
+	pub fn distance(&self) -> f64 {
+		self.segments
+			.iter()
+			.map(ReedsSheppSegments::distance)
+			.sum::<f64>()
+			/ self.max_curve
+	}
+	#[must_use]
+	pub fn get_points(&self, step_size: f64) -> Vec<(Ray, PathSegmentType)> {
+		curved_paths::get_points(self.start, &self.segments, 1.0 / self.max_curve, step_size)
+	}
+}
+
+// continuous curvature path that can reverse
+// curvature is zero at every cusp
+#[derive(Debug)]
+pub struct CcReedsShepp {
+	pub start: Ray,
+	pub end: Ray,
+	// normalised by the minimum radius
+	pub segments: Vec<PathSegmentType>,
+	pub max_curve: f64,
+	// maximum rate of change of curvature with distance
+	pub max_sharpness: f64,
+}
+
+impl CcReedsShepp {
+	pub fn new(start: Ray, end: Ray, max_curve: f64, max_sharpness: f64) -> Result<Self, Error> {
+		Ok(Self {
+			start,
+			end,
+			segments: shortest_path(start, end, max_curve, max_sharpness, &[-1.0, 1.0])?,
+			max_curve,
+			max_sharpness,
+		})
+	}
+	#[must_use]
+	pub fn distance(&self) -> f64 {
+		self.segments
+			.iter()
+			.map(ReedsSheppSegments::distance)
+			.sum::<f64>()
+			/ self.max_curve
+	}
+	#[must_use]
+	pub fn get_points(&self, step_size: f64) -> Vec<(Ray, PathSegmentType)> {
+		curved_paths::get_points(self.start, &self.segments, 1.0 / self.max_curve, step_size)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::path_planning::Dubins;
+
+	// curvature and sharpness between consecutive samples
+	fn check_limits(points: &[(Ray, PathSegmentType)], max_curve: f64, max_sharpness: f64) {
+		let mut last_curvature: Option<f64> = None;
+		for w in points.windows(2) {
+			let ds = (w[1].0.pos - w[0].0.pos).magnitude();
+			if ds < 1e-9 {
+				continue;
+			}
+			let curvature = map_angle(w[1].0.angle - w[0].0.angle).abs() / ds;
+			assert!(curvature < max_curve * 1.01);
+			if let Some(last) = last_curvature {
+				assert!((curvature - last).abs() / ds < max_sharpness * 1.5);
+			}
+			last_curvature = Some(curvature);
+		}
+	}
+
+	#[test]
+	fn cc_dubins() {
+		let start = Ray::new(Pos2::new(1.0, 2.0), 0.3);
+		let end = Ray::new(Pos2::new(12.0, -4.0), 2.5);
+		let (max_curve, max_sharpness) = (0.5, 0.4);
+		let path = CcDubins::new(start, end, max_curve, max_sharpness).unwrap();
+
+		let points = path.get_points(0.01);
+		let last = points[points.len() - 1].0;
+		assert!((last.pos - end.pos).magnitude() < 1e-6);
+		assert!(map_angle(last.angle - end.angle).abs() < 1e-6);
+		check_limits(&points, max_curve, max_sharpness);
+
+		// limiting sharpness makes the path longer than the plain Dubins path
+		let dubins_distance: f64 = Dubins::new(start, end, max_curve)
+			.unwrap()
+			.get_points(0.01)
+			.windows(2)
+			.map(|w| (w[1].0.pos - w[0].0.pos).magnitude())
+			.sum();
+		assert!(path.distance() > dubins_distance);
+		assert!(path.distance() < dubins_distance * 1.5);
+	}
+
+	#[test]
+	fn nearly_straight() {
+		let start = Ray::new(Pos2::new(0.0, 0.0), 0.0);
+		let (max_curve, max_sharpness) = (0.5, 0.4);
+		for end in [
+			Ray::new(Pos2::new(20.0, 0.0), 0.0),
+			Ray::new(Pos2::new(20.0, 0.5), 0.0),
+			Ray::new(Pos2::new(20.0, -0.3), 0.1),
+			Ray::new(Pos2::new(15.0, 0.0), -0.2),
+		] {
+			let path = CcDubins::new(start, end, max_curve, max_sharpness).unwrap();
+			let points = path.get_points(0.01);
+			let last = points[points.len() - 1].0;
+			assert!((last.pos - end.pos).magnitude() < 1e-6);
+			assert!(map_angle(last.angle - end.angle).abs() < 1e-6);
+			check_limits(&points, max_curve, max_sharpness);
+
+			let dubins = Dubins::new(start, end, max_curve).unwrap();
+			let dubins_distance = dubins.path.segments().distance() / max_curve;
+			assert!(path.distance() >= dubins_distance - 1e-9);
+			assert!(path.distance() < dubins_distance + 0.1);
+		}
+	}
+
+	#[test]
+	fn cc_reeds_shepp() {
+		let start = Ray::new(Pos2::new(0.0, 0.0), 0.0);
+		let (max_curve, max_sharpness) = (1.0, 1.0);
+		for end in [
+			Ray::new(Pos2::new(-3.0, 1.0), 0.2),
+			Ray::new(Pos2::new(0.5, 3.0), -1.0),
+			Ray::new(Pos2::new(6.0, -2.0), 3.0),
+		] {
+			let path = CcReedsShepp::new(start, end, max_curve, max_sharpness).unwrap();
+			let points = path.get_points(0.01);
+			let last = points[points.len() - 1].0;
+			assert!((last.pos - end.pos).magnitude() < 1e-6);
+			assert!(map_angle(last.angle - end.angle).abs() < 1e-6);
+
+			// reversing makes it no longer than going forwards
+			let forwards = CcDubins::new(start, end, max_curve, max_sharpness).unwrap();
+			assert!(path.distance() <= forwards.distance() + 1e-9);
+		}
+	}
+}
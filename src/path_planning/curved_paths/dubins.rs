@@ -100,6 +100,10 @@ impl DubinsPath {
 	pub(crate) fn new(distance: f64, segments: [PathSegmentType; 3]) -> Self {
 		Self { distance, segments }
 	}
+	#[must_use]
+	pub fn segments(&self) -> [PathSegmentType; 3] {
+		self.segments
+	}
 }
 
 impl PartialOrd for DubinsPath {
@@ -1,3 +1,5 @@
+pub(crate) mod clothoid;
+pub(crate) mod continuous_curvature;
 pub(crate) mod dubins;
 pub(crate) mod reeds_shepp;
 
@@ -8,6 +10,13 @@ pub enum PathSegmentType {
 	Right(f64),
 	Straight(f64),
 	Left(f64),
+	// curvature changes linearly along the segment
+	// length, curvature at the start and sharpness are normalised by the minimum radius
+	Clothoid {
+		length: f64,
+		curvature: f64,
+		sharpness: f64,
+	},
 	Nill,
 }
 
@@ -17,7 +26,7 @@ fn get_point_value(
 	cl: f64,
 	min_radius: f64,
 ) -> (Ray, PathSegmentType) {
-	use PathSegmentType::{Left, Nill, Right, Straight};
+	use PathSegmentType::{Clothoid, Left, Nill, Right, Straight};
 
 	match st {
 		Straight(_) => (
@@ -33,6 +42,14 @@ fn get_point_value(
 			let l = Vec2::new(cl.sin(), cl.cos() - 1.0) * min_radius;
 			(r0.ray_from_local(Ray::new(l.into(), -cl)), st)
 		}
+		Clothoid {
+			curvature,
+			sharpness,
+			..
+		} => {
+			let l = clothoid::clothoid_point(curvature, sharpness, cl);
+			(r0.ray_from_local(l.scaled(min_radius)), st)
+		}
 		Nill => unreachable!(),
 	}
 }
@@ -51,7 +68,8 @@ fn get_points_local(
 	for &current_segment in segments {
 		let (PathSegmentType::Right(segment_length) 
 			| PathSegmentType::Left(segment_length)
-			| PathSegmentType::Straight(segment_length)) = current_segment else { break; };
+			| PathSegmentType::Straight(segment_length)
+			| PathSegmentType::Clothoid { length: segment_length, .. }) = current_segment else { break; };
 
 		if segment_length == 0.0 {
 			continue;
@@ -78,6 +96,22 @@ fn get_points_local(
 
 	points
 }
+// reversing segments have negative lengths
+pub(crate) fn gear(segment: PathSegmentType) -> f64 {
+	match segment {
+		PathSegmentType::Left(v)
+		| PathSegmentType::Right(v)
+		| PathSegmentType::Straight(v)
+		| PathSegmentType::Clothoid { length: v, .. } => {
+			if v < 0.0 {
+				-1.0
+			} else {
+				1.0
+			}
+		}
+		PathSegmentType::Nill => 1.0,
+	}
+}
 #[must_use]
 pub fn get_points(
 	start: Ray,
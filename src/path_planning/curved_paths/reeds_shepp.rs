@@ -1,6 +1,6 @@
 use super::PathSegmentType::{self, Left, Nill, Right, Straight};
 use crate::{path_planning::curved_paths, prelude::*};
-use core::f64::consts::{FRAC_PI_2, PI, TAU};
+use core::f64::consts::{FRAC_PI_2, PI};
 
 // references:
 // https://projecteuclid.org/journals/pacific-journal-of-mathematics/volume-145/issue-2/Optimal-paths-for-a-car-that-goes-both-forwards-and/pjm/1102645450.pdf
@@ -48,6 +48,15 @@ impl ReedsSheppSegments for PathSegmentType {
 			Self::Right(v) => Self::Right(-v),
 			Self::Left(v) => Self::Left(-v),
 			Self::Straight(v) => Self::Straight(-v),
+			Self::Clothoid {
+				length,
+				curvature,
+				sharpness,
+			} => Self::Clothoid {
+				length: -length,
+				curvature,
+				sharpness: -sharpness,
+			},
 			Self::Nill => self,
 		}
 	}
@@ -55,12 +64,24 @@ impl ReedsSheppSegments for PathSegmentType {
 		match self {
 			Self::Right(v) => Self::Left(v),
 			Self::Left(v) => Self::Right(v),
+			Self::Clothoid {
+				length,
+				curvature,
+				sharpness,
+			} => Self::Clothoid {
+				length,
+				curvature: -curvature,
+				sharpness: -sharpness,
+			},
 			_ => self,
 		}
 	}
 	fn distance(&self) -> f64 {
 		match self {
-			Self::Right(a) | Self::Left(a) | Self::Straight(a) => (*a).abs(),
+			Self::Right(a)
+			| Self::Left(a)
+			| Self::Straight(a)
+			| Self::Clothoid { length: a, .. } => (*a).abs(),
 			Self::Nill => 0.0,
 		}
 	}
@@ -296,17 +317,6 @@ fn to_polar(x: f64, y: f64) -> (f64, f64) {
 	(r_sq.sqrt(), y.atan2(x))
 }
 
-// map angle to [-pi, pi]
-fn map_angle(angle: f64) -> f64 {
-	let mut angle = angle % TAU;
-	if angle > PI {
-		angle -= TAU;
-	} else if angle < -PI {
-		angle += TAU;
-	}
-	angle
-}
-
 fn tau(u: f64, v: f64, xi: f64, eta: f64) -> f64 {
 	let delta = map_angle(u - v);
 	let a = u.sin() - delta.sin();
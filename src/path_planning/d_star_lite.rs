@@ -0,0 +1,277 @@
+use super::grid::{Cell, Grid, GridError};
+use crate::prelude::*;
+use core::cmp::Ordering;
+
+// references:
+// https://doi.org/10.1109/TRO.2004.838026 (Koenig & Likhachev)
+// --------
+// searches backwards from the goal so the g values (cost to the goal) stay
+// valid as the robot moves, only cells affected by cost changes are repaired
+// the heuristic is measured from the robot so km keeps old queue keys
+// consistent when the robot moves instead of reordering the whole queue
+// --------
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Entry {
+	key: [f64; 2],
+	index: usize,
+}
+
+impl Eq for Entry {}
+
+impl Ord for Entry {
+	// reversed for a min heap
+	fn cmp(&self, other: &Self) -> Ordering {
+		compare_keys(other.key, self.key)
+	}
+}
+
+impl PartialOrd for Entry {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+fn compare_keys(a: [f64; 2], b: [f64; 2]) -> Ordering {
+	float_cmp(a[0], b[0]).then(float_cmp(a[1], b[1]))
+}
+
+#[derive(Debug, Clone)]
+pub struct DStarLite {
+	grid: Grid,
+	start: Cell,
+	goal: Cell,
+	// where the robot was when km was last updated
+	last: Cell,
+	km: f64,
+	g: Vec<f64>,
+	rhs: Vec<f64>,
+	queue: BinaryHeap<Entry>,
+	// key each cell is queued with, entries with other keys are stale
+	queued: Vec<Option<[f64; 2]>>,
+}
+
+impl DStarLite {
+	pub fn new(grid: Grid, start: Pos2, goal: Pos2) -> Result<Self, GridError> {
+		let start = grid.cell(start).ok_or(GridError::OutOfBounds)?;
+		let goal = grid.cell(goal).ok_or(GridError::OutOfBounds)?;
+		let n = grid.width() * grid.height();
+		let mut planner = Self {
+			grid,
+			start,
+			goal,
+			last: start,
+			km: 0.0,
+			g: Vec::from([f64::INFINITY]).repeat(n),
+			rhs: Vec::from([f64::INFINITY]).repeat(n),
+			queue: BinaryHeap::new(),
+			queued: Vec::from([None]).repeat(n),
+		};
+		let goal = planner.grid.index(goal);
+		planner.rhs[goal] = 0.0;
+		planner.push(goal);
+		Ok(planner)
+	}
+	#[must_use]
+	pub fn grid(&self) -> &Grid {
+		&self.grid
+	}
+	// change the costs of cells, e.g. to infinity for newly seen obstacles
+	// nothing changes unless every cost is valid for Grid::set_cost
+	pub fn update_costs(&mut self, changes: &[(Cell, f64)]) -> Result<(), GridError> {
+		for &(cell, cost) in changes {
+			if !self.grid.contains(cell) {
+				return Err(GridError::OutOfBounds);
+			}
+			if cost.is_nan() || cost < 1.0 {
+				return Err(GridError::InvalidInput);
+			}
+		}
+		for &(cell, cost) in changes {
+			self.grid.set_cost(cell, cost)?;
+		}
+		for &(cell, _) in changes {
+			let adjacent: Vec<Cell> = self.grid.adjacent(cell).collect();
+			self.update_vertex(cell);
+			for n in adjacent {
+				self.update_vertex(n);
+			}
+		}
+		Ok(())
+	}
+	// the robot has moved, the next plan starts from here
+	pub fn move_to(&mut self, position: Pos2) -> Result<(), GridError> {
+		let cell = self.grid.cell(position).ok_or(GridError::OutOfBounds)?;
+		self.km += self.grid.heuristic(self.last, cell);
+		self.last = cell;
+		self.start = cell;
+		Ok(())
+	}
+	// cell centres from the robot to the goal, repairing the search as needed
+	pub fn plan(&mut self) -> Result<Vec<Pos2>, GridError> {
+		self.compute_shortest_path();
+
+		let mut cell = self.start;
+		if self.g[self.grid.index(cell)].is_infinite() {
+			return Err(GridError::PathNotFound);
+		}
+		let mut path = Vec::from([self.grid.centre(cell)]);
+		while cell != self.goal {
+			// g strictly decreases along the path so this can't loop
+			cell = self
+				.grid
+				.neighbours(cell)
+				.map(|(n, cost)| (n, cost + self.g[self.grid.index(n)]))
+				.min_by(|a, b| float_cmp(a.1, b.1))
+				.filter(|v| v.1.is_finite())
+				.ok_or(GridError::PathNotFound)?
+				.0;
+			path.push(self.grid.centre(cell));
+		}
+		Ok(path)
+	}
+	// cost to the goal from a cell with the current search state
+	#[must_use]
+	pub fn cost_to_goal(&self, position: Pos2) -> Option<f64> {
+		let cell = self.grid.cell(position)?;
+		let g = self.g[self.grid.index(cell)];
+		g.is_finite().then_some(g)
+	}
+	fn key(&self, index: usize) -> [f64; 2] {
+		let m = self.g[index].min(self.rhs[index]);
+		let h = self.grid.heuristic(self.start, self.grid.cell_at(index));
+		[m + h + self.km, m]
+	}
+	fn push(&mut self, index: usize) {
+		let key = self.key(index);
+		self.queued[index] = Some(key);
+		self.queue.push(Entry { key, index });
+	}
+	fn update_vertex(&mut self, cell: Cell) {
+		let index = self.grid.index(cell);
+		if cell != self.goal {
+			self.rhs[index] = self
+				.grid
+				.neighbours(cell)
+				.map(|(n, cost)| cost + self.g[self.grid.index(n)])
+				.fold(f64::INFINITY, f64::min);
+		}
+		#[allow(clippy::float_cmp)]
+		if self.g[index] == self.rhs[index] {
+			self.queued[index] = None;
+		} else {
+			self.push(index);
+		}
+	}
+	#[allow(clippy::float_cmp)]
+	fn compute_shortest_path(&mut self) {
+		let start = self.grid.index(self.start);
+		while let Some(&top) = self.queue.peek() {
+			if self.queued[top.index] != Some(top.key) {
+				self.queue.pop();
+				continue;
+			}
+			if compare_keys(top.key, self.key(start)) != Ordering::Less
+				&& self.rhs[start] == self.g[start]
+			{
+				break;
+			}
+			self.queue.pop();
+			self.queued[top.index] = None;
+
+			let cell = self.grid.cell_at(top.index);
+			let adjacent: Vec<Cell> = self.grid.adjacent(cell).collect();
+			let new_key = self.key(top.index);
+			if compare_keys(top.key, new_key) == Ordering::Less {
+				// the robot moved since this was queued
+				self.push(top.index);
+			} else if self.g[top.index] > self.rhs[top.index] {
+				self.g[top.index] = self.rhs[top.index];
+				for n in adjacent {
+					self.update_vertex(n);
+				}
+			} else {
+				self.g[top.index] = f64::INFINITY;
+				self.update_vertex(cell);
+				for n in adjacent {
+					self.update_vertex(n);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn path_cost(grid: &Grid, path: &[Pos2]) -> f64 {
+		path.windows(2)
+			.map(|w| {
+				let (a, b) = (grid.cell(w[0]).unwrap(), grid.cell(w[1]).unwrap());
+				grid.move_cost(a, b)
+			})
+			.sum()
+	}
+
+	#[test]
+	fn open_grid() {
+		let grid = Grid::new(10, 10, 1.0, Pos2::new(0.0, 0.0)).unwrap();
+		let mut planner = DStarLite::new(grid, Pos2::new(0.5, 0.5), Pos2::new(9.5, 5.5)).unwrap();
+		let path = planner.plan().unwrap();
+		assert_eq!(path[0], Pos2::new(0.5, 0.5));
+		assert_eq!(path[path.len() - 1], Pos2::new(9.5, 5.5));
+		// 5 diagonal and 4 straight moves
+		let expected = 5.0 * core::f64::consts::SQRT_2 + 4.0;
+		assert!((path_cost(planner.grid(), &path) - expected).abs() < 1e-9);
+	}
+
+	#[test]
+	fn replans_around_new_obstacles() {
+		let grid = Grid::new(12, 8, 0.5, Pos2::new(0.0, 0.0)).unwrap();
+		let goal = Pos2::new(5.75, 1.75);
+		let mut planner = DStarLite::new(grid, Pos2::new(0.25, 1.75), goal).unwrap();
+		let path = planner.plan().unwrap();
+
+		// drive a few cells then discover a wall with a gap at the top
+		planner.move_to(path[3]).unwrap();
+		let wall: Vec<(Cell, f64)> = (0..7).map(|y| ((6, y), f64::INFINITY)).collect();
+		planner.update_costs(&wall).unwrap();
+		let path = planner.plan().unwrap();
+		assert_eq!(path[0], planner.grid().centre((3, 3)));
+		assert!(path
+			.iter()
+			.all(|&p| planner.grid().is_free(planner.grid().cell(p).unwrap())));
+
+		// same cost as planning from scratch
+		let mut fresh = DStarLite::new(planner.grid().clone(), path[0], goal).unwrap();
+		let fresh_path = fresh.plan().unwrap();
+		let expected = path_cost(fresh.grid(), &fresh_path);
+		assert!((path_cost(planner.grid(), &path) - expected).abs() < 1e-9);
+		assert!((planner.cost_to_goal(path[0]).unwrap() - expected).abs() < 1e-9);
+
+		// closing the gap leaves no path
+		planner.update_costs(&[((6, 7), f64::INFINITY)]).unwrap();
+		assert_eq!(planner.plan().unwrap_err(), GridError::PathNotFound);
+
+		// a bad change leaves the rest of the batch unapplied
+		assert_eq!(
+			planner
+				.update_costs(&[((6, 2), 1.0), ((12, 0), 1.0)])
+				.unwrap_err(),
+			GridError::OutOfBounds
+		);
+		assert_eq!(
+			planner
+				.update_costs(&[((6, 2), 1.0), ((6, 3), f64::NAN)])
+				.unwrap_err(),
+			GridError::InvalidInput
+		);
+		assert!(!planner.grid().is_free((6, 2)));
+		assert_eq!(planner.plan().unwrap_err(), GridError::PathNotFound);
+
+		// and reopening part of the wall finds one again
+		planner.update_costs(&[((6, 2), 1.0)]).unwrap();
+		assert!(planner.plan().is_ok());
+	}
+}
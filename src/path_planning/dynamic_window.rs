@@ -0,0 +1,312 @@
+use crate::prelude::*;
+
+// references:
+// https://doi.org/10.1109/100.580977 (Fox, Burgard & Thrun)
+// https://github.com/AtsushiSakai/PythonRobotics/tree/master/PathPlanning/DynamicWindowApproach
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DwaError {
+	InvalidInput,
+	NoAdmissibleCommand,
+}
+
+// angular velocity is positive counter clockwise
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct VelocityCommand {
+	pub velocity: f64,
+	pub angular_velocity: f64,
+}
+
+impl VelocityCommand {
+	#[must_use]
+	pub const fn new(velocity: f64, angular_velocity: f64) -> Self {
+		Self {
+			velocity,
+			angular_velocity,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DwaTrajectory {
+	pub command: VelocityCommand,
+	// predicted poses starting at the current pose
+	pub points: Vec<Ray>,
+	// smallest distance between the robot's edge and an obstacle, negative on collision
+	pub clearance: f64,
+	// distance travelled before the first collision, infinite without one
+	pub free_distance: f64,
+	pub cost: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DynamicWindow {
+	pub min_velocity: f64,
+	pub max_velocity: f64,
+	pub max_angular_velocity: f64,
+	pub max_acceleration: f64,
+	pub max_angular_acceleration: f64,
+	// spacing of the sampled commands
+	pub velocity_resolution: f64,
+	pub angular_velocity_resolution: f64,
+	// time until the next command, which sets the size of the window
+	pub control_period: f64,
+	// commands are simulated for predict_time in steps of dt
+	pub predict_time: f64,
+	pub dt: f64,
+	pub robot_radius: f64,
+	// cost weights for heading to the goal, obstacle clearance and speed
+	pub k_heading: f64,
+	pub k_clearance: f64,
+	pub k_velocity: f64,
+}
+
+impl Default for DynamicWindow {
+	fn default() -> Self {
+		Self {
+			min_velocity: -0.5,
+			max_velocity: 1.0,
+			max_angular_velocity: 40f64.to_radians(),
+			max_acceleration: 0.2,
+			max_angular_acceleration: 40f64.to_radians(),
+			velocity_resolution: 0.01,
+			angular_velocity_resolution: 0.1f64.to_radians(),
+			control_period: 0.1,
+			predict_time: 3.0,
+			dt: 0.1,
+			robot_radius: 1.0,
+			k_heading: 0.15,
+			k_clearance: 1.0,
+			k_velocity: 1.0,
+		}
+	}
+}
+
+impl DynamicWindow {
+	// lowest cost command reachable from the current one that can still stop
+	// before reaching an obstacle
+	pub fn plan(
+		&self,
+		pose: Ray,
+		current: VelocityCommand,
+		goal: Pos2,
+		obstacles: &[Pos2],
+	) -> Result<DwaTrajectory, DwaError> {
+		self.candidates(pose, current, goal, obstacles)?
+			.into_iter()
+			.filter(|t| {
+				t.command.velocity * t.command.velocity
+					<= 2.0 * self.max_acceleration * t.free_distance
+			})
+			.min_by(|a, b| float_cmp(a.cost, b.cost))
+			.ok_or(DwaError::NoAdmissibleCommand)
+	}
+	// every sampled command in the window without any admissibility checks
+	pub fn candidates(
+		&self,
+		pose: Ray,
+		current: VelocityCommand,
+		goal: Pos2,
+		obstacles: &[Pos2],
+	) -> Result<Vec<DwaTrajectory>, DwaError> {
+		if self.velocity_resolution <= 0.0
+			|| self.angular_velocity_resolution <= 0.0
+			|| self.control_period <= 0.0
+			|| self.dt <= 0.0
+			|| self.predict_time < self.dt
+			|| self.min_velocity > self.max_velocity
+			|| self.max_angular_velocity < 0.0
+		{
+			return Err(DwaError::InvalidInput);
+		}
+		let (min, max) = self.window(current);
+
+		let velocity_count =
+			((max.velocity - min.velocity) / self.velocity_resolution).ceil() as usize;
+		let angular_count = ((max.angular_velocity - min.angular_velocity)
+			/ self.angular_velocity_resolution)
+			.ceil() as usize;
+
+		let mut trajectories = Vec::with_capacity((velocity_count + 1) * (angular_count + 1));
+		for i in 0..=velocity_count {
+			let velocity = (min.velocity + i as f64 * self.velocity_resolution).min(max.velocity);
+			for j in 0..=angular_count {
+				let angular_velocity = (min.angular_velocity
+					+ j as f64 * self.angular_velocity_resolution)
+					.min(max.angular_velocity);
+				let command = VelocityCommand::new(velocity, angular_velocity);
+				let points = self.simulate(pose, command);
+				trajectories.push(self.score(command, points, goal, obstacles));
+			}
+		}
+		Ok(trajectories)
+	}
+	// range of commands reachable within one control period
+	#[must_use]
+	pub fn window(&self, current: VelocityCommand) -> (VelocityCommand, VelocityCommand) {
+		let dv = self.max_acceleration * self.control_period;
+		let dw = self.max_angular_acceleration * self.control_period;
+		let min = VelocityCommand::new(
+			(current.velocity - dv).max(self.min_velocity),
+			(current.angular_velocity - dw).max(-self.max_angular_velocity),
+		);
+		let max = VelocityCommand::new(
+			(current.velocity + dv).min(self.max_velocity),
+			(current.angular_velocity + dw).min(self.max_angular_velocity),
+		);
+		// the current command can be outside the limits so keep the window non empty
+		(
+			VelocityCommand::new(
+				min.velocity.min(max.velocity),
+				min.angular_velocity.min(max.angular_velocity),
+			),
+			max,
+		)
+	}
+	// poses from holding the command for predict_time
+	#[must_use]
+	pub fn simulate(&self, pose: Ray, command: VelocityCommand) -> Vec<Ray> {
+		let steps = (self.predict_time / self.dt).round() as usize;
+		let mut points = Vec::with_capacity(steps + 1);
+		points.push(pose);
+		let mut pose = pose;
+		for _ in 0..steps {
+			pose = step(pose, command, self.dt);
+			points.push(pose);
+		}
+		points
+	}
+	fn score(
+		&self,
+		command: VelocityCommand,
+		points: Vec<Ray>,
+		goal: Pos2,
+		obstacles: &[Pos2],
+	) -> DwaTrajectory {
+		let end = points[points.len() - 1];
+		let to_goal = goal - end.pos;
+		let heading = map_angle(to_goal.y.atan2(to_goal.x) - end.angle).abs();
+
+		let clearances: Vec<f64> = points
+			.iter()
+			.map(|p| {
+				obstacles
+					.iter()
+					.map(|o| (o - p.pos).magnitude())
+					.fold(f64::INFINITY, f64::min)
+					- self.robot_radius
+			})
+			.collect();
+		let clearance = clearances.iter().copied().fold(f64::INFINITY, f64::min);
+		let free_distance = clearances
+			.iter()
+			.position(|&c| c <= 0.0)
+			.map_or(f64::INFINITY, |i| {
+				command.velocity.abs() * self.dt * i as f64
+			});
+
+		let cost = self.k_heading * heading
+			+ self.k_clearance / clearance.max(f64::MIN_POSITIVE)
+			+ self.k_velocity * (self.max_velocity - command.velocity);
+		DwaTrajectory {
+			command,
+			points,
+			clearance,
+			free_distance,
+			cost,
+		}
+	}
+}
+
+// exact motion of a unicycle with constant velocities
+fn step(pose: Ray, command: VelocityCommand, dt: f64) -> Ray {
+	let VelocityCommand {
+		velocity: v,
+		angular_velocity: w,
+	} = command;
+	let angle = pose.angle + w * dt;
+	let offset = if w.abs() < 1e-9 {
+		Vec2::new(pose.angle.cos(), pose.angle.sin()) * v * dt
+	} else {
+		Vec2::new(
+			angle.sin() - pose.angle.sin(),
+			pose.angle.cos() - angle.cos(),
+		) * v / w
+	};
+	Ray::new(pose.pos + offset, angle)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use core::f64::consts::FRAC_PI_2;
+
+	#[test]
+	fn window_and_simulation() {
+		let planner = DynamicWindow::default();
+		let (min, max) = planner.window(VelocityCommand::new(0.5, 0.0));
+		assert!((min.velocity - 0.48).abs() < 1e-12 && (max.velocity - 0.52).abs() < 1e-12);
+		assert!((max.angular_velocity - 4f64.to_radians()).abs() < 1e-12);
+
+		// quarter circle of radius 1
+		let planner = DynamicWindow {
+			predict_time: 1.0,
+			..Default::default()
+		};
+		let points = planner.simulate(Ray::ZERO, VelocityCommand::new(FRAC_PI_2, FRAC_PI_2));
+		let end = points[points.len() - 1];
+		assert_eq!(points.len(), 11);
+		assert!((end.pos - Pos2::new(1.0, 1.0)).magnitude() < 1e-12);
+		assert!((end.angle - FRAC_PI_2).abs() < 1e-12);
+	}
+
+	#[test]
+	fn heads_to_goal() {
+		let planner = DynamicWindow::default();
+		let current = VelocityCommand::new(0.5, 0.0);
+		let best = planner
+			.plan(Ray::ZERO, current, Pos2::new(10.0, 0.0), &[])
+			.unwrap();
+		assert!((best.command.velocity - 0.52).abs() < 1e-9);
+		assert!(best.command.angular_velocity.abs() < 1e-9);
+
+		// goal to the left
+		let best = planner
+			.plan(Ray::ZERO, current, Pos2::new(0.0, 10.0), &[])
+			.unwrap();
+		assert!(best.command.angular_velocity > 0.0);
+	}
+
+	#[test]
+	fn avoids_obstacles() {
+		let planner = DynamicWindow {
+			max_angular_acceleration: 200f64.to_radians(),
+			..Default::default()
+		};
+		let current = VelocityCommand::new(0.5, 0.0);
+		// driving straight would hit the obstacles
+		let obstacles = [Pos2::new(2.5, 0.0), Pos2::new(2.5, -0.3)];
+		let best = planner
+			.plan(Ray::ZERO, current, Pos2::new(10.0, 0.0), &obstacles)
+			.unwrap();
+		assert!(best.clearance > 0.0);
+		assert!(best.command.angular_velocity > 0.0);
+		for p in &best.points {
+			assert!(obstacles.iter().all(|o| (o - p.pos).magnitude() > 1.0));
+		}
+
+		// boxed in
+		let obstacles: Vec<Pos2> = (0..8)
+			.map(|i| {
+				let angle = i as f64 * FRAC_PI_2 / 2.0;
+				Pos2::new(0.9 * angle.cos(), 0.9 * angle.sin())
+			})
+			.collect();
+		assert_eq!(
+			planner
+				.plan(Ray::ZERO, current, Pos2::new(10.0, 0.0), &obstacles)
+				.unwrap_err(),
+			DwaError::NoAdmissibleCommand
+		);
+	}
+}
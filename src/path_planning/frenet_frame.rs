@@ -0,0 +1,495 @@
+use super::{
+	cubic_spline::CubicSpline2D,
+	curved_paths::{gear, PathSegmentType},
+	frenet_planner::FrenetState,
+};
+use crate::prelude::*;
+
+// references:
+// https://doi.org/10.1109/ROBOT.2010.5509799 (Werling et al.)
+// https://github.com/ApolloAuto/apollo/blob/master/modules/common/math/cartesian_frenet_conversion.cc
+
+// a path that can be used as the reference for a frenet frame
+// s must be arc length, the conversions assume the path moves at unit speed in s
+pub trait ReferencePath {
+	fn length(&self) -> f64;
+	// position and heading at s
+	fn pose(&self, s: f64) -> Ray;
+	fn curvature(&self, s: f64) -> f64;
+	// derivative of curvature with respect to s
+	fn curvature_derivative(&self, s: f64) -> f64;
+	// s of the closest point on the path
+	fn project(&self, point: Pos2) -> f64;
+}
+
+// s is distance along the path, d is the signed lateral offset (left positive)
+// and heading_error is the heading relative to the path heading
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct FrenetPose {
+	pub s: f64,
+	pub d: f64,
+	pub heading_error: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CartesianState {
+	pub pose: Ray,
+	pub speed: f64,
+	// tangential acceleration
+	pub acceleration: f64,
+	pub curvature: f64,
+}
+
+#[must_use]
+pub fn to_frenet<P: ReferencePath>(path: &P, pose: Ray) -> FrenetPose {
+	let s = path.project(pose.pos);
+	let reference = path.pose(s);
+	let offset = pose.pos - reference.pos;
+	let d = reference.angle.cos() * offset.y - reference.angle.sin() * offset.x;
+	FrenetPose {
+		s,
+		d,
+		heading_error: map_angle(pose.angle - reference.angle),
+	}
+}
+
+pub fn from_frenet<P: ReferencePath>(path: &P, pose: FrenetPose) -> Ray {
+	let reference = path.pose(pose.s);
+	let normal = Vec2::new(-reference.angle.sin(), reference.angle.cos());
+	Ray::new(
+		reference.pos + pose.d * normal,
+		map_angle(reference.angle + pose.heading_error),
+	)
+}
+
+// derivatives of d with respect to s are found from the time derivatives in the frenet state
+// if the robot isn't moving along the path (s_d = 0) they are taken to be zero
+#[must_use]
+pub fn cartesian_to_frenet_state<P: ReferencePath>(path: &P, state: CartesianState) -> FrenetState {
+	let FrenetPose {
+		s,
+		d,
+		heading_error,
+	} = to_frenet(path, state.pose);
+	let (kr, dkr) = (path.curvature(s), path.curvature_derivative(s));
+
+	let (sin, cos) = heading_error.sin_cos();
+	let tan = sin / cos;
+	let one_minus_kd = 1.0 - kr * d;
+
+	// derivatives of d with respect to s
+	let d_p = one_minus_kd * tan;
+	let kd_p = dkr * d + kr * d_p;
+	let d_pp =
+		-kd_p * tan + one_minus_kd / (cos * cos) * (state.curvature * one_minus_kd / cos - kr);
+
+	let s_d = state.speed * cos / one_minus_kd;
+	let heading_error_p = one_minus_kd / cos * state.curvature - kr;
+	let s_dd =
+		(state.acceleration * cos - s_d * s_d * (d_p * heading_error_p - kd_p)) / one_minus_kd;
+
+	FrenetState {
+		s,
+		s_d,
+		s_dd,
+		d,
+		d_d: d_p * s_d,
+		d_dd: d_pp * s_d * s_d + d_p * s_dd,
+	}
+}
+
+#[must_use]
+pub fn frenet_to_cartesian_state<P: ReferencePath>(path: &P, state: FrenetState) -> CartesianState {
+	let (kr, dkr) = (path.curvature(state.s), path.curvature_derivative(state.s));
+	let d = state.d;
+
+	// derivatives of d with respect to s
+	let (d_p, d_pp) = if state.s_d == 0.0 {
+		(0.0, 0.0)
+	} else {
+		let d_p = state.d_d / state.s_d;
+		(
+			d_p,
+			(state.d_dd - d_p * state.s_dd) / (state.s_d * state.s_d),
+		)
+	};
+
+	let one_minus_kd = 1.0 - kr * d;
+	let heading_error = d_p.atan2(one_minus_kd);
+	let (sin, cos) = heading_error.sin_cos();
+	let tan = sin / cos;
+	let kd_p = dkr * d + kr * d_p;
+
+	let curvature = ((d_pp + kd_p * tan) * cos * cos / one_minus_kd + kr) * cos / one_minus_kd;
+	let heading_error_p = one_minus_kd / cos * curvature - kr;
+
+	let speed =
+		(one_minus_kd * one_minus_kd * state.s_d * state.s_d + state.d_d * state.d_d).sqrt();
+	let acceleration = state.s_dd * one_minus_kd / cos
+		+ state.s_d * state.s_d / cos * (d_p * heading_error_p - kd_p);
+
+	CartesianState {
+		pose: from_frenet(
+			path,
+			FrenetPose {
+				s: state.s,
+				d,
+				heading_error,
+			},
+		),
+		speed,
+		acceleration,
+		curvature,
+	}
+}
+
+impl ReferencePath for CubicSpline2D {
+	fn length(&self) -> f64 {
+		CubicSpline2D::length(self)
+	}
+	fn pose(&self, s: f64) -> Ray {
+		let s = s.clamp(0.0, CubicSpline2D::length(self));
+		Ray::new(self.evaluate_unchecked(s), self.heading(s))
+	}
+	fn curvature(&self, s: f64) -> f64 {
+		CubicSpline2D::curvature(self, s)
+	}
+	fn curvature_derivative(&self, s: f64) -> f64 {
+		CubicSpline2D::curvature_derivative(self, s)
+	}
+	// coarse search over the spline followed by newton's method
+	fn project(&self, point: Pos2) -> f64 {
+		let length = CubicSpline2D::length(self);
+		let mut best = (f64::INFINITY, 0.0);
+		for w in self.knots().windows(2) {
+			for i in 0..PROJECTION_SAMPLES {
+				let s = w[0] + (w[1] - w[0]) * i as f64 / PROJECTION_SAMPLES as f64;
+				let d_sq = (self.evaluate_unchecked(s) - point).magnitude_squared();
+				if d_sq < best.0 {
+					best = (d_sq, s);
+				}
+			}
+		}
+		if (self.evaluate_unchecked(length) - point).magnitude_squared() < best.0 {
+			best.1 = length;
+		}
+
+		// minimise |r(s) - p|^2 by finding the root of (r(s) - p) . r'(s)
+		let mut s = best.1;
+		for _ in 0..10 {
+			let offset = self.evaluate_unchecked(s) - point;
+			let tangent = self.derivative(s);
+			let f = offset.dot(&tangent);
+			let df = tangent.magnitude_squared() + offset.dot(&self.second_derivative(s));
+			if df <= 0.0 {
+				break;
+			}
+			let next = (s - f / df).clamp(0.0, length);
+			if (next - s).abs() < 1e-12 {
+				s = next;
+				break;
+			}
+			s = next;
+		}
+		s
+	}
+}
+
+const PROJECTION_SAMPLES: usize = 8;
+
+// piecewise linear path, the heading is constant along each segment
+// the curvature of each segment is found from the change in heading at its ends
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+	points: Vec<Pos2>,
+	// cumulative distance to each point
+	s: Vec<f64>,
+	headings: Vec<f64>,
+	curvatures: Vec<f64>,
+}
+
+impl Polyline {
+	// consecutive duplicate points are removed, None if less than two distinct points
+	#[must_use]
+	pub fn new(points: &[Vec2]) -> Option<Self> {
+		let points: Vec<Pos2> = points.iter().map(|&p| p.into()).collect();
+		Self::with_headings(&points, None)
+	}
+	// from the sampled output of Dubins::get_points or ReedsShepp::get_points
+	// the headings of the samples are used for the curvature
+	// None for samples that reverse, s can't follow the path back from a cusp
+	#[must_use]
+	pub fn from_samples(samples: &[(Ray, PathSegmentType)]) -> Option<Self> {
+		if samples.iter().any(|v| gear(v.1) < 0.0) {
+			return None;
+		}
+		let points: Vec<Pos2> = samples.iter().map(|v| v.0.pos).collect();
+		let headings: Vec<f64> = samples.iter().map(|v| v.0.angle).collect();
+		Self::with_headings(&points, Some(&headings))
+	}
+	fn with_headings(points: &[Pos2], headings: Option<&[f64]>) -> Option<Self> {
+		let mut kept: Vec<(Pos2, Option<f64>)> = Vec::with_capacity(points.len());
+		for (i, &p) in points.iter().enumerate() {
+			if kept.last().is_none_or(|v| v.0 != p) {
+				kept.push((p, headings.map(|h| h[i])));
+			}
+		}
+		if kept.len() < 2 {
+			return None;
+		}
+
+		let segment_headings: Vec<f64> = kept
+			.windows(2)
+			.map(|w| (w[1].0.y - w[0].0.y).atan2(w[1].0.x - w[0].0.x))
+			.collect();
+		let mut s = Vec::with_capacity(kept.len());
+		s.push(0.0);
+		for w in kept.windows(2) {
+			s.push(s[s.len() - 1] + (w[1].0 - w[0].0).magnitude());
+		}
+
+		// heading at each vertex, either given or half way between the neighbouring segments
+		let n = segment_headings.len();
+		let vertex_headings: Vec<f64> = (0..kept.len())
+			.map(|i| {
+				kept[i].1.unwrap_or_else(|| match i {
+					0 => segment_headings[0],
+					_ if i == n => segment_headings[n - 1],
+					_ => {
+						segment_headings[i - 1]
+							+ 0.5 * map_angle(segment_headings[i] - segment_headings[i - 1])
+					}
+				})
+			})
+			.collect();
+		let curvatures = (0..n)
+			.map(|i| map_angle(vertex_headings[i + 1] - vertex_headings[i]) / (s[i + 1] - s[i]))
+			.collect();
+
+		Some(Self {
+			points: kept.into_iter().map(|v| v.0).collect(),
+			s,
+			headings: segment_headings,
+			curvatures,
+		})
+	}
+	#[must_use]
+	pub fn points(&self) -> &[Pos2] {
+		&self.points
+	}
+	fn segment(&self, s: f64) -> usize {
+		self.s
+			.partition_point(|&v| v <= s)
+			.saturating_sub(1)
+			.min(self.headings.len() - 1)
+	}
+}
+
+impl ReferencePath for Polyline {
+	fn length(&self) -> f64 {
+		self.s[self.s.len() - 1]
+	}
+	fn pose(&self, s: f64) -> Ray {
+		let i = self.segment(s);
+		let t = s - self.s[i];
+		let heading = self.headings[i];
+		Ray::new(
+			self.points[i] + t * Vec2::new(heading.cos(), heading.sin()),
+			heading,
+		)
+	}
+	fn curvature(&self, s: f64) -> f64 {
+		self.curvatures[self.segment(s)]
+	}
+	fn curvature_derivative(&self, _: f64) -> f64 {
+		0.0
+	}
+	fn project(&self, point: Pos2) -> f64 {
+		self.points
+			.windows(2)
+			.enumerate()
+			.map(|(i, w)| {
+				let seg = w[1] - w[0];
+				let t = ((point - w[0]).dot(&seg) / seg.magnitude_squared()).clamp(0.0, 1.0);
+				let d_sq = (w[0] + t * seg - point).magnitude_squared();
+				(d_sq, self.s[i] + t * (self.s[i + 1] - self.s[i]))
+			})
+			.min_by(|a, b| float_cmp(a.0, b.0))
+			.map_or(0.0, |v| v.1)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::path_planning::{Dubins, ReedsShepp};
+	use core::f64::consts::{FRAC_PI_2, PI};
+
+	#[test]
+	fn polyline_round_trip() {
+		let path = Polyline::new(&[
+			Vec2::new(0.0, 0.0),
+			Vec2::new(10.0, 0.0),
+			Vec2::new(10.0, 10.0),
+		])
+		.unwrap();
+		let pose = Ray::new(Pos2::new(3.0, 2.0), 0.1);
+		let frenet = to_frenet(&path, pose);
+		assert!((frenet.s - 3.0).abs() < 1e-12);
+		assert!((frenet.d - 2.0).abs() < 1e-12);
+		assert!((frenet.heading_error - 0.1).abs() < 1e-12);
+
+		// right of the second segment
+		let frenet = to_frenet(&path, Ray::new(Pos2::new(11.0, 4.0), 0.0));
+		assert!((frenet.s - 14.0).abs() < 1e-12);
+		assert!((frenet.d + 1.0).abs() < 1e-12);
+
+		let back = from_frenet(&path, frenet);
+		assert!((back.pos - Pos2::new(11.0, 4.0)).magnitude() < 1e-12);
+		assert!(back.angle.abs() < 1e-12);
+	}
+
+	#[test]
+	fn spline_state_round_trip() {
+		let path = CubicSpline2D::new(&[
+			Pos2::new(0.0, 0.0),
+			Pos2::new(10.0, -6.0),
+			Pos2::new(20.5, 5.0),
+			Pos2::new(35.0, 6.5),
+			Pos2::new(70.5, 0.0),
+		])
+		.unwrap();
+
+		let state = CartesianState {
+			pose: Ray::new(Pos2::new(15.0, 1.0), 0.9),
+			speed: 4.0,
+			acceleration: 0.5,
+			curvature: 0.05,
+		};
+		let frenet = cartesian_to_frenet_state(&path, state);
+		// projection is the closest point
+		let closest = path.evaluate_unchecked(frenet.s);
+		assert!((path.derivative(frenet.s).dot(&(closest - state.pose.pos))).abs() < 1e-9);
+
+		let back = frenet_to_cartesian_state(&path, frenet);
+		assert!((back.pose.pos - state.pose.pos).magnitude() < 1e-9);
+		assert!((back.pose.angle - state.pose.angle).abs() < 1e-9);
+		assert!((back.speed - state.speed).abs() < 1e-9);
+		assert!((back.acceleration - state.acceleration).abs() < 1e-9);
+		assert!((back.curvature - state.curvature).abs() < 1e-9);
+	}
+
+	// counter-clockwise circle around the origin starting on the x axis
+	struct Circle(f64);
+
+	impl ReferencePath for Circle {
+		fn length(&self) -> f64 {
+			PI * self.0
+		}
+		fn pose(&self, s: f64) -> Ray {
+			let theta = s / self.0;
+			Ray::new(
+				Pos2::new(self.0 * theta.cos(), self.0 * theta.sin()),
+				theta + FRAC_PI_2,
+			)
+		}
+		fn curvature(&self, _: f64) -> f64 {
+			1.0 / self.0
+		}
+		fn curvature_derivative(&self, _: f64) -> f64 {
+			0.0
+		}
+		fn project(&self, point: Pos2) -> f64 {
+			point.y.atan2(point.x) * self.0
+		}
+	}
+
+	#[test]
+	fn circle_state() {
+		// driving round a concentric circle inside the reference
+		let (r, d, theta): (f64, f64, f64) = (10.0, 2.0, 1.2);
+		let state = CartesianState {
+			pose: Ray::new(
+				Pos2::new((r - d) * theta.cos(), (r - d) * theta.sin()),
+				theta + FRAC_PI_2,
+			),
+			speed: 4.0,
+			acceleration: 0.5,
+			curvature: 1.0 / (r - d),
+		};
+		let scale = r / (r - d);
+		let expected = FrenetState {
+			s: r * theta,
+			s_d: state.speed * scale,
+			s_dd: state.acceleration * scale,
+			d,
+			d_d: 0.0,
+			d_dd: 0.0,
+		};
+
+		let frenet = cartesian_to_frenet_state(&Circle(r), state);
+		assert!((frenet.s - expected.s).abs() < 1e-9);
+		assert!((frenet.s_d - expected.s_d).abs() < 1e-9);
+		assert!((frenet.s_dd - expected.s_dd).abs() < 1e-9);
+		assert!((frenet.d - expected.d).abs() < 1e-9);
+		assert!(frenet.d_d.abs() < 1e-9 && frenet.d_dd.abs() < 1e-9);
+
+		let back = frenet_to_cartesian_state(&Circle(r), expected);
+		assert!((back.pose.pos - state.pose.pos).magnitude() < 1e-9);
+		assert!((back.speed - state.speed).abs() < 1e-9);
+		assert!((back.acceleration - state.acceleration).abs() < 1e-9);
+		assert!((back.curvature - state.curvature).abs() < 1e-9);
+
+		// a spline through points on the circle gives nearly the same state
+		let spline = CubicSpline2D::new(
+			&(0..=36)
+				.map(|i| Circle(r).pose(f64::from(i) * PI * r / 36.0).pos)
+				.collect::<Vec<_>>(),
+		)
+		.unwrap();
+		let frenet = cartesian_to_frenet_state(&spline, state);
+		assert!((frenet.s - expected.s).abs() < 1e-3);
+		assert!((frenet.s_d - expected.s_d).abs() < 1e-3);
+		assert!((frenet.d - expected.d).abs() < 1e-3);
+		// the curvature of the spline wiggles slightly between the points
+		assert!((frenet.s_dd - expected.s_dd).abs() < 0.05);
+	}
+
+	#[test]
+	fn dubins_samples() {
+		let dubins = Dubins::new(
+			Ray::new(Pos2::new(0.0, 0.0), 0.0),
+			Ray::new(Pos2::new(5.0, 5.0), 1.5),
+			0.5,
+		)
+		.unwrap();
+		let samples = dubins.get_points(0.05);
+		let path = Polyline::from_samples(&samples).unwrap();
+
+		let frenet = to_frenet(&path, samples[20].0);
+		assert!(frenet.d.abs() < 1e-9);
+		assert!((frenet.s - path.s[20]).abs() < 1e-9);
+		// segments within the turns have the curvature of the turning circle
+		assert_eq!(path.points().len(), samples.len());
+		for (i, w) in samples.windows(2).enumerate() {
+			let s = 0.5 * (path.s[i] + path.s[i + 1]);
+			match (w[0].1, w[1].1) {
+				(PathSegmentType::Left(_), PathSegmentType::Left(_)) => {
+					assert!((path.curvature(s) - 0.5).abs() < 1e-3);
+				}
+				(PathSegmentType::Right(_), PathSegmentType::Right(_)) => {
+					assert!((path.curvature(s) + 0.5).abs() < 1e-3);
+				}
+				_ => {}
+			}
+		}
+
+		let reeds_shepp = ReedsShepp::new(
+			Ray::new(Pos2::new(0.0, 0.0), 0.0),
+			Ray::new(Pos2::new(-3.0, 1.0), 0.0),
+			0.5,
+		)
+		.unwrap();
+		assert!(Polyline::from_samples(&reeds_shepp.get_points(0.05)).is_none());
+	}
+}
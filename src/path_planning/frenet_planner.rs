@@ -0,0 +1,420 @@
+use super::{
+	cubic_spline::CubicSpline2D, quartic_polynomial::QuarticPolynomial1D,
+	quintic_polynomial::QuinticPolynomial1D,
+};
+use crate::prelude::*;
+
+// references:
+// https://doi.org/10.1109/ROBOT.2010.5509799 (Werling et al.)
+// https://github.com/AtsushiSakai/PythonRobotics/tree/master/PathPlanning/FrenetOptimalTrajectory
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrenetError {
+	InvalidInput,
+	NoValidTrajectory,
+}
+
+// s is distance along the reference path and d is the lateral offset (left positive)
+// with derivatives taken with respect to time
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct FrenetState {
+	pub s: f64,
+	pub s_d: f64,
+	pub s_dd: f64,
+	pub d: f64,
+	pub d_d: f64,
+	pub d_dd: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LongitudinalTarget {
+	// velocity keeping with a quartic, the end position is free
+	Speed(f64),
+	// reach a position along the path with a quintic, e.g. for stopping or following
+	Position { s: f64, speed: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrenetTrajectory {
+	pub times: Vec<f64>,
+	pub states: Vec<FrenetState>,
+	// cartesian poses of the states, shorter than states if the trajectory
+	// runs past the end of the reference path which makes it invalid
+	pub points: Vec<Ray>,
+	pub curvatures: Vec<f64>,
+	pub cost: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrenetPlanner {
+	pub max_speed: f64,
+	pub max_acceleration: f64,
+	pub max_curvature: f64,
+	// lateral end offsets are sampled in [-max_road_width, max_road_width]
+	pub max_road_width: f64,
+	pub lateral_step: f64,
+	// trajectory durations are sampled in [min_time, max_time]
+	pub min_time: f64,
+	pub max_time: f64,
+	pub time_step: f64,
+	// time between samples along each trajectory
+	pub dt: f64,
+	// number of samples either side of the longitudinal target and the step between them
+	pub longitudinal_samples: usize,
+	pub longitudinal_step: f64,
+	pub robot_radius: f64,
+	// cost weights for jerk, time and deviation from the target
+	pub k_jerk: f64,
+	pub k_time: f64,
+	pub k_deviation: f64,
+	pub k_lateral: f64,
+	pub k_longitudinal: f64,
+}
+
+impl Default for FrenetPlanner {
+	fn default() -> Self {
+		Self {
+			max_speed: 50.0 / 3.6,
+			max_acceleration: 2.0,
+			max_curvature: 1.0,
+			max_road_width: 7.0,
+			lateral_step: 1.0,
+			min_time: 4.0,
+			max_time: 5.0,
+			time_step: 0.2,
+			dt: 0.2,
+			longitudinal_samples: 1,
+			longitudinal_step: 5.0 / 3.6,
+			robot_radius: 2.0,
+			k_jerk: 0.1,
+			k_time: 0.1,
+			k_deviation: 1.0,
+			k_lateral: 1.0,
+			k_longitudinal: 1.0,
+		}
+	}
+}
+
+impl FrenetPlanner {
+	// lowest cost trajectory that satisfies the limits and avoids the obstacles
+	pub fn plan(
+		&self,
+		reference: &CubicSpline2D,
+		start: FrenetState,
+		target: LongitudinalTarget,
+		obstacles: &[Pos2],
+	) -> Result<FrenetTrajectory, FrenetError> {
+		self.candidates(reference, start, target)?
+			.into_iter()
+			.filter(|t| self.is_valid(t, obstacles))
+			.min_by(|a, b| float_cmp(a.cost, b.cost))
+			.ok_or(FrenetError::NoValidTrajectory)
+	}
+	// every sampled trajectory without any validity checks
+	pub fn candidates(
+		&self,
+		reference: &CubicSpline2D,
+		start: FrenetState,
+		target: LongitudinalTarget,
+	) -> Result<Vec<FrenetTrajectory>, FrenetError> {
+		if self.lateral_step <= 0.0
+			|| self.time_step <= 0.0
+			|| self.dt <= 0.0
+			|| self.min_time <= 0.0
+			|| self.max_time < self.min_time
+			|| self.max_road_width < 0.0
+		{
+			return Err(FrenetError::InvalidInput);
+		}
+
+		let lateral_count = (2.0 * self.max_road_width / self.lateral_step).round() as usize;
+		let time_count = ((self.max_time - self.min_time) / self.time_step).round() as usize;
+		let n = self.longitudinal_samples as i64;
+
+		let mut trajectories = Vec::new();
+		for i in 0..=lateral_count {
+			let d_end = -self.max_road_width + i as f64 * self.lateral_step;
+			for j in 0..=time_count {
+				let duration = self.min_time + j as f64 * self.time_step;
+				let lateral = QuinticPolynomial1D::new(
+					(start.d, start.d_d, start.d_dd),
+					(d_end, 0.0, 0.0),
+					duration,
+				)
+				.map_err(|_| FrenetError::InvalidInput)?;
+
+				for k in -n..=n {
+					let offset = k as f64 * self.longitudinal_step;
+					let trajectory = match target {
+						LongitudinalTarget::Speed(speed) => {
+							let target_speed = speed + offset;
+							let longitudinal = QuarticPolynomial1D::new(
+								(start.s, start.s_d, start.s_dd),
+								(target_speed, 0.0),
+								duration,
+							)
+							.map_err(|_| FrenetError::InvalidInput)?;
+							self.build(reference, &lateral, duration, target_speed, |t| {
+								(
+									longitudinal.evaluate_unchecked(t),
+									longitudinal.velocity(t),
+									longitudinal.acceleration(t),
+									longitudinal.jerk(t),
+								)
+							})
+						}
+						LongitudinalTarget::Position { s, speed } => {
+							let longitudinal = QuinticPolynomial1D::new(
+								(start.s, start.s_d, start.s_dd),
+								(s + offset, speed, 0.0),
+								duration,
+							)
+							.map_err(|_| FrenetError::InvalidInput)?;
+							self.build(reference, &lateral, duration, speed, |t| {
+								(
+									longitudinal.evaluate_unchecked(t),
+									longitudinal.velocity(t),
+									longitudinal.acceleration(t),
+									longitudinal.jerk(t),
+								)
+							})
+						}
+					};
+					trajectories.push(trajectory);
+				}
+			}
+		}
+
+		Ok(trajectories)
+	}
+	fn build<F>(
+		&self,
+		reference: &CubicSpline2D,
+		lateral: &QuinticPolynomial1D,
+		duration: f64,
+		target_speed: f64,
+		longitudinal: F,
+	) -> FrenetTrajectory
+	where
+		F: Fn(f64) -> (f64, f64, f64, f64),
+	{
+		let steps = (duration / self.dt).round() as usize;
+		let mut times = Vec::with_capacity(steps + 1);
+		let mut states = Vec::with_capacity(steps + 1);
+		let (mut lateral_jerk, mut longitudinal_jerk) = (0.0, 0.0);
+
+		for i in 0..=steps {
+			let t = (i as f64 * self.dt).min(duration);
+			let (s, s_d, s_dd, s_ddd) = longitudinal(t);
+			let d_ddd = lateral.jerk(t);
+			lateral_jerk += d_ddd * d_ddd;
+			longitudinal_jerk += s_ddd * s_ddd;
+
+			times.push(t);
+			states.push(FrenetState {
+				s,
+				s_d,
+				s_dd,
+				d: lateral.evaluate_unchecked(t),
+				d_d: lateral.velocity(t),
+				d_dd: lateral.acceleration(t),
+			});
+		}
+
+		let end = states[states.len() - 1];
+		let lateral_cost =
+			self.k_jerk * lateral_jerk + self.k_time * duration + self.k_deviation * end.d * end.d;
+		let speed_error = target_speed - end.s_d;
+		let longitudinal_cost = self.k_jerk * longitudinal_jerk
+			+ self.k_time * duration
+			+ self.k_deviation * speed_error * speed_error;
+
+		let (points, curvatures) = to_cartesian(reference, &states);
+
+		FrenetTrajectory {
+			times,
+			states,
+			points,
+			curvatures,
+			cost: self.k_lateral * lateral_cost + self.k_longitudinal * longitudinal_cost,
+		}
+	}
+	#[must_use]
+	pub fn is_valid(&self, trajectory: &FrenetTrajectory, obstacles: &[Pos2]) -> bool {
+		let r_sq = self.robot_radius * self.robot_radius;
+		trajectory.points.len() >= 2
+			&& trajectory.points.len() == trajectory.states.len()
+			&& trajectory
+				.states
+				.iter()
+				.all(|v| v.s_d <= self.max_speed && v.s_dd.abs() <= self.max_acceleration)
+			&& trajectory
+				.curvatures
+				.iter()
+				.all(|k| k.abs() <= self.max_curvature)
+			&& trajectory.points.iter().all(|p| {
+				obstacles
+					.iter()
+					.all(|o| (o - p.pos).magnitude_squared() > r_sq)
+			})
+	}
+}
+
+// heading and curvature are found from differences between neighbouring points
+fn to_cartesian(reference: &CubicSpline2D, states: &[FrenetState]) -> (Vec<Ray>, Vec<f64>) {
+	let positions: Vec<Pos2> = states
+		.iter()
+		.take_while(|v| v.s <= reference.length())
+		.map(|v| {
+			let base = reference.evaluate_unchecked(v.s);
+			let yaw = reference.heading(v.s);
+			base + v.d * Vec2::new(-yaw.sin(), yaw.cos())
+		})
+		.collect();
+
+	if positions.len() < 2 {
+		return (
+			positions.into_iter().map(|p| Ray::new(p, 0.0)).collect(),
+			Vec::new(),
+		);
+	}
+
+	let mut headings: Vec<f64> = positions
+		.windows(2)
+		.map(|w| (w[1].y - w[0].y).atan2(w[1].x - w[0].x))
+		.collect();
+	headings.push(headings[headings.len() - 1]);
+
+	let curvatures = positions
+		.windows(2)
+		.zip(headings.windows(2))
+		.map(|(p, h)| {
+			let ds = (p[1] - p[0]).magnitude();
+			if ds == 0.0 {
+				0.0
+			} else {
+				map_angle(h[1] - h[0]) / ds
+			}
+		})
+		.collect();
+
+	let points = positions
+		.into_iter()
+		.zip(headings)
+		.map(|(p, h)| Ray::new(p, h))
+		.collect();
+
+	(points, curvatures)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn reference() -> CubicSpline2D {
+		CubicSpline2D::new(&[
+			Pos2::new(0.0, 0.0),
+			Pos2::new(10.0, -6.0),
+			Pos2::new(20.5, 5.0),
+			Pos2::new(35.0, 6.5),
+			Pos2::new(70.5, 0.0),
+		])
+		.unwrap()
+	}
+
+	#[test]
+	fn avoids_obstacles() {
+		let reference = reference();
+		let planner = FrenetPlanner::default();
+		let obstacles = [
+			Pos2::new(20.0, 10.0),
+			Pos2::new(30.0, 6.0),
+			Pos2::new(30.0, 8.0),
+			Pos2::new(35.0, 8.0),
+			Pos2::new(50.0, 3.0),
+		];
+		let mut state = FrenetState {
+			s_d: 10.0 / 3.6,
+			d: 2.0,
+			..Default::default()
+		};
+
+		// drive along the path replanning every step
+		for _ in 0..100 {
+			let trajectory = planner
+				.plan(
+					&reference,
+					state,
+					LongitudinalTarget::Speed(30.0 / 3.6),
+					&obstacles,
+				)
+				.unwrap();
+			assert!(planner.is_valid(&trajectory, &obstacles));
+			state = trajectory.states[1];
+			if state.s > 45.0 {
+				break;
+			}
+		}
+		assert!(state.s > 45.0);
+	}
+
+	#[test]
+	fn returns_to_reference() {
+		let reference = reference();
+		let planner = FrenetPlanner::default();
+		let start = FrenetState {
+			s_d: 5.0,
+			d: 1.0,
+			..Default::default()
+		};
+		let trajectory = planner
+			.plan(&reference, start, LongitudinalTarget::Speed(5.0), &[])
+			.unwrap();
+		let end = trajectory.states[trajectory.states.len() - 1];
+		assert!(end.d.abs() < 1e-9);
+		assert!((end.s_d - 5.0).abs() < 1e-9);
+
+		// stopping at a position
+		let trajectory = planner
+			.plan(
+				&reference,
+				start,
+				LongitudinalTarget::Position {
+					s: 12.0,
+					speed: 0.0,
+				},
+				&[],
+			)
+			.unwrap();
+		let end = trajectory.states[trajectory.states.len() - 1];
+		assert!(end.s_d.abs() < 1e-9);
+		assert!((end.s - 12.0).abs() < 5.0 / 3.6 + 1e-9);
+	}
+
+	#[test]
+	fn stays_on_reference() {
+		let reference = reference();
+		let planner = FrenetPlanner::default();
+		let start = FrenetState {
+			s: reference.length() - 15.0,
+			s_d: 5.0,
+			..Default::default()
+		};
+		let target = LongitudinalTarget::Speed(5.0);
+		// every candidate drives off the end of the reference
+		let candidates = planner.candidates(&reference, start, target).unwrap();
+		assert!(candidates.iter().all(|t| t.points.len() < t.states.len()));
+		assert_eq!(
+			planner.plan(&reference, start, target, &[]),
+			Err(FrenetError::NoValidTrajectory)
+		);
+
+		// stopping before the end is fine
+		let target = LongitudinalTarget::Position {
+			s: reference.length() - 1.0,
+			speed: 0.0,
+		};
+		let trajectory = planner.plan(&reference, start, target, &[]).unwrap();
+		assert_eq!(trajectory.points.len(), trajectory.states.len());
+	}
+}
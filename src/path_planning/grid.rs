@@ -0,0 +1,263 @@
+use crate::prelude::*;
+use core::{cmp::Ordering, f64::consts::SQRT_2};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GridError {
+	InvalidInput,
+	OutOfBounds,
+	PathNotFound,
+}
+
+// (x, y) index of a cell
+pub type Cell = (usize, usize);
+
+// entry in a best first search queue, ordered so BinaryHeap pops the lowest cost
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct OpenNode {
+	pub cost: f64,
+	pub index: usize,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+	fn cmp(&self, other: &Self) -> Ordering {
+		float_cmp(other.cost, self.cost)
+	}
+}
+
+impl PartialOrd for OpenNode {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+// 8 connected grid where each cell has a traversal cost of at least 1
+// moving between cells costs the distance times the mean cost of the two cells
+// blocked cells have infinite cost and diagonal moves can't cut their corners
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+	// corner of cell (0, 0)
+	pub origin: Pos2,
+	pub resolution: f64,
+	width: usize,
+	height: usize,
+	costs: Vec<f64>,
+}
+
+impl Grid {
+	pub fn new(
+		width: usize,
+		height: usize,
+		resolution: f64,
+		origin: Pos2,
+	) -> Result<Self, GridError> {
+		if width == 0 || height == 0 || resolution <= 0.0 {
+			return Err(GridError::InvalidInput);
+		}
+		Ok(Self {
+			origin,
+			resolution,
+			width,
+			height,
+			costs: Vec::from([1.0]).repeat(width * height),
+		})
+	}
+	// row major with y increasing
+	pub fn from_occupancy(
+		occupied: &[bool],
+		width: usize,
+		resolution: f64,
+		origin: Pos2,
+	) -> Result<Self, GridError> {
+		if width == 0 || !occupied.len().is_multiple_of(width) {
+			return Err(GridError::InvalidInput);
+		}
+		let mut grid = Self::new(width, occupied.len() / width, resolution, origin)?;
+		for (cost, &occupied) in grid.costs.iter_mut().zip(occupied) {
+			if occupied {
+				*cost = f64::INFINITY;
+			}
+		}
+		Ok(grid)
+	}
+	#[must_use]
+	pub fn width(&self) -> usize {
+		self.width
+	}
+	#[must_use]
+	pub fn height(&self) -> usize {
+		self.height
+	}
+	// infinite outside the grid
+	#[must_use]
+	pub fn cost(&self, cell: Cell) -> f64 {
+		if self.contains(cell) {
+			self.costs[self.index(cell)]
+		} else {
+			f64::INFINITY
+		}
+	}
+	// cost must be at least 1, infinity blocks the cell
+	pub fn set_cost(&mut self, cell: Cell, cost: f64) -> Result<(), GridError> {
+		if !self.contains(cell) {
+			return Err(GridError::OutOfBounds);
+		}
+		if cost.is_nan() || cost < 1.0 {
+			return Err(GridError::InvalidInput);
+		}
+		let i = self.index(cell);
+		self.costs[i] = cost;
+		Ok(())
+	}
+	#[must_use]
+	pub fn is_free(&self, cell: Cell) -> bool {
+		self.cost(cell).is_finite()
+	}
+	#[must_use]
+	pub fn contains(&self, cell: Cell) -> bool {
+		cell.0 < self.width && cell.1 < self.height
+	}
+	#[must_use]
+	pub fn cell(&self, point: Pos2) -> Option<Cell> {
+		let p = (point - self.origin) / self.resolution;
+		if p.x < 0.0 || p.y < 0.0 {
+			return None;
+		}
+		let cell = (p.x as usize, p.y as usize);
+		self.contains(cell).then_some(cell)
+	}
+	#[must_use]
+	pub fn centre(&self, cell: Cell) -> Pos2 {
+		self.origin + Vec2::new(cell.0 as f64 + 0.5, cell.1 as f64 + 0.5) * self.resolution
+	}
+	// every cell inside the grid next to this one, including blocked ones
+	pub fn adjacent(&self, cell: Cell) -> impl Iterator<Item = Cell> + '_ {
+		let (x, y) = (cell.0 as i64, cell.1 as i64);
+		(-1..=1)
+			.flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+			.filter(move |&(nx, ny)| {
+				(nx, ny) != (x, y)
+					&& nx >= 0 && ny >= 0
+					&& (nx as usize) < self.width
+					&& (ny as usize) < self.height
+			})
+			.map(|(nx, ny)| (nx as usize, ny as usize))
+	}
+	// neighbours that can be moved to with the cost of the move
+	pub fn neighbours(&self, cell: Cell) -> impl Iterator<Item = (Cell, f64)> + '_ {
+		self.adjacent(cell).filter_map(move |n| {
+			let cost = self.move_cost(cell, n);
+			cost.is_finite().then_some((n, cost))
+		})
+	}
+	// infinite unless the cells are adjacent and the move is free
+	#[must_use]
+	pub fn move_cost(&self, a: Cell, b: Cell) -> f64 {
+		let (dx, dy) = (a.0.abs_diff(b.0), a.1.abs_diff(b.1));
+		if dx > 1 || dy > 1 || (dx, dy) == (0, 0) {
+			return f64::INFINITY;
+		}
+		let mean = 0.5 * (self.cost(a) + self.cost(b));
+		if dx + dy == 2 {
+			if !self.is_free((a.0, b.1)) || !self.is_free((b.0, a.1)) {
+				return f64::INFINITY;
+			}
+			return SQRT_2 * self.resolution * mean;
+		}
+		self.resolution * mean
+	}
+	// octile distance, a lower bound on the cost between two cells
+	#[must_use]
+	pub fn heuristic(&self, a: Cell, b: Cell) -> f64 {
+		let (dx, dy) = (a.0.abs_diff(b.0) as f64, a.1.abs_diff(b.1) as f64);
+		(dx.max(dy) + (SQRT_2 - 1.0) * dx.min(dy)) * self.resolution
+	}
+	// the straight line between the cell centres only passes through free cells
+	// and doesn't squeeze between two blocked cells touching at a corner
+	#[must_use]
+	pub fn line_of_sight(&self, a: Cell, b: Cell) -> bool {
+		if !self.is_free(a) || !self.is_free(b) {
+			return false;
+		}
+		// walk the cells the line crosses in order
+		let (dx, dy) = (b.0 as i64 - a.0 as i64, b.1 as i64 - a.1 as i64);
+		let (sx, sy) = (dx.signum(), dy.signum());
+		let step = |d: i64| {
+			if d == 0 {
+				f64::INFINITY
+			} else {
+				1.0 / d.unsigned_abs() as f64
+			}
+		};
+		let (delta_x, delta_y) = (step(dx), step(dy));
+		let (mut next_x, mut next_y) = (0.5 * delta_x, 0.5 * delta_y);
+		let (mut x, mut y) = (a.0 as i64, a.1 as i64);
+		let free = |x: i64, y: i64| x >= 0 && y >= 0 && self.is_free((x as usize, y as usize));
+
+		for _ in 0..dx.abs() + dy.abs() {
+			if (next_x - next_y).abs() < 1e-12 {
+				if !free(x + sx, y) || !free(x, y + sy) {
+					return false;
+				}
+				x += sx;
+				y += sy;
+				next_x += delta_x;
+				next_y += delta_y;
+			} else if next_x < next_y {
+				x += sx;
+				next_x += delta_x;
+			} else {
+				y += sy;
+				next_y += delta_y;
+			}
+			if !free(x, y) {
+				return false;
+			}
+			if (x, y) == (b.0 as i64, b.1 as i64) {
+				break;
+			}
+		}
+		true
+	}
+	pub(crate) fn index(&self, cell: Cell) -> usize {
+		cell.1 * self.width + cell.0
+	}
+	pub(crate) fn cell_at(&self, index: usize) -> Cell {
+		(index % self.width, index / self.width)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cells_and_costs() {
+		#[rustfmt::skip]
+		let occupied = [
+			false, false, false,
+			false, true,  false,
+			false, false, false,
+		];
+		let mut grid = Grid::from_occupancy(&occupied, 3, 0.5, Pos2::new(-1.0, 0.0)).unwrap();
+		assert_eq!(grid.cell(Pos2::new(-0.1, 0.6)), Some((1, 1)));
+		assert_eq!(grid.cell(Pos2::new(-1.1, 0.6)), None);
+		assert_eq!(grid.centre((1, 1)), Pos2::new(-0.25, 0.75));
+
+		// the blocked centre stops diagonal moves around it
+		assert_eq!(grid.neighbours((0, 0)).count(), 2);
+		assert!((grid.move_cost((0, 0), (1, 0)) - 0.5).abs() < 1e-12);
+		assert!(grid.move_cost((0, 0), (1, 1)).is_infinite());
+
+		grid.set_cost((1, 0), 3.0).unwrap();
+		assert!((grid.move_cost((0, 0), (1, 0)) - 1.0).abs() < 1e-12);
+		assert_eq!(grid.set_cost((1, 0), 0.5), Err(GridError::InvalidInput));
+		assert_eq!(grid.set_cost((3, 0), 2.0), Err(GridError::OutOfBounds));
+
+		assert!(grid.line_of_sight((0, 0), (2, 0)));
+		assert!(!grid.line_of_sight((0, 0), (2, 1)));
+		assert!(!grid.line_of_sight((0, 0), (2, 2)));
+		assert!(!grid.line_of_sight((0, 1), (2, 1)));
+	}
+}
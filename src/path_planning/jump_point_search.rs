@@ -0,0 +1,280 @@
+use super::grid::{Cell, Grid, GridError, OpenNode};
+use crate::prelude::*;
+
+// references:
+// https://doi.org/10.1609/aaai.v25i1.7994 (Harabor & Grastien, JPS)
+// https://doi.org/10.1609/icaps.v24i1.13633 (Harabor & Grastien, JPS+)
+// --------
+// cell costs are ignored, only whether a cell is blocked matters
+// diagonal moves can't cut corners which changes the forced neighbour rules:
+// moving straight a cell is a jump point when a side cell is free but the side
+// cell behind it is blocked, moving diagonally a cell is a jump point when a
+// straight jump from it finds one
+// straight jump distances are precomputed for every cell (JPS+) so only
+// diagonal jumps walk the grid
+// --------
+
+// east, west, north, south
+const DIRECTIONS: [(i64, i64); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JumpPointSearch {
+	grid: Grid,
+	// for each cell and direction the distance to the next jump point when
+	// positive, otherwise minus the number of free cells before a wall
+	jumps: Vec<[i64; 4]>,
+}
+
+impl JumpPointSearch {
+	#[must_use]
+	pub fn new(grid: Grid) -> Self {
+		let mut search = Self {
+			jumps: Vec::from([[0; 4]]).repeat(grid.width() * grid.height()),
+			grid,
+		};
+		let (w, h) = (search.grid.width() as i64, search.grid.height() as i64);
+		for (direction, &(dx, dy)) in DIRECTIONS.iter().enumerate() {
+			// scan against the direction so the next cell is always done first
+			let xs: Vec<i64> = if dx > 0 {
+				(0..w).rev().collect()
+			} else {
+				(0..w).collect()
+			};
+			let ys: Vec<i64> = if dy > 0 {
+				(0..h).rev().collect()
+			} else {
+				(0..h).collect()
+			};
+			for &y in &ys {
+				for &x in &xs {
+					let (nx, ny) = (x + dx, y + dy);
+					let jump = if !search.free(nx, ny) {
+						0
+					} else if search.forced(nx, ny, dx, dy) {
+						1
+					} else {
+						let next =
+							search.jumps[search.grid.index((nx as usize, ny as usize))][direction];
+						if next > 0 {
+							next + 1
+						} else {
+							next - 1
+						}
+					};
+					let i = search.grid.index((x as usize, y as usize));
+					search.jumps[i][direction] = jump;
+				}
+			}
+		}
+		search
+	}
+	#[must_use]
+	pub fn grid(&self) -> &Grid {
+		&self.grid
+	}
+	// jump points from start to goal, consecutive points are joined by a straight
+	// or diagonal line of free cells
+	pub fn plan(&self, start: Pos2, goal: Pos2) -> Result<Vec<Pos2>, GridError> {
+		let start = self.grid.cell(start).ok_or(GridError::OutOfBounds)?;
+		let goal = self.grid.cell(goal).ok_or(GridError::OutOfBounds)?;
+		if !self.grid.is_free(start) || !self.grid.is_free(goal) {
+			return Err(GridError::PathNotFound);
+		}
+		let n = self.grid.width() * self.grid.height();
+		let mut g = Vec::from([f64::INFINITY]).repeat(n);
+		let mut parent: Vec<Option<usize>> = Vec::from([None]).repeat(n);
+		let mut closed = Vec::from([false]).repeat(n);
+
+		let start_index = self.grid.index(start);
+		g[start_index] = 0.0;
+		let mut open = BinaryHeap::from([OpenNode {
+			cost: self.grid.heuristic(start, goal),
+			index: start_index,
+		}]);
+		while let Some(OpenNode { index, .. }) = open.pop() {
+			if closed[index] {
+				continue;
+			}
+			closed[index] = true;
+			let cell = self.grid.cell_at(index);
+			if cell == goal {
+				let mut path = Vec::from([self.grid.centre(cell)]);
+				let mut i = index;
+				while let Some(p) = parent[i] {
+					path.push(self.grid.centre(self.grid.cell_at(p)));
+					i = p;
+				}
+				path.reverse();
+				return Ok(path);
+			}
+			let from = parent[index].map(|p| self.grid.cell_at(p));
+			for (dx, dy) in self.directions(cell, from) {
+				let jump = if dx != 0 && dy != 0 {
+					self.jump_diagonal(cell, dx, dy, goal)
+				} else {
+					self.jump_straight(cell, dx, dy, goal)
+				};
+				let Some(next) = jump else {
+					continue;
+				};
+				let next_index = self.grid.index(next);
+				let cost = g[index] + self.grid.heuristic(cell, next);
+				if !closed[next_index] && cost < g[next_index] {
+					g[next_index] = cost;
+					parent[next_index] = Some(index);
+					open.push(OpenNode {
+						cost: cost + self.grid.heuristic(next, goal),
+						index: next_index,
+					});
+				}
+			}
+		}
+		Err(GridError::PathNotFound)
+	}
+	fn free(&self, x: i64, y: i64) -> bool {
+		x >= 0 && y >= 0 && self.grid.is_free((x as usize, y as usize))
+	}
+	// entering (x, y) moving straight in (dx, dy)
+	fn forced(&self, x: i64, y: i64, dx: i64, dy: i64) -> bool {
+		if dx != 0 {
+			(self.free(x, y - 1) && !self.free(x - dx, y - 1))
+				|| (self.free(x, y + 1) && !self.free(x - dx, y + 1))
+		} else {
+			(self.free(x - 1, y) && !self.free(x - 1, y - dy))
+				|| (self.free(x + 1, y) && !self.free(x + 1, y - dy))
+		}
+	}
+	// pruned directions to search from a cell reached from parent
+	fn directions(&self, cell: Cell, parent: Option<Cell>) -> Vec<(i64, i64)> {
+		let (x, y) = (cell.0 as i64, cell.1 as i64);
+		let Some(parent) = parent else {
+			return (-1..=1)
+				.flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+				.filter(|&d| d != (0, 0))
+				.collect();
+		};
+		let dx = (x - parent.0 as i64).signum();
+		let dy = (y - parent.1 as i64).signum();
+
+		let mut directions = Vec::new();
+		if dx != 0 && dy != 0 {
+			let (horizontal, vertical) = (self.free(x + dx, y), self.free(x, y + dy));
+			if vertical {
+				directions.push((0, dy));
+			}
+			if horizontal {
+				directions.push((dx, 0));
+			}
+			if horizontal && vertical {
+				directions.push((dx, dy));
+			}
+		} else {
+			// the two sides perpendicular to the direction of travel
+			let (px, py) = (dy, dx);
+			for side in [-1, 1] {
+				if self.free(x + side * px, y + side * py) {
+					directions.push((side * px, side * py));
+					if self.free(x + dx, y + dy) {
+						directions.push((dx + side * px, dy + side * py));
+					}
+				}
+			}
+			if self.free(x + dx, y + dy) {
+				directions.push((dx, dy));
+			}
+		}
+		directions
+	}
+	fn jump_straight(&self, cell: Cell, dx: i64, dy: i64, goal: Cell) -> Option<Cell> {
+		let direction = DIRECTIONS.iter().position(|&d| d == (dx, dy))?;
+		let jump = self.jumps[self.grid.index(cell)][direction];
+		let reach = jump.abs();
+
+		// the goal counts as a jump point
+		let (gx, gy) = (goal.0 as i64 - cell.0 as i64, goal.1 as i64 - cell.1 as i64);
+		let along = gx * dx + gy * dy;
+		let across = gx * dy - gy * dx;
+		if across == 0 && along > 0 && along <= reach {
+			return Some(goal);
+		}
+		(jump > 0).then(|| {
+			(
+				(cell.0 as i64 + dx * jump) as usize,
+				(cell.1 as i64 + dy * jump) as usize,
+			)
+		})
+	}
+	fn jump_diagonal(&self, cell: Cell, dx: i64, dy: i64, goal: Cell) -> Option<Cell> {
+		let (mut x, mut y) = (cell.0 as i64, cell.1 as i64);
+		loop {
+			if !self.free(x + dx, y) || !self.free(x, y + dy) || !self.free(x + dx, y + dy) {
+				return None;
+			}
+			x += dx;
+			y += dy;
+			let next = (x as usize, y as usize);
+			if next == goal
+				|| self.jump_straight(next, dx, 0, goal).is_some()
+				|| self.jump_straight(next, 0, dy, goal).is_some()
+			{
+				return Some(next);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::path_planning::DStarLite;
+
+	// fixed scattered obstacles with a few walls
+	fn test_grid() -> Grid {
+		let (w, h) = (30, 20);
+		let occupied: Vec<bool> = (0..w * h)
+			.map(|i| {
+				let (x, y) = (i % w, i / w);
+				(x == 10 && y < 15) || (x == 20 && y > 4) || (x * 7 + y * 13) % 17 == 0
+			})
+			.collect();
+		let mut grid = Grid::from_occupancy(&occupied, w, 0.5, Pos2::new(0.0, 0.0)).unwrap();
+		grid.set_cost((0, 0), 1.0).unwrap();
+		grid.set_cost((29, 19), 1.0).unwrap();
+		grid
+	}
+
+	fn length(path: &[Pos2]) -> f64 {
+		path.windows(2).map(|w| (w[1] - w[0]).magnitude()).sum()
+	}
+
+	#[test]
+	fn matches_optimal_cost() {
+		let grid = test_grid();
+		let (start, goal) = (grid.centre((0, 0)), grid.centre((29, 19)));
+		let jps = JumpPointSearch::new(grid.clone());
+		let path = jps.plan(start, goal).unwrap();
+		assert_eq!(path[0], start);
+		assert_eq!(path[path.len() - 1], goal);
+
+		// every leg is straight or diagonal over free cells
+		for w in path.windows(2) {
+			let (a, b) = (grid.cell(w[0]).unwrap(), grid.cell(w[1]).unwrap());
+			let (dx, dy) = (a.0.abs_diff(b.0), a.1.abs_diff(b.1));
+			assert!(dx == 0 || dy == 0 || dx == dy);
+			assert!(grid.line_of_sight(a, b));
+		}
+
+		let mut d_star = DStarLite::new(grid, start, goal).unwrap();
+		d_star.plan().unwrap();
+		let optimal = d_star.cost_to_goal(start).unwrap();
+		assert!((length(&path) - optimal).abs() < 1e-9);
+
+		// walled off
+		let mut blocked = test_grid();
+		for y in 0..20 {
+			blocked.set_cost((15, y), f64::INFINITY).unwrap();
+		}
+		let jps = JumpPointSearch::new(blocked);
+		assert_eq!(jps.plan(start, goal).unwrap_err(), GridError::PathNotFound);
+	}
+}
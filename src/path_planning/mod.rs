@@ -1,6 +1,50 @@
+pub(crate) mod b_spline;
+pub(crate) mod bezier;
+pub(crate) mod coverage;
+pub(crate) mod cubic_spline;
 pub mod curved_paths;
+pub(crate) mod d_star_lite;
+pub(crate) mod dynamic_window;
+pub(crate) mod frenet_frame;
+pub(crate) mod frenet_planner;
+pub(crate) mod grid;
+pub(crate) mod jump_point_search;
+pub(crate) mod multi_agent;
+pub(crate) mod parametric_curve;
+pub(crate) mod polygon;
+pub(crate) mod polynomial;
+pub(crate) mod potential_field;
+pub(crate) mod quartic_polynomial;
 pub(crate) mod quintic_polynomial;
+pub(crate) mod quintic_trajectory;
+pub(crate) mod theta_star;
+pub(crate) mod topp;
+pub(crate) mod velocity_profile;
+pub(crate) mod visibility_graph;
+pub(crate) mod voronoi_roadmap;
 
+pub use b_spline::*;
+pub use bezier::*;
+pub use coverage::*;
+pub use cubic_spline::*;
+pub use curved_paths::clothoid::*;
+pub use curved_paths::continuous_curvature::*;
 pub use curved_paths::dubins::*;
 pub use curved_paths::reeds_shepp::*;
+pub use d_star_lite::*;
+pub use dynamic_window::*;
+pub use frenet_frame::*;
+pub use frenet_planner::*;
+pub use grid::*;
+pub use jump_point_search::*;
+pub use multi_agent::*;
+pub use parametric_curve::*;
+pub use potential_field::*;
+pub use quartic_polynomial::*;
 pub use quintic_polynomial::*;
+pub use quintic_trajectory::*;
+pub use theta_star::*;
+pub use topp::*;
+pub use velocity_profile::*;
+pub use visibility_graph::*;
+pub use voronoi_roadmap::*;
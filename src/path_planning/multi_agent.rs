@@ -0,0 +1,431 @@
+use super::grid::{Cell, Grid, GridError, OpenNode};
+use crate::prelude::*;
+use core::mem;
+
+// references:
+// https://doi.org/10.1016/j.artint.2014.11.006 (Sharon et al., CBS)
+// https://doi.org/10.1007/BF01840371 (Erdmann & Lozano-Pérez, prioritised planning)
+// https://doi.org/10.1609/aiide.v1i1.18726 (Silver, cooperative A*)
+// --------
+// agents move to one of the 4 adjacent free cells or wait each time step, cell
+// costs are ignored apart from blocked cells
+// each agent plans with A* over (cell, time) avoiding its constraints, and
+// rests at its goal once it gets there
+// CBS looks for the first vertex conflict (same cell at the same time) or edge
+// conflict (swapping cells) between the paths and branches on which of the two
+// agents is kept out, which finds the lowest sum of path costs
+// prioritised planning plans the agents in order, each avoiding the paths of
+// the ones before, which is fast but can fail where CBS wouldn't
+// --------
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MultiAgentStrategy {
+	// falls back to prioritised planning after expanding this many constraint
+	// tree nodes
+	ConflictBasedSearch { max_nodes: usize },
+	Prioritised,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Constraint {
+	Vertex { cell: usize, time: usize },
+	// moving from one cell to the other, arriving at the time
+	Edge { from: usize, to: usize, time: usize },
+	// the cell is taken from the time onwards, by an agent resting at its goal
+	From { cell: usize, time: usize },
+}
+
+#[derive(Debug, Clone)]
+struct ConstraintNode {
+	constraints: Vec<(usize, Constraint)>,
+	paths: Vec<Vec<usize>>,
+}
+
+// positions of every agent at each time step, all the same length with agents
+// waiting at their goals once they arrive
+pub fn multi_agent_paths(
+	grid: &Grid,
+	agents: &[(Pos2, Pos2)],
+	strategy: MultiAgentStrategy,
+) -> Result<Vec<Vec<Pos2>>, GridError> {
+	let mut ends = Vec::new();
+	for &(start, goal) in agents {
+		let start = grid.cell(start).ok_or(GridError::OutOfBounds)?;
+		let goal = grid.cell(goal).ok_or(GridError::OutOfBounds)?;
+		if !grid.is_free(start) || !grid.is_free(goal) {
+			return Err(GridError::PathNotFound);
+		}
+		ends.push((grid.index(start), grid.index(goal)));
+	}
+	for (i, a) in ends.iter().enumerate() {
+		if ends[..i].iter().any(|b| a.0 == b.0 || a.1 == b.1) {
+			return Err(GridError::InvalidInput);
+		}
+	}
+
+	let paths = match strategy {
+		MultiAgentStrategy::ConflictBasedSearch { max_nodes } => {
+			match conflict_based_search(grid, &ends, max_nodes)? {
+				Some(paths) => paths,
+				None => prioritised(grid, &ends)?,
+			}
+		}
+		MultiAgentStrategy::Prioritised => prioritised(grid, &ends)?,
+	};
+
+	let length = paths.iter().map(Vec::len).max().unwrap_or(0);
+	Ok(paths
+		.iter()
+		.map(|path| {
+			(0..length)
+				.map(|t| grid.centre(grid.cell_at(at(path, t))))
+				.collect()
+		})
+		.collect())
+}
+
+// None when the node limit is reached
+fn conflict_based_search(
+	grid: &Grid,
+	ends: &[(usize, usize)],
+	max_nodes: usize,
+) -> Result<Option<Vec<Vec<usize>>>, GridError> {
+	let paths = ends
+		.iter()
+		.map(|&(start, goal)| search(grid, start, goal, &[]).ok_or(GridError::PathNotFound))
+		.collect::<Result<Vec<_>, _>>()?;
+	let mut nodes = Vec::from([ConstraintNode {
+		constraints: Vec::new(),
+		paths,
+	}]);
+	let mut open = BinaryHeap::from([OpenNode {
+		cost: sum_of_costs(&nodes[0].paths),
+		index: 0,
+	}]);
+
+	while let Some(OpenNode { index, .. }) = open.pop() {
+		let Some((agents, constraints)) = first_conflict(&nodes[index].paths) else {
+			return Ok(Some(mem::take(&mut nodes[index].paths)));
+		};
+		for (agent, constraint) in agents.into_iter().zip(constraints) {
+			if nodes.len() >= max_nodes {
+				return Ok(None);
+			}
+			let mut constraints = nodes[index].constraints.clone();
+			constraints.push((agent, constraint));
+			let own: Vec<Constraint> = constraints
+				.iter()
+				.filter(|c| c.0 == agent)
+				.map(|c| c.1)
+				.collect();
+			// no path for this agent means this branch is dead
+			let Some(path) = search(grid, ends[agent].0, ends[agent].1, &own) else {
+				continue;
+			};
+			let mut paths = nodes[index].paths.clone();
+			paths[agent] = path;
+			open.push(OpenNode {
+				cost: sum_of_costs(&paths),
+				index: nodes.len(),
+			});
+			nodes.push(ConstraintNode { constraints, paths });
+		}
+	}
+	Err(GridError::PathNotFound)
+}
+
+fn prioritised(grid: &Grid, ends: &[(usize, usize)]) -> Result<Vec<Vec<usize>>, GridError> {
+	let mut constraints = Vec::new();
+	let mut paths: Vec<Vec<usize>> = Vec::new();
+	for &(start, goal) in ends {
+		let path = search(grid, start, goal, &constraints).ok_or(GridError::PathNotFound)?;
+		for (t, w) in path.windows(2).enumerate() {
+			constraints.push(Constraint::Vertex {
+				cell: w[0],
+				time: t,
+			});
+			constraints.push(Constraint::Edge {
+				from: w[1],
+				to: w[0],
+				time: t + 1,
+			});
+		}
+		constraints.push(Constraint::From {
+			cell: goal,
+			time: path.len() - 1,
+		});
+		paths.push(path);
+	}
+	Ok(paths)
+}
+
+fn at(path: &[usize], time: usize) -> usize {
+	path[time.min(path.len() - 1)]
+}
+
+fn sum_of_costs(paths: &[Vec<usize>]) -> f64 {
+	paths.iter().map(|p| (p.len() - 1) as f64).sum()
+}
+
+// the two agents in the earliest conflict and the constraint for each that
+// resolves it
+fn first_conflict(paths: &[Vec<usize>]) -> Option<([usize; 2], [Constraint; 2])> {
+	let length = paths.iter().map(Vec::len).max().unwrap_or(0);
+	for t in 0..length {
+		for a in 0..paths.len() {
+			for b in a + 1..paths.len() {
+				let (cell, other) = (at(&paths[a], t), at(&paths[b], t));
+				if cell == other {
+					let constraint = Constraint::Vertex { cell, time: t };
+					return Some(([a, b], [constraint, constraint]));
+				}
+				let (next, other_next) = (at(&paths[a], t + 1), at(&paths[b], t + 1));
+				if cell == other_next && next == other && cell != next {
+					return Some((
+						[a, b],
+						[
+							Constraint::Edge {
+								from: cell,
+								to: next,
+								time: t + 1,
+							},
+							Constraint::Edge {
+								from: next,
+								to: cell,
+								time: t + 1,
+							},
+						],
+					));
+				}
+			}
+		}
+	}
+	None
+}
+
+// A* over (cell, time) to the goal, after which the agent can stay there
+fn search(
+	grid: &Grid,
+	start: usize,
+	goal: usize,
+	constraints: &[Constraint],
+) -> Option<Vec<usize>> {
+	let n = grid.width() * grid.height();
+	let mut vertices = BTreeSet::new();
+	let mut edges = BTreeSet::new();
+	let mut taken = Vec::from([usize::MAX]).repeat(n);
+	// the agent can only stop at the goal after the goal's last constraint
+	let mut earliest_stop = 0;
+	let mut last = 0;
+	for &constraint in constraints {
+		match constraint {
+			Constraint::Vertex { cell, time } => {
+				vertices.insert((cell, time));
+				if cell == goal {
+					earliest_stop = earliest_stop.max(time + 1);
+				}
+				last = last.max(time);
+			}
+			Constraint::Edge { from, to, time } => {
+				edges.insert((from, to, time));
+				last = last.max(time);
+			}
+			Constraint::From { cell, time } => {
+				taken[cell] = taken[cell].min(time);
+				if cell == goal {
+					return None;
+				}
+				last = last.max(time);
+			}
+		}
+	}
+	// past every constraint waiting never helps
+	let horizon = last + n;
+
+	// exact distances to the goal ignoring other agents
+	let mut distance = Vec::from([usize::MAX]).repeat(n);
+	distance[goal] = 0;
+	let mut frontier = Vec::from([goal]);
+	let mut d = 0;
+	while !frontier.is_empty() {
+		d += 1;
+		let mut next = Vec::new();
+		for index in frontier {
+			for cell in moves(grid, index) {
+				if distance[cell] == usize::MAX {
+					distance[cell] = d;
+					next.push(cell);
+				}
+			}
+		}
+		frontier = next;
+	}
+	if distance[start] == usize::MAX {
+		return None;
+	}
+
+	// (cell, time, parent)
+	let mut states = Vec::from([(start, 0, usize::MAX)]);
+	let mut closed = BTreeSet::new();
+	let mut open = BinaryHeap::from([OpenNode {
+		cost: distance[start] as f64,
+		index: 0,
+	}]);
+	while let Some(OpenNode { index, .. }) = open.pop() {
+		let (cell, time, _) = states[index];
+		if !closed.insert((cell, time)) {
+			continue;
+		}
+		if cell == goal && time >= earliest_stop {
+			let mut path = Vec::from([cell]);
+			let mut i = index;
+			while states[i].2 != usize::MAX {
+				i = states[i].2;
+				path.push(states[i].0);
+			}
+			path.reverse();
+			return Some(path);
+		}
+		if time >= horizon {
+			continue;
+		}
+		let t = time + 1;
+		for next in core::iter::once(cell).chain(moves(grid, cell)) {
+			if distance[next] == usize::MAX
+				|| t >= taken[next]
+				|| vertices.contains(&(next, t))
+				|| edges.contains(&(cell, next, t))
+				|| closed.contains(&(next, t))
+			{
+				continue;
+			}
+			open.push(OpenNode {
+				cost: (t + distance[next]) as f64,
+				index: states.len(),
+			});
+			states.push((next, t, index));
+		}
+	}
+	None
+}
+
+// free cells one step north, south, east or west
+fn moves(grid: &Grid, index: usize) -> impl Iterator<Item = usize> + '_ {
+	let (x, y) = grid.cell_at(index);
+	[(1, 0), (-1, 0), (0, 1), (0, -1)]
+		.into_iter()
+		.filter_map(move |(dx, dy): (i64, i64)| {
+			let cell: Cell = (
+				usize::try_from(x as i64 + dx).ok()?,
+				usize::try_from(y as i64 + dy).ok()?,
+			);
+			grid.is_free(cell).then(|| grid.index(cell))
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// no two agents in the same place or swapping places
+	fn conflict_free(paths: &[Vec<Pos2>]) -> bool {
+		let length = paths[0].len();
+		paths.iter().all(|p| p.len() == length)
+			&& (0..length).all(|t| {
+				(0..paths.len()).all(|a| {
+					(a + 1..paths.len()).all(|b| {
+						paths[a][t] != paths[b][t]
+							&& (t + 1 == length
+								|| paths[a][t] != paths[b][t + 1]
+								|| paths[a][t + 1] != paths[b][t])
+					})
+				})
+			})
+	}
+
+	fn sum_of_costs(paths: &[Vec<Pos2>]) -> usize {
+		paths
+			.iter()
+			.map(|p| {
+				let goal = p[p.len() - 1];
+				p.iter().rposition(|&q| q != goal).map_or(0, |i| i + 1)
+			})
+			.sum()
+	}
+
+	#[test]
+	fn swap_in_corridor() {
+		// a corridor with a pocket in the middle to let the other agent by
+		let occupied: Vec<bool> = (0..15).map(|i| i / 5 != 1 && i != 12).collect();
+		let grid = Grid::from_occupancy(&occupied, 5, 1.0, Pos2::new(0.0, 0.0)).unwrap();
+		let (left, right) = (Pos2::new(0.5, 1.5), Pos2::new(4.5, 1.5));
+		let agents = [(left, right), (right, left)];
+
+		let paths = multi_agent_paths(
+			&grid,
+			&agents,
+			MultiAgentStrategy::ConflictBasedSearch { max_nodes: 1000 },
+		)
+		.unwrap();
+		assert!(conflict_free(&paths));
+		assert_eq!(paths[0][paths[0].len() - 1], right);
+		assert_eq!(paths[1][paths[1].len() - 1], left);
+		// one agent steps into the pocket and back out while the other waits a step
+		assert_eq!(sum_of_costs(&paths), 11);
+
+		// the first agent drives straight through and traps the second
+		assert_eq!(
+			multi_agent_paths(&grid, &agents, MultiAgentStrategy::Prioritised).unwrap_err(),
+			GridError::PathNotFound
+		);
+		// with no node budget CBS falls back to prioritised planning
+		assert_eq!(
+			multi_agent_paths(
+				&grid,
+				&agents,
+				MultiAgentStrategy::ConflictBasedSearch { max_nodes: 1 }
+			)
+			.unwrap_err(),
+			GridError::PathNotFound
+		);
+	}
+
+	#[test]
+	fn crossing_agents() {
+		let grid = Grid::new(7, 7, 0.5, Pos2::new(0.0, 0.0)).unwrap();
+		let cell = |x: usize, y: usize| grid.centre((x, y));
+		let agents = [
+			(cell(0, 3), cell(6, 3)),
+			(cell(6, 3), cell(0, 3)),
+			(cell(3, 0), cell(3, 6)),
+			(cell(3, 6), cell(3, 0)),
+			(cell(2, 3), cell(3, 3)),
+		];
+		let cbs = multi_agent_paths(
+			&grid,
+			&agents,
+			MultiAgentStrategy::ConflictBasedSearch { max_nodes: 10000 },
+		)
+		.unwrap();
+		let prioritised =
+			multi_agent_paths(&grid, &agents, MultiAgentStrategy::Prioritised).unwrap();
+		for paths in [&cbs, &prioritised] {
+			assert!(conflict_free(paths));
+			for (path, agent) in paths.iter().zip(&agents) {
+				assert_eq!(path[0], agent.0);
+				assert_eq!(path[path.len() - 1], agent.1);
+			}
+		}
+		assert!(sum_of_costs(&cbs) <= sum_of_costs(&prioritised));
+
+		assert_eq!(
+			multi_agent_paths(
+				&grid,
+				&[(cell(0, 0), cell(1, 1)), (cell(2, 2), cell(1, 1))],
+				MultiAgentStrategy::Prioritised
+			)
+			.unwrap_err(),
+			GridError::InvalidInput
+		);
+	}
+}
@@ -0,0 +1,159 @@
+use crate::prelude::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CurveError {
+	NotEnoughPoints,
+	InvalidDegree,
+	OutOfRange,
+	InvalidInput,
+}
+
+// number of intervals of the parameter used for arc length
+const ARC_LENGTH_INTERVALS: usize = 128;
+
+// 5 point gauss-legendre nodes and weights on [-1, 1]
+pub(crate) const GAUSS_LEGENDRE: [(f64, f64); 5] = [
+	(0.0, 0.568_888_888_888_888_9),
+	(-0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+	(0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+	(-0.906_179_845_938_664, 0.236_926_885_056_189_1),
+	(0.906_179_845_938_664, 0.236_926_885_056_189_1),
+];
+
+// curve over a parameter t that isn't arc length
+pub trait ParametricCurve {
+	// range of t
+	fn domain(&self) -> (f64, f64);
+	fn evaluate_unchecked(&self, t: f64) -> Pos2;
+	// derivatives with respect to t
+	fn derivative(&self, t: f64) -> Vec2;
+	fn second_derivative(&self, t: f64) -> Vec2;
+
+	fn evaluate(&self, t: f64) -> Result<Pos2, CurveError> {
+		let (start, end) = self.domain();
+		if t < start || t > end {
+			return Err(CurveError::OutOfRange);
+		}
+		Ok(self.evaluate_unchecked(t))
+	}
+	fn heading(&self, t: f64) -> f64 {
+		let d = self.derivative(t);
+		d.y.atan2(d.x)
+	}
+	// positive is a left turn
+	fn curvature(&self, t: f64) -> f64 {
+		let d = self.derivative(t);
+		let dd = self.second_derivative(t);
+		(d.x * dd.y - d.y * dd.x) / d.magnitude().powi(3)
+	}
+	fn ray(&self, t: f64) -> Ray {
+		Ray::new(self.evaluate_unchecked(t), self.heading(t))
+	}
+	// rays evenly spaced by arc length
+	fn get_points(&self, step_size: f64) -> Vec<Ray>
+	where
+		Self: Sized,
+	{
+		let mut points = Vec::new();
+		if step_size <= 0.0 {
+			return points;
+		}
+		let arc_length = ArcLength::new(self, ARC_LENGTH_INTERVALS);
+		let mut s = 0.0;
+		while s < arc_length.length() {
+			points.push(self.ray(arc_length.parameter(self, s)));
+			s += step_size;
+		}
+		points.push(self.ray(self.domain().1));
+		points
+	}
+}
+
+// integral of the speed |dr/dt| between two parameters
+fn speed_integral<C: ParametricCurve>(curve: &C, t0: f64, t1: f64) -> f64 {
+	let (mid, half) = (0.5 * (t0 + t1), 0.5 * (t1 - t0));
+	GAUSS_LEGENDRE
+		.iter()
+		.map(|&(x, w)| w * curve.derivative(mid + half * x).magnitude())
+		.sum::<f64>()
+		* half
+}
+
+// table of arc length at evenly spaced parameters for reparameterising by arc length
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArcLength {
+	t: Vec<f64>,
+	s: Vec<f64>,
+}
+
+impl ArcLength {
+	#[must_use]
+	pub fn new<C: ParametricCurve>(curve: &C, intervals: usize) -> Self {
+		let intervals = intervals.max(1);
+		let (start, end) = curve.domain();
+		let t: Vec<f64> = (0..=intervals)
+			.map(|i| start + (end - start) * i as f64 / intervals as f64)
+			.collect();
+		Self::from_parameters(curve, t)
+	}
+	// table at increasing parameters covering the domain, which should include
+	// any parameters where the curve isn't smooth for accurate integration
+	#[must_use]
+	pub fn from_parameters<C: ParametricCurve>(curve: &C, t: Vec<f64>) -> Self {
+		let mut s = Vec::with_capacity(t.len());
+		s.push(0.0);
+		for w in t.windows(2) {
+			s.push(s[s.len() - 1] + speed_integral(curve, w[0], w[1]));
+		}
+		Self { t, s }
+	}
+	#[must_use]
+	pub fn length(&self) -> f64 {
+		self.s[self.s.len() - 1]
+	}
+	// distance along the curve at a parameter, clamped to the curve
+	#[must_use]
+	pub fn distance<C: ParametricCurve>(&self, curve: &C, t: f64) -> f64 {
+		let t = t.clamp(self.t[0], self.t[self.t.len() - 1]);
+		let i = self
+			.t
+			.partition_point(|&v| v <= t)
+			.saturating_sub(1)
+			.min(self.t.len() - 2);
+		self.s[i] + speed_integral(curve, self.t[i], t)
+	}
+	// parameter at a distance along the curve, clamped to the curve
+	// newton's method within the interval containing s, falling back to bisection
+	#[must_use]
+	pub fn parameter<C: ParametricCurve>(&self, curve: &C, s: f64) -> f64 {
+		let s = s.clamp(0.0, self.length());
+		let i = self
+			.s
+			.partition_point(|&v| v <= s)
+			.saturating_sub(1)
+			.min(self.t.len() - 2);
+		let (mut lo, mut hi) = (self.t[i], self.t[i + 1]);
+		let target = s - self.s[i];
+
+		let mut t = lo + (hi - lo) * target / (self.s[i + 1] - self.s[i]).max(f64::MIN_POSITIVE);
+		for _ in 0..20 {
+			let f = speed_integral(curve, self.t[i], t) - target;
+			if f.abs() < 1e-12 {
+				break;
+			}
+			if f > 0.0 {
+				hi = t;
+			} else {
+				lo = t;
+			}
+			let speed = curve.derivative(t).magnitude();
+			let next = t - f / speed;
+			t = if speed > 0.0 && next > lo && next < hi {
+				next
+			} else {
+				0.5 * (lo + hi)
+			};
+		}
+		t
+	}
+}
@@ -0,0 +1,63 @@
+use crate::prelude::*;
+
+// helpers for polygons stored as vertices in order, either winding
+
+pub(crate) fn cross(a: Vec2, b: Vec2) -> f64 {
+	a.x * b.y - a.y * b.x
+}
+
+// signed, positive for counter clockwise
+pub(crate) fn area(polygon: &[Pos2]) -> f64 {
+	let n = polygon.len();
+	0.5 * (0..n)
+		.map(|i| cross(polygon[i].coords, polygon[(i + 1) % n].coords))
+		.sum::<f64>()
+}
+
+// strictly inside, crossing number
+pub(crate) fn contains(polygon: &[Pos2], point: Pos2) -> bool {
+	let n = polygon.len();
+	let mut inside = false;
+	for (i, &a) in polygon.iter().enumerate() {
+		let b = polygon[(i + 1) % n];
+		// on the boundary counts as outside
+		let ab = b - a;
+		let t = (point - a).dot(&ab) / ab.magnitude_squared();
+		if (0.0..=1.0).contains(&t) && (a + ab * t - point).magnitude() < 1e-9 {
+			return false;
+		}
+		if (a.y > point.y) != (b.y > point.y)
+			&& point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+		{
+			inside = !inside;
+		}
+	}
+	inside
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn l_shape() {
+		let polygon = [
+			Pos2::new(0.0, 0.0),
+			Pos2::new(2.0, 0.0),
+			Pos2::new(2.0, 1.0),
+			Pos2::new(1.0, 1.0),
+			Pos2::new(1.0, 2.0),
+			Pos2::new(0.0, 2.0),
+		];
+		assert!((area(&polygon) - 3.0).abs() < 1e-12);
+		let reversed: Vec<Pos2> = polygon.iter().rev().copied().collect();
+		assert!((area(&reversed) + 3.0).abs() < 1e-12);
+
+		assert!(contains(&polygon, Pos2::new(0.5, 1.5)));
+		assert!(contains(&reversed, Pos2::new(1.5, 0.5)));
+		assert!(!contains(&polygon, Pos2::new(1.5, 1.5)));
+		// on an edge or a vertex is outside
+		assert!(!contains(&polygon, Pos2::new(1.0, 1.5)));
+		assert!(!contains(&polygon, Pos2::new(1.0, 1.0)));
+	}
+}
@@ -0,0 +1,139 @@
+#[cfg(feature = "no_std")]
+use crate::prelude::*;
+
+// helpers for polynomials stored as coefficients in ascending order of power
+
+pub(crate) fn evaluate(p: &[f64], t: f64) -> f64 {
+	p.iter().rev().fold(0.0, |acc, &c| acc * t + c)
+}
+
+pub(crate) fn derivative(p: &[f64]) -> Vec<f64> {
+	p.iter()
+		.enumerate()
+		.skip(1)
+		.map(|(i, &c)| i as f64 * c)
+		.collect()
+}
+
+pub(crate) fn multiply(a: &[f64], b: &[f64]) -> Vec<f64> {
+	if a.is_empty() || b.is_empty() {
+		return Vec::new();
+	}
+	let mut out = Vec::new();
+	out.resize(a.len() + b.len() - 1, 0.0);
+	for (i, &x) in a.iter().enumerate() {
+		for (j, &y) in b.iter().enumerate() {
+			out[i + j] += x * y;
+		}
+	}
+	out
+}
+
+pub(crate) fn add(a: &[f64], b: &[f64]) -> Vec<f64> {
+	let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+	let mut out = long.to_vec();
+	out.iter_mut().zip(short).for_each(|(o, s)| *o += s);
+	out
+}
+
+// squared magnitude of a 2d polynomial curve
+pub(crate) fn magnitude_squared(x: &[f64], y: &[f64]) -> Vec<f64> {
+	add(&multiply(x, x), &multiply(y, y))
+}
+
+// all real roots in [lo, hi] in ascending order
+// roots of p are separated by the roots of p' so recursively find those and
+// bisect each interval over which p is monotonic
+pub(crate) fn roots_in(p: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+	let Some(degree) = p.iter().rposition(|&c| c != 0.0) else {
+		return Vec::new();
+	};
+	let p = &p[..=degree];
+
+	match degree {
+		0 => Vec::new(),
+		1 => {
+			let root = -p[0] / p[1];
+			if (lo..=hi).contains(&root) {
+				Vec::from([root])
+			} else {
+				Vec::new()
+			}
+		}
+		_ => {
+			let mut bounds = Vec::from([lo]);
+			bounds.extend(roots_in(&derivative(p), lo, hi));
+			bounds.push(hi);
+
+			let mut roots: Vec<f64> = Vec::new();
+			for w in bounds.windows(2) {
+				if let Some(root) = bisect(p, w[0], w[1]) {
+					// the same root can be found at the end of one interval and the start of the next
+					if !roots.last().is_some_and(|&r| (root - r).abs() <= 1e-12) {
+						roots.push(root);
+					}
+				}
+			}
+			roots
+		}
+	}
+}
+
+fn bisect(p: &[f64], mut a: f64, mut b: f64) -> Option<f64> {
+	let (mut fa, fb) = (evaluate(p, a), evaluate(p, b));
+	if fa == 0.0 {
+		return Some(a);
+	}
+	if fb == 0.0 {
+		return Some(b);
+	}
+	if fa.signum() == fb.signum() {
+		return None;
+	}
+	for _ in 0..200 {
+		let mid = 0.5 * (a + b);
+		if mid <= a || mid >= b {
+			break;
+		}
+		let fm = evaluate(p, mid);
+		if fm == 0.0 {
+			return Some(mid);
+		}
+		if fm.signum() == fa.signum() {
+			a = mid;
+			fa = fm;
+		} else {
+			b = mid;
+		}
+	}
+	Some(0.5 * (a + b))
+}
+
+// maximum of sqrt(p) over [lo, hi] where p is non-negative, such as a squared magnitude
+pub(crate) fn max_sqrt_in(p: &[f64], lo: f64, hi: f64) -> f64 {
+	roots_in(&derivative(p), lo, hi)
+		.into_iter()
+		.chain([lo, hi])
+		.map(|t| evaluate(p, t))
+		.fold(0.0, f64::max)
+		.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_roots() {
+		// (t - 1)(t - 2)(t - 3)(t + 1) = t^4 - 5t^3 + 5t^2 + 5t - 6
+		let p = [-6.0, 5.0, 5.0, -5.0, 1.0];
+		let roots = roots_in(&p, -0.5, 10.0);
+		assert_eq!(roots.len(), 3);
+		for (r, e) in roots.iter().zip([1.0, 2.0, 3.0]) {
+			assert!((r - e).abs() < 1e-10);
+		}
+		// double root at 1
+		assert!((roots_in(&[1.0, -2.0, 1.0], 0.0, 2.0)[0] - 1.0).abs() < 1e-6);
+		assert!(roots_in(&[1.0, 0.0, 1.0], -5.0, 5.0).is_empty());
+	}
+}
@@ -0,0 +1,487 @@
+use super::polygon::contains;
+use crate::prelude::*;
+use core::f64::consts::TAU;
+
+// references:
+// https://doi.org/10.1177/027836498600500106 (Khatib)
+// https://doi.org/10.1007/978-1-4615-4022-9 (Latombe, chapter 7 for the numerical navigation function)
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PotentialFieldError {
+	InvalidInput,
+	PathNotFound,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Obstacle {
+	Point(Pos2),
+	// vertices in order, either winding
+	Polygon(Vec<Pos2>),
+}
+
+impl Obstacle {
+	// distance to the obstacle, negative inside a polygon, and the closest point on it
+	#[must_use]
+	pub fn signed_distance(&self, point: Pos2) -> (f64, Pos2) {
+		match self {
+			Self::Point(p) => ((point - p).magnitude(), *p),
+			Self::Polygon(vertices) => {
+				let Some(&last) = vertices.last() else {
+					return (f64::INFINITY, point);
+				};
+				let mut best = (f64::INFINITY, last);
+				let mut a = last;
+				for &b in vertices {
+					let ab = b - a;
+					let t = ((point - a).dot(&ab) / ab.magnitude_squared().max(f64::MIN_POSITIVE))
+						.clamp(0.0, 1.0);
+					let closest = a + ab * t;
+					let d = (point - closest).magnitude();
+					if d < best.0 {
+						best = (d, closest);
+					}
+					a = b;
+				}
+				if contains(vertices, point) {
+					(-best.0, best.1)
+				} else {
+					best
+				}
+			}
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LocalMinimumEscape {
+	// walk this many random steps then carry on descending
+	RandomWalk { steps: usize },
+	// follow a wavefront over a grid of this resolution, which has no local minima,
+	// for the rest of the path
+	NavigationFunction { resolution: f64 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PotentialField {
+	pub attractive_gain: f64,
+	// the attractive potential is quadratic within this distance of the goal and conic beyond
+	pub quadratic_radius: f64,
+	pub repulsive_gain: f64,
+	// obstacles further away than this have no effect
+	pub influence_distance: f64,
+	pub step_size: f64,
+	pub max_iterations: usize,
+	// stuck if the path moves less than two steps over this many steps
+	pub oscillation_window: usize,
+	pub escape: LocalMinimumEscape,
+	pub seed: u64,
+}
+
+impl Default for PotentialField {
+	fn default() -> Self {
+		Self {
+			attractive_gain: 1.0,
+			quadratic_radius: 2.0,
+			repulsive_gain: 1.0,
+			influence_distance: 2.0,
+			step_size: 0.1,
+			max_iterations: 5000,
+			oscillation_window: 20,
+			escape: LocalMinimumEscape::RandomWalk { steps: 30 },
+			seed: 0x2545_f491_4f6c_dd1d,
+		}
+	}
+}
+
+impl PotentialField {
+	#[must_use]
+	pub fn potential(&self, point: Pos2, goal: Pos2, obstacles: &[Obstacle]) -> f64 {
+		let d = (point - goal).magnitude();
+		let attractive = if d <= self.quadratic_radius {
+			0.5 * self.attractive_gain * d * d
+		} else {
+			self.attractive_gain * self.quadratic_radius * (d - 0.5 * self.quadratic_radius)
+		};
+		let repulsive: f64 = obstacles
+			.iter()
+			.map(|o| {
+				let d = o.signed_distance(point).0;
+				if d <= 0.0 {
+					f64::INFINITY
+				} else if d < self.influence_distance {
+					0.5 * self.repulsive_gain * (1.0 / d - 1.0 / self.influence_distance).powi(2)
+				} else {
+					0.0
+				}
+			})
+			.sum();
+		attractive + repulsive
+	}
+	#[must_use]
+	pub fn gradient(&self, point: Pos2, goal: Pos2, obstacles: &[Obstacle]) -> Vec2 {
+		let to_goal = point - goal;
+		let d = to_goal.magnitude();
+		let mut gradient = if d <= self.quadratic_radius {
+			self.attractive_gain * to_goal
+		} else {
+			self.attractive_gain * self.quadratic_radius * to_goal / d
+		};
+		for obstacle in obstacles {
+			let (d, closest) = obstacle.signed_distance(point);
+			if d >= self.influence_distance {
+				continue;
+			}
+			let away = point - closest;
+			let length = away.magnitude();
+			if length == 0.0 {
+				continue;
+			}
+			// inside a polygon push out through the closest edge
+			let (normal, d) = if d > 0.0 {
+				(away / length, d)
+			} else {
+				(-away / length, f64::EPSILON)
+			};
+			gradient -=
+				self.repulsive_gain * (1.0 / d - 1.0 / self.influence_distance) / (d * d) * normal;
+		}
+		gradient
+	}
+	// gradient descent from start, ending exactly at the goal
+	pub fn plan(
+		&self,
+		start: Pos2,
+		goal: Pos2,
+		obstacles: &[Obstacle],
+	) -> Result<Vec<Vec2>, PotentialFieldError> {
+		if self.step_size <= 0.0
+			|| self.influence_distance <= 0.0
+			|| self.quadratic_radius < 0.0
+			|| self.oscillation_window == 0
+			|| self.escape == (LocalMinimumEscape::RandomWalk { steps: 0 })
+		{
+			return Err(PotentialFieldError::InvalidInput);
+		}
+		let mut rng = XorShift(self.seed.max(1));
+		let mut path = Vec::from([start.coords]);
+		let mut point = start;
+		// oscillation is only checked since the last escape
+		let mut last_escape = 0;
+
+		let mut iteration = 0;
+		while iteration < self.max_iterations {
+			if (point - goal).magnitude() <= self.step_size {
+				path.push(goal.coords);
+				return Ok(path);
+			}
+			let gradient = self.gradient(point, goal, obstacles);
+			let n = path.len();
+			let oscillating = n - last_escape > self.oscillation_window
+				&& (path[n - 1] - path[n - 1 - self.oscillation_window]).magnitude()
+					< 2.0 * self.step_size;
+
+			if gradient.magnitude() < 1e-9 || oscillating {
+				match self.escape {
+					LocalMinimumEscape::RandomWalk { steps } => {
+						for _ in 0..steps {
+							point = self.random_step(point, obstacles, &mut rng);
+							path.push(point.coords);
+						}
+						iteration += steps;
+						last_escape = path.len() - 1;
+					}
+					LocalMinimumEscape::NavigationFunction { resolution } => {
+						let grid = Wavefront::new(start, goal, obstacles, resolution, self)?;
+						path.extend(grid.descend(point, goal)?);
+						return Ok(path);
+					}
+				}
+				continue;
+			}
+			point -= gradient.normalize() * self.step_size;
+			path.push(point.coords);
+			iteration += 1;
+		}
+		Err(PotentialFieldError::PathNotFound)
+	}
+	// step in a random direction that stays outside the obstacles
+	fn random_step(&self, point: Pos2, obstacles: &[Obstacle], rng: &mut XorShift) -> Pos2 {
+		for _ in 0..16 {
+			let angle = rng.next_f64() * TAU;
+			let next = point + self.step_size * Vec2::new(angle.cos(), angle.sin());
+			if obstacles.iter().all(|o| o.signed_distance(next).0 > 0.0) {
+				return next;
+			}
+		}
+		point
+	}
+}
+
+// largest navigation function grid, which covers the start, goal and obstacles
+const MAX_WAVEFRONT_CELLS: f64 = 1e7;
+
+// breadth first distance to the goal over free cells, 8 connected
+struct Wavefront {
+	origin: Pos2,
+	resolution: f64,
+	width: usize,
+	height: usize,
+	// usize::MAX for obstacles and unreachable cells
+	cost: Vec<usize>,
+}
+
+impl Wavefront {
+	fn new(
+		start: Pos2,
+		goal: Pos2,
+		obstacles: &[Obstacle],
+		resolution: f64,
+		field: &PotentialField,
+	) -> Result<Self, PotentialFieldError> {
+		if resolution <= 0.0 {
+			return Err(PotentialFieldError::InvalidInput);
+		}
+		let mut min = start.inf(&goal);
+		let mut max = start.sup(&goal);
+		for obstacle in obstacles {
+			match obstacle {
+				Obstacle::Point(p) => {
+					min = min.inf(p);
+					max = max.sup(p);
+				}
+				Obstacle::Polygon(vertices) => {
+					for v in vertices {
+						min = min.inf(v);
+						max = max.sup(v);
+					}
+				}
+			}
+		}
+		let margin = Vec2::repeat(field.influence_distance.min(1e3 * resolution));
+		let origin = min - margin;
+		let size = (max + margin - origin) / resolution;
+		let cells = (size.x.ceil() + 1.0) * (size.y.ceil() + 1.0);
+		if cells.is_nan() || cells > MAX_WAVEFRONT_CELLS {
+			return Err(PotentialFieldError::InvalidInput);
+		}
+		let (width, height) = (size.x.ceil() as usize + 1, size.y.ceil() as usize + 1);
+
+		let mut grid = Self {
+			origin,
+			resolution,
+			width,
+			height,
+			cost: Vec::from([0]).repeat(width * height),
+		};
+		for i in 0..width * height {
+			let centre = grid.centre(i);
+			if obstacles
+				.iter()
+				.any(|o| o.signed_distance(centre).0 <= 0.5 * resolution)
+			{
+				grid.cost[i] = usize::MAX;
+			}
+		}
+		let mut unvisited = grid
+			.cost
+			.iter()
+			.map(|&c| c != usize::MAX)
+			.collect::<Vec<_>>();
+		for c in &mut grid.cost {
+			*c = usize::MAX;
+		}
+
+		let goal = grid.index(goal);
+		grid.cost[goal] = 0;
+		unvisited[goal] = false;
+		let mut queue = Vec::from([goal]);
+		let mut head = 0;
+		while head < queue.len() {
+			let i = queue[head];
+			head += 1;
+			let neighbours: Vec<usize> = grid.neighbours(i).collect();
+			for j in neighbours {
+				if unvisited[j] {
+					unvisited[j] = false;
+					grid.cost[j] = grid.cost[i] + 1;
+					queue.push(j);
+				}
+			}
+		}
+		Ok(grid)
+	}
+	fn index(&self, point: Pos2) -> usize {
+		let p = (point - self.origin) / self.resolution;
+		let x = (p.x.round().max(0.0) as usize).min(self.width - 1);
+		let y = (p.y.round().max(0.0) as usize).min(self.height - 1);
+		y * self.width + x
+	}
+	fn centre(&self, index: usize) -> Pos2 {
+		let (x, y) = (index % self.width, index / self.width);
+		self.origin + Vec2::new(x as f64, y as f64) * self.resolution
+	}
+	fn neighbours(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+		let (x, y) = ((index % self.width) as i64, (index / self.width) as i64);
+		(-1..=1)
+			.flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+			.filter(move |&(nx, ny)| {
+				(nx, ny) != (x, y)
+					&& nx >= 0 && ny >= 0
+					&& nx < self.width as i64
+					&& ny < self.height as i64
+			})
+			.map(move |(nx, ny)| ny as usize * self.width + nx as usize)
+	}
+	// cell centres down the wavefront finishing at the goal
+	fn descend(&self, from: Pos2, goal: Pos2) -> Result<Vec<Vec2>, PotentialFieldError> {
+		let mut i = self.index(from);
+		if self.cost[i] == usize::MAX {
+			// start the descent from the nearest reachable neighbour
+			i = self
+				.neighbours(i)
+				.filter(|&j| self.cost[j] != usize::MAX)
+				.min_by_key(|&j| self.cost[j])
+				.ok_or(PotentialFieldError::PathNotFound)?;
+		}
+		let mut path = Vec::new();
+		while self.cost[i] > 0 {
+			path.push(self.centre(i).coords);
+			i = self
+				.neighbours(i)
+				.filter(|&j| self.cost[j] < self.cost[i])
+				.min_by(|&a, &b| {
+					let (da, db) = (
+						(self.centre(a) - goal).magnitude(),
+						(self.centre(b) - goal).magnitude(),
+					);
+					self.cost[a].cmp(&self.cost[b]).then(float_cmp(da, db))
+				})
+				.ok_or(PotentialFieldError::PathNotFound)?;
+		}
+		path.push(goal.coords);
+		Ok(path)
+	}
+}
+
+// xorshift64*, enough for picking random walk directions
+struct XorShift(u64);
+
+impl XorShift {
+	fn next_f64(&mut self) -> f64 {
+		self.0 ^= self.0 >> 12;
+		self.0 ^= self.0 << 25;
+		self.0 ^= self.0 >> 27;
+		let v = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d);
+		(v >> 11) as f64 / (1u64 << 53) as f64
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn check_path(path: &[Vec2], start: Pos2, goal: Pos2, obstacles: &[Obstacle]) {
+		assert_eq!(path[0], start.coords);
+		assert_eq!(path[path.len() - 1], goal.coords);
+		for p in path {
+			assert!(obstacles
+				.iter()
+				.all(|o| o.signed_distance(Pos2::from(*p)).0 > 0.0));
+		}
+	}
+
+	#[test]
+	fn signed_distance() {
+		let square = Obstacle::Polygon(Vec::from([
+			Pos2::new(0.0, 0.0),
+			Pos2::new(2.0, 0.0),
+			Pos2::new(2.0, 2.0),
+			Pos2::new(0.0, 2.0),
+		]));
+		let (d, closest) = square.signed_distance(Pos2::new(3.0, 1.0));
+		assert!((d - 1.0).abs() < 1e-12 && (closest - Pos2::new(2.0, 1.0)).magnitude() < 1e-12);
+		assert!((square.signed_distance(Pos2::new(1.5, 1.0)).0 + 0.5).abs() < 1e-12);
+	}
+
+	#[test]
+	fn gradient_descent() {
+		let start = Pos2::new(0.0, 0.0);
+		let goal = Pos2::new(10.0, 1.0);
+		let obstacles = [
+			Obstacle::Point(Pos2::new(4.0, 1.0)),
+			Obstacle::Polygon(Vec::from([
+				Pos2::new(6.0, -2.0),
+				Pos2::new(7.0, -2.0),
+				Pos2::new(7.0, 0.0),
+			])),
+		];
+		let planner = PotentialField::default();
+		let path = planner.plan(start, goal, &obstacles).unwrap();
+		check_path(&path, start, goal, &obstacles);
+
+		// gradient matches the potential
+		let p = Pos2::new(3.0, 0.3);
+		let h = 1e-6;
+		let numerical = Vec2::new(
+			planner.potential(p + Vec2::new(h, 0.0), goal, &obstacles)
+				- planner.potential(p - Vec2::new(h, 0.0), goal, &obstacles),
+			planner.potential(p + Vec2::new(0.0, h), goal, &obstacles)
+				- planner.potential(p - Vec2::new(0.0, h), goal, &obstacles),
+		) / (2.0 * h);
+		assert!((planner.gradient(p, goal, &obstacles) - numerical).magnitude() < 1e-5);
+	}
+
+	#[test]
+	fn escapes_local_minimum() {
+		// u shaped trap facing the start
+		let trap = Obstacle::Polygon(Vec::from([
+			Pos2::new(3.0, -3.0),
+			Pos2::new(5.0, -3.0),
+			Pos2::new(5.0, 3.0),
+			Pos2::new(3.0, 3.0),
+			Pos2::new(3.0, 2.0),
+			Pos2::new(4.0, 2.0),
+			Pos2::new(4.0, -2.0),
+			Pos2::new(3.0, -2.0),
+		]));
+		let obstacles = [trap];
+		let start = Pos2::new(0.0, 0.0);
+		let goal = Pos2::new(8.0, 0.0);
+
+		let planner = PotentialField {
+			escape: LocalMinimumEscape::NavigationFunction { resolution: 0.25 },
+			..Default::default()
+		};
+		let path = planner.plan(start, goal, &obstacles).unwrap();
+		check_path(&path, start, goal, &obstacles);
+		let planner = PotentialField {
+			escape: LocalMinimumEscape::NavigationFunction { resolution: 1e-6 },
+			..Default::default()
+		};
+		assert_eq!(
+			planner.plan(start, goal, &obstacles).unwrap_err(),
+			PotentialFieldError::InvalidInput
+		);
+
+		// a random walk is only good for shallow minima like a gap that is too narrow
+		let obstacles = [
+			Obstacle::Point(Pos2::new(4.0, 0.5)),
+			Obstacle::Point(Pos2::new(4.0, -0.5)),
+		];
+		let planner = PotentialField {
+			escape: LocalMinimumEscape::RandomWalk { steps: 100 },
+			..Default::default()
+		};
+		let path = planner.plan(start, goal, &obstacles).unwrap();
+		check_path(&path, start, goal, &obstacles);
+		// would never leave the minimum
+		let planner = PotentialField {
+			escape: LocalMinimumEscape::RandomWalk { steps: 0 },
+			..planner
+		};
+		assert_eq!(
+			planner.plan(start, goal, &obstacles).unwrap_err(),
+			PotentialFieldError::InvalidInput
+		);
+	}
+}
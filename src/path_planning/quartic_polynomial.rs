@@ -0,0 +1,153 @@
+use crate::prelude::*;
+
+// quartic polynomials leave the end position free
+// used for velocity keeping where only the final velocity and acceleration matter
+
+#[derive(Debug, Copy, Clone)]
+pub enum QuarticError {
+	OutOfRange,
+	InvalidStartTime,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct QuarticPolynomial1D {
+	c: [f64; 5],
+	max_t: f64,
+}
+
+impl QuarticPolynomial1D {
+	pub fn new(
+		// position, velocity, acceleration
+		start: (f64, f64, f64),
+		// velocity, acceleration
+		end: (f64, f64),
+		t1: f64,
+	) -> Result<Self, QuarticError> {
+		if t1 <= 0.0 {
+			return Err(QuarticError::InvalidStartTime);
+		}
+		Ok(Self {
+			c: get_coefficients(start, end, t1),
+			max_t: t1,
+		})
+	}
+	pub fn evaluate(&self, t: f64) -> Result<f64, QuarticError> {
+		if t < 0.0 || t > self.max_t {
+			return Err(QuarticError::OutOfRange);
+		}
+		Ok(self.evaluate_unchecked(t))
+	}
+	#[must_use]
+	pub fn evaluate_unchecked(&self, t: f64) -> f64 {
+		let c = &self.c;
+		c[0] + t * (c[1] + t * (c[2] + t * (c[3] + t * c[4])))
+	}
+	#[must_use]
+	pub fn velocity(&self, t: f64) -> f64 {
+		let c = &self.c;
+		c[1] + t * (2.0 * c[2] + t * (3.0 * c[3] + t * 4.0 * c[4]))
+	}
+	#[must_use]
+	pub fn acceleration(&self, t: f64) -> f64 {
+		let c = &self.c;
+		2.0 * c[2] + t * (6.0 * c[3] + t * 12.0 * c[4])
+	}
+	#[must_use]
+	pub fn jerk(&self, t: f64) -> f64 {
+		6.0 * self.c[3] + 24.0 * self.c[4] * t
+	}
+	#[must_use]
+	pub fn max_t(&self) -> f64 {
+		self.max_t
+	}
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct QuarticPolynomial {
+	x: QuarticPolynomial1D,
+	y: QuarticPolynomial1D,
+}
+
+impl QuarticPolynomial {
+	pub fn new(
+		// position, velocity, acceleration
+		start: (Vec2, Vec2, Vec2),
+		// velocity, acceleration
+		end: (Vec2, Vec2),
+		t1: f64,
+	) -> Result<Self, QuarticError> {
+		Ok(Self {
+			x: QuarticPolynomial1D::new((start.0.x, start.1.x, start.2.x), (end.0.x, end.1.x), t1)?,
+			y: QuarticPolynomial1D::new((start.0.y, start.1.y, start.2.y), (end.0.y, end.1.y), t1)?,
+		})
+	}
+	pub fn evaluate(&self, t: f64) -> Result<Vec2, QuarticError> {
+		if t < 0.0 || t > self.max_t() {
+			return Err(QuarticError::OutOfRange);
+		}
+		Ok(self.evaluate_unchecked(t))
+	}
+	#[must_use]
+	pub fn evaluate_unchecked(&self, t: f64) -> Vec2 {
+		Vec2::new(self.x.evaluate_unchecked(t), self.y.evaluate_unchecked(t))
+	}
+	#[must_use]
+	pub fn velocity(&self, t: f64) -> Vec2 {
+		Vec2::new(self.x.velocity(t), self.y.velocity(t))
+	}
+	#[must_use]
+	pub fn acceleration(&self, t: f64) -> Vec2 {
+		Vec2::new(self.x.acceleration(t), self.y.acceleration(t))
+	}
+	#[must_use]
+	pub fn jerk(&self, t: f64) -> Vec2 {
+		Vec2::new(self.x.jerk(t), self.y.jerk(t))
+	}
+	#[must_use]
+	pub fn max_t(&self) -> f64 {
+		self.x.max_t
+	}
+}
+
+fn get_coefficients((x0, v0, a0): (f64, f64, f64), (v1, a1): (f64, f64), t1: f64) -> [f64; 5] {
+	// remaining velocity change not accounted for by the initial acceleration
+	let dv = v1 - v0 - a0 * t1;
+	let da = a1 - a0;
+
+	let c4 = (0.5 * da * t1 - dv) / (2.0 * t1 * t1 * t1);
+	let c3 = da / (6.0 * t1) - 2.0 * c4 * t1;
+
+	[x0, v0, 0.5 * a0, c3, c4]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn boundary_conditions() {
+		let p = QuarticPolynomial1D::new((3.0, 1.0, 0.5), (2.5, -0.2), 4.0).unwrap();
+		assert!((p.evaluate(0.0).unwrap() - 3.0).abs() < 1e-12);
+		assert!((p.velocity(0.0) - 1.0).abs() < 1e-12);
+		assert!((p.acceleration(0.0) - 0.5).abs() < 1e-12);
+		assert!((p.velocity(4.0) - 2.5).abs() < 1e-12);
+		assert!((p.acceleration(4.0) + 0.2).abs() < 1e-12);
+		assert!(p.evaluate(4.1).is_err());
+		assert!(QuarticPolynomial1D::new((0.0, 0.0, 0.0), (1.0, 0.0), 0.0).is_err());
+	}
+
+	#[test]
+	fn two_dimensional() {
+		let start = (Vec2::new(1.0, 2.0), Vec2::new(0.5, 0.0), Vec2::zeros());
+		let end = (Vec2::new(2.0, 1.0), Vec2::zeros());
+		let p = QuarticPolynomial::new(start, end, 3.0).unwrap();
+		assert!((p.evaluate(0.0).unwrap() - start.0).magnitude() < 1e-12);
+		assert!((p.velocity(3.0) - end.0).magnitude() < 1e-12);
+		assert!((p.acceleration(3.0) - end.1).magnitude() < 1e-12);
+
+		// jerk is the derivative of acceleration
+		let h = 1e-6;
+		let numerical = (p.acceleration(1.0 + h) - p.acceleration(1.0 - h)) / (2.0 * h);
+		assert!((p.jerk(1.0) - numerical).magnitude() < 1e-6);
+	}
+}
@@ -1,4 +1,5 @@
-use crate::prelude::Vec2;
+use super::polynomial;
+use crate::prelude::*;
 
 #[derive(Debug, Copy, Clone)]
 pub enum QuinticError {
@@ -6,6 +7,7 @@ pub enum QuinticError {
 	InvalidStartTime,
 	InvalidTimeStep,
 	ValidPolynomialNotFound,
+	InvalidWaypoints,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -66,6 +68,56 @@ impl QuinticPolynomial {
 
 		Err(QuinticError::ValidPolynomialNotFound)
 	}
+
+	// finds the shortest duration accepted by the validator to within tolerance with bisection
+	// assumes that if a duration is valid then all longer durations are also valid
+	pub fn find_optimal_new<F>(
+		start: (Vec2, Vec2, Vec2),
+		end: (Vec2, Vec2, Vec2),
+		validator: &F,
+		min_time: f64,
+		max_time: f64,
+		tolerance: f64,
+	) -> Result<Self, QuinticError>
+	where
+		F: Fn(&Self) -> bool,
+	{
+		if tolerance <= 0.0 || max_time <= 0.0 || max_time < min_time {
+			return Err(QuinticError::InvalidTimeStep);
+		}
+		if min_time < 0.0 {
+			return Err(QuinticError::InvalidStartTime);
+		}
+
+		let valid = |t: f64| -> Result<Option<Self>, QuinticError> {
+			let p = Self::new(start, end, t)?;
+			Ok(validator(&p).then_some(p))
+		};
+
+		// zero duration polynomials aren't defined
+		if min_time > 0.0 {
+			if let Some(p) = valid(min_time)? {
+				return Ok(p);
+			}
+		}
+		let Some(mut best) = valid(max_time)? else {
+			return Err(QuinticError::ValidPolynomialNotFound);
+		};
+
+		// invariant: lo is invalid and hi is valid
+		let (mut lo, mut hi) = (min_time, max_time);
+		while hi - lo > tolerance {
+			let mid = 0.5 * (lo + hi);
+			if let Some(p) = valid(mid)? {
+				best = p;
+				hi = mid;
+			} else {
+				lo = mid;
+			}
+		}
+
+		Ok(best)
+	}
 	#[must_use]
 	pub fn velocity(&self, t: f64) -> Vec2 {
 		let t_2 = t * t;
@@ -139,6 +191,110 @@ impl QuinticPolynomial {
 	pub fn max_t(&self) -> f64 {
 		self.max_t
 	}
+	// the max functions find the exact maximum magnitude over [0, max_t]
+	// by checking the end points and the roots of the derivative of the squared magnitude
+	#[must_use]
+	pub fn max_speed(&self) -> f64 {
+		let (cx, cy) = (&self.cx, &self.cy);
+		let vx = [cx[1], 2.0 * cx[2], 3.0 * cx[3], 4.0 * cx[4], 5.0 * cx[5]];
+		let vy = [cy[1], 2.0 * cy[2], 3.0 * cy[3], 4.0 * cy[4], 5.0 * cy[5]];
+		polynomial::max_sqrt_in(&polynomial::magnitude_squared(&vx, &vy), 0.0, self.max_t)
+	}
+	#[must_use]
+	pub fn max_acceleration(&self) -> f64 {
+		let (cx, cy) = (&self.cx, &self.cy);
+		let ax = [2.0 * cx[2], 6.0 * cx[3], 12.0 * cx[4], 20.0 * cx[5]];
+		let ay = [2.0 * cy[2], 6.0 * cy[3], 12.0 * cy[4], 20.0 * cy[5]];
+		polynomial::max_sqrt_in(&polynomial::magnitude_squared(&ax, &ay), 0.0, self.max_t)
+	}
+	#[must_use]
+	pub fn max_jerk(&self) -> f64 {
+		let (cx, cy) = (&self.cx, &self.cy);
+		let jx = [6.0 * cx[3], 24.0 * cx[4], 60.0 * cx[5]];
+		let jy = [6.0 * cy[3], 24.0 * cy[4], 60.0 * cy[5]];
+		polynomial::max_sqrt_in(&polynomial::magnitude_squared(&jx, &jy), 0.0, self.max_t)
+	}
+}
+
+// single axis quintic, e.g. for lateral offset in a frenet frame
+#[derive(Debug, Copy, Clone)]
+pub struct QuinticPolynomial1D {
+	c: [f64; 6],
+	max_t: f64,
+}
+
+impl QuinticPolynomial1D {
+	pub fn new(
+		// position, velocity, acceleration
+		start: (f64, f64, f64),
+		end: (f64, f64, f64),
+		t1: f64,
+	) -> Result<Self, QuinticError> {
+		if t1 <= 0.0 {
+			return Err(QuinticError::InvalidStartTime);
+		}
+		Ok(Self {
+			c: get_coefficients(start, end, t1),
+			max_t: t1,
+		})
+	}
+	pub fn evaluate(&self, t: f64) -> Result<f64, QuinticError> {
+		if t < 0.0 || t > self.max_t {
+			return Err(QuinticError::OutOfRange);
+		}
+		Ok(self.evaluate_unchecked(t))
+	}
+	#[must_use]
+	pub fn evaluate_unchecked(&self, t: f64) -> f64 {
+		let c = &self.c;
+		c[0] + t * (c[1] + t * (c[2] + t * (c[3] + t * (c[4] + t * c[5]))))
+	}
+	#[must_use]
+	pub fn velocity(&self, t: f64) -> f64 {
+		let c = &self.c;
+		c[1] + t * (2.0 * c[2] + t * (3.0 * c[3] + t * (4.0 * c[4] + t * 5.0 * c[5])))
+	}
+	#[must_use]
+	pub fn acceleration(&self, t: f64) -> f64 {
+		let c = &self.c;
+		2.0 * c[2] + t * (6.0 * c[3] + t * (12.0 * c[4] + t * 20.0 * c[5]))
+	}
+	#[must_use]
+	pub fn jerk(&self, t: f64) -> f64 {
+		let c = &self.c;
+		6.0 * c[3] + t * (24.0 * c[4] + t * 60.0 * c[5])
+	}
+	#[must_use]
+	pub fn max_t(&self) -> f64 {
+		self.max_t
+	}
+}
+
+// validator for use with the find optimal functions
+// use f64::INFINITY to leave a quantity unconstrained
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct KinematicLimits {
+	pub max_speed: f64,
+	pub max_acceleration: f64,
+	pub max_jerk: f64,
+}
+
+impl KinematicLimits {
+	#[must_use]
+	pub const fn new(max_speed: f64, max_acceleration: f64, max_jerk: f64) -> Self {
+		Self {
+			max_speed,
+			max_acceleration,
+			max_jerk,
+		}
+	}
+	#[must_use]
+	pub fn is_satisfied_by(&self, p: &QuinticPolynomial) -> bool {
+		(self.max_speed == f64::INFINITY || p.max_speed() <= self.max_speed)
+			&& (self.max_acceleration == f64::INFINITY
+				|| p.max_acceleration() <= self.max_acceleration)
+			&& (self.max_jerk == f64::INFINITY || p.max_jerk() <= self.max_jerk)
+	}
 }
 
 fn get_coefficients(
@@ -269,4 +425,62 @@ mod tests {
 
 		assert!(check_correct(sx, sv, sa, 0.0) && check_correct(ex, ev, ea, t_max));
 	}
+
+	#[test]
+	fn find_optimal() {
+		let start = (Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::zeros());
+		let end = (Vec2::new(10.0, 5.0), Vec2::new(0.0, 1.0), Vec2::zeros());
+		let limits = KinematicLimits::new(2.0, 1.0, f64::INFINITY);
+		let validator = |p: &QuinticPolynomial| limits.is_satisfied_by(p);
+		let tolerance = 1e-4;
+
+		let p =
+			QuinticPolynomial::find_optimal_new(start, end, &validator, 0.0, 100.0, tolerance)
+				.unwrap();
+		assert!(validator(&p));
+		let shorter = QuinticPolynomial::new(start, end, p.max_t() - 2.0 * tolerance).unwrap();
+		assert!(!validator(&shorter));
+
+		// agrees with linear search
+		let linear =
+			QuinticPolynomial::iterative_find_optimal_new(start, end, &validator, 0.0, 100.0, 0.01)
+				.unwrap();
+		assert!(linear.max_t() >= p.max_t() && linear.max_t() - p.max_t() < 0.01 + tolerance);
+
+		assert!(matches!(
+			QuinticPolynomial::find_optimal_new(start, end, &validator, 0.0, 1.0, tolerance),
+			Err(QuinticError::ValidPolynomialNotFound)
+		));
+	}
+
+	#[test]
+	fn analytic_maximums() {
+		let p = QuinticPolynomial::new(
+			(
+				Vec2::new(15.0, 20.0),
+				polar(0.5, deg_to_rad(60.0)),
+				polar(0.1, deg_to_rad(60.0)),
+			),
+			(Vec2::new(30.0, -10.0), polar(1.3, deg_to_rad(-20.0)), Vec2::zeros()),
+			12.0,
+		)
+		.unwrap();
+
+		let samples = 100_000;
+		let sampled = |f: &dyn Fn(f64) -> Vec2| {
+			(0..=samples)
+				.map(|i| f(p.max_t() * f64::from(i) / f64::from(samples)).magnitude())
+				.fold(0.0, f64::max)
+		};
+
+		let checks = [
+			(p.max_speed(), sampled(&|t| p.velocity(t))),
+			(p.max_acceleration(), sampled(&|t| p.acceleration(t))),
+			(p.max_jerk(), sampled(&|t| p.jerk(t))),
+		];
+		for (exact, sampled) in checks {
+			assert!(exact >= sampled - 1e-12);
+			assert!(exact - sampled < 1e-6);
+		}
+	}
 }
@@ -0,0 +1,216 @@
+use super::quintic_polynomial::{QuinticError, QuinticPolynomial};
+use crate::prelude::*;
+
+// piecewise quintic through a sequence of states
+// neighbouring segments share the junction state so the trajectory is C2
+#[derive(Debug, Clone)]
+pub struct QuinticTrajectory {
+	segments: Vec<QuinticPolynomial>,
+	// global start time of each segment
+	start_times: Vec<f64>,
+	max_t: f64,
+}
+
+impl QuinticTrajectory {
+	pub fn new(
+		// position, velocity, acceleration at each waypoint
+		states: &[(Vec2, Vec2, Vec2)],
+		durations: &[f64],
+	) -> Result<Self, QuinticError> {
+		if states.len() < 2 || durations.len() + 1 != states.len() {
+			return Err(QuinticError::InvalidWaypoints);
+		}
+		if durations.iter().any(|&t| t <= 0.0) {
+			return Err(QuinticError::InvalidTimeStep);
+		}
+
+		let segments = states
+			.windows(2)
+			.zip(durations)
+			.map(|(s, &t)| QuinticPolynomial::new(s[0], s[1], t))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let mut start_times = Vec::with_capacity(durations.len());
+		let mut max_t = 0.0;
+		for t in durations {
+			start_times.push(max_t);
+			max_t += t;
+		}
+
+		Ok(Self {
+			segments,
+			start_times,
+			max_t,
+		})
+	}
+	// velocities and accelerations at the intermediate waypoints are chosen
+	// with finite differences of the neighbouring waypoints
+	pub fn from_waypoints(
+		positions: &[Vec2],
+		// velocity, acceleration
+		start: (Vec2, Vec2),
+		end: (Vec2, Vec2),
+		durations: &[f64],
+	) -> Result<Self, QuinticError> {
+		let n = positions.len();
+		if n < 2 || durations.len() + 1 != n {
+			return Err(QuinticError::InvalidWaypoints);
+		}
+		if durations.iter().any(|&t| t <= 0.0) {
+			return Err(QuinticError::InvalidTimeStep);
+		}
+
+		let mut states = Vec::with_capacity(n);
+		states.push((positions[0], start.0, start.1));
+		for i in 1..n - 1 {
+			let (t0, t1) = (durations[i - 1], durations[i]);
+			let v0 = (positions[i] - positions[i - 1]) / t0;
+			let v1 = (positions[i + 1] - positions[i]) / t1;
+			let velocity = (positions[i + 1] - positions[i - 1]) / (t0 + t1);
+			let acceleration = 2.0 * (v1 - v0) / (t0 + t1);
+			states.push((positions[i], velocity, acceleration));
+		}
+		states.push((positions[n - 1], end.0, end.1));
+
+		Self::new(&states, durations)
+	}
+	// segment durations proportional to the distance between waypoints
+	pub fn durations_from_speed(positions: &[Vec2], speed: f64) -> Result<Vec<f64>, QuinticError> {
+		if speed <= 0.0 {
+			return Err(QuinticError::InvalidTimeStep);
+		}
+		let durations: Vec<f64> = positions
+			.windows(2)
+			.map(|w| (w[1] - w[0]).magnitude() / speed)
+			.collect();
+		if durations.iter().any(|&t| t <= 0.0) {
+			return Err(QuinticError::InvalidWaypoints);
+		}
+		Ok(durations)
+	}
+	// segment and local time, times outside of the trajectory use the end segments
+	fn segment(&self, t: f64) -> (&QuinticPolynomial, f64) {
+		let i = self
+			.start_times
+			.partition_point(|&v| v <= t)
+			.saturating_sub(1);
+		(&self.segments[i], t - self.start_times[i])
+	}
+	pub fn evaluate(&self, t: f64) -> Result<Vec2, QuinticError> {
+		if t < 0.0 || t > self.max_t {
+			return Err(QuinticError::OutOfRange);
+		}
+		Ok(self.evaluate_unchecked(t))
+	}
+	#[must_use]
+	pub fn evaluate_unchecked(&self, t: f64) -> Vec2 {
+		let (segment, t) = self.segment(t);
+		segment.evaluate_unchecked(t)
+	}
+	#[must_use]
+	pub fn velocity(&self, t: f64) -> Vec2 {
+		let (segment, t) = self.segment(t);
+		segment.velocity(t)
+	}
+	#[must_use]
+	pub fn acceleration(&self, t: f64) -> Vec2 {
+		let (segment, t) = self.segment(t);
+		segment.acceleration(t)
+	}
+	#[must_use]
+	pub fn jerk(&self, t: f64) -> Vec2 {
+		let (segment, t) = self.segment(t);
+		segment.jerk(t)
+	}
+	#[must_use]
+	pub fn max_t(&self) -> f64 {
+		self.max_t
+	}
+	#[must_use]
+	pub fn segments(&self) -> &[QuinticPolynomial] {
+		&self.segments
+	}
+	#[must_use]
+	pub fn start_times(&self) -> &[f64] {
+		&self.start_times
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn waypoints() -> [Vec2; 5] {
+		[
+			Vec2::new(0.0, 0.0),
+			Vec2::new(2.0, 1.0),
+			Vec2::new(4.0, -1.0),
+			Vec2::new(5.0, 3.0),
+			Vec2::new(8.0, 3.0),
+		]
+	}
+
+	#[test]
+	fn passes_through_waypoints() {
+		let positions = waypoints();
+		let durations = QuinticTrajectory::durations_from_speed(&positions, 1.5).unwrap();
+		let trajectory = QuinticTrajectory::from_waypoints(
+			&positions,
+			(Vec2::zeros(), Vec2::zeros()),
+			(Vec2::zeros(), Vec2::zeros()),
+			&durations,
+		)
+		.unwrap();
+
+		for (&t, p) in trajectory.start_times().iter().zip(positions) {
+			assert!((trajectory.evaluate(t).unwrap() - p).magnitude() < 1e-9);
+		}
+		let end = trajectory.evaluate(trajectory.max_t()).unwrap();
+		assert!((end - positions[4]).magnitude() < 1e-9);
+		assert!(trajectory.velocity(trajectory.max_t()).magnitude() < 1e-9);
+		assert!(trajectory.evaluate(trajectory.max_t() + 0.1).is_err());
+	}
+
+	#[test]
+	fn continuous_at_junctions() {
+		let positions = waypoints();
+		let trajectory = QuinticTrajectory::from_waypoints(
+			&positions,
+			(Vec2::new(1.0, 0.0), Vec2::zeros()),
+			(Vec2::new(1.0, 0.0), Vec2::zeros()),
+			&[1.0, 2.0, 1.5, 2.5],
+		)
+		.unwrap();
+
+		let h = 1e-7;
+		for &t in &trajectory.start_times()[1..] {
+			assert!(
+				(trajectory.evaluate_unchecked(t - h) - trajectory.evaluate_unchecked(t))
+					.magnitude() < 1e-5
+			);
+			assert!((trajectory.velocity(t - h) - trajectory.velocity(t)).magnitude() < 1e-5);
+			assert!(
+				(trajectory.acceleration(t - h) - trajectory.acceleration(t)).magnitude() < 1e-5
+			);
+		}
+	}
+
+	#[test]
+	fn invalid_input() {
+		let positions = waypoints();
+		assert!(QuinticTrajectory::from_waypoints(
+			&positions,
+			(Vec2::zeros(), Vec2::zeros()),
+			(Vec2::zeros(), Vec2::zeros()),
+			&[1.0, 1.0],
+		)
+		.is_err());
+		assert!(QuinticTrajectory::from_waypoints(
+			&positions,
+			(Vec2::zeros(), Vec2::zeros()),
+			(Vec2::zeros(), Vec2::zeros()),
+			&[1.0, 1.0, 0.0, 1.0],
+		)
+		.is_err());
+	}
+}
@@ -0,0 +1,153 @@
+use super::grid::{Cell, Grid, GridError, OpenNode};
+use crate::prelude::*;
+
+// references:
+// https://doi.org/10.1613/jair.2994 (Daniel et al., Theta*)
+// https://doi.org/10.1609/aaai.v24i1.7566 (Nash, Koenig & Tovey, Lazy Theta*)
+// --------
+// A* where a cell can take its parent's parent when there is line of sight,
+// giving any angle paths, cell costs are ignored apart from blocked cells
+// Lazy Theta* assumes line of sight when a cell is opened and only checks it
+// when the cell is expanded which needs far fewer checks
+// --------
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AnyAngleVariant {
+	ThetaStar,
+	LazyThetaStar,
+}
+
+// cell centres from start to goal with line of sight between consecutive points
+pub fn theta_star(
+	grid: &Grid,
+	start: Pos2,
+	goal: Pos2,
+	variant: AnyAngleVariant,
+) -> Result<Vec<Pos2>, GridError> {
+	let start = grid.cell(start).ok_or(GridError::OutOfBounds)?;
+	let goal = grid.cell(goal).ok_or(GridError::OutOfBounds)?;
+	if !grid.is_free(start) || !grid.is_free(goal) {
+		return Err(GridError::PathNotFound);
+	}
+	let distance = |a: Cell, b: Cell| (grid.centre(a) - grid.centre(b)).magnitude();
+
+	let n = grid.width() * grid.height();
+	let mut g = Vec::from([f64::INFINITY]).repeat(n);
+	let mut parent: Vec<usize> = (0..n).collect();
+	let mut closed = Vec::from([false]).repeat(n);
+
+	let start_index = grid.index(start);
+	g[start_index] = 0.0;
+	let mut open = BinaryHeap::from([OpenNode {
+		cost: distance(start, goal),
+		index: start_index,
+	}]);
+	while let Some(OpenNode { index, .. }) = open.pop() {
+		if closed[index] {
+			continue;
+		}
+		let cell = grid.cell_at(index);
+		if variant == AnyAngleVariant::LazyThetaStar
+			&& !grid.line_of_sight(grid.cell_at(parent[index]), cell)
+		{
+			// the assumed shortcut is blocked so take the best expanded neighbour
+			let (best, cost) = grid
+				.neighbours(cell)
+				.map(|(n, _)| grid.index(n))
+				.filter(|&n| closed[n])
+				.map(|n| (n, g[n] + distance(grid.cell_at(n), cell)))
+				.min_by(|a, b| float_cmp(a.1, b.1))
+				.ok_or(GridError::PathNotFound)?;
+			parent[index] = best;
+			g[index] = cost;
+		}
+		closed[index] = true;
+
+		if cell == goal {
+			let mut path = Vec::from([grid.centre(cell)]);
+			let mut i = index;
+			while parent[i] != i {
+				i = parent[i];
+				path.push(grid.centre(grid.cell_at(i)));
+			}
+			path.reverse();
+			return Ok(path);
+		}
+
+		let from = parent[index];
+		for (next, _) in grid.neighbours(cell) {
+			let next_index = grid.index(next);
+			if closed[next_index] {
+				continue;
+			}
+			let shortcut = match variant {
+				AnyAngleVariant::ThetaStar => grid.line_of_sight(grid.cell_at(from), next),
+				AnyAngleVariant::LazyThetaStar => true,
+			};
+			let (via, cost) = if shortcut {
+				(from, g[from] + distance(grid.cell_at(from), next))
+			} else {
+				(index, g[index] + distance(cell, next))
+			};
+			if cost < g[next_index] {
+				g[next_index] = cost;
+				parent[next_index] = via;
+				open.push(OpenNode {
+					cost: cost + distance(next, goal),
+					index: next_index,
+				});
+			}
+		}
+	}
+	Err(GridError::PathNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::path_planning::DStarLite;
+
+	fn length(path: &[Pos2]) -> f64 {
+		path.windows(2).map(|w| (w[1] - w[0]).magnitude()).sum()
+	}
+
+	#[test]
+	fn any_angle_paths() {
+		let (w, h) = (30, 20);
+		let occupied: Vec<bool> = (0..w * h)
+			.map(|i| {
+				let (x, y) = (i % w, i / w);
+				(x == 10 && y < 15) || (x == 20 && y > 4) || (8..13).contains(&x) && y == 17
+			})
+			.collect();
+		let grid = Grid::from_occupancy(&occupied, w, 0.5, Pos2::new(0.0, 0.0)).unwrap();
+		let (start, goal) = (grid.centre((0, 0)), grid.centre((29, 19)));
+
+		let mut d_star = DStarLite::new(grid.clone(), start, goal).unwrap();
+		d_star.plan().unwrap();
+		let grid_length = d_star.cost_to_goal(start).unwrap();
+
+		for variant in [AnyAngleVariant::ThetaStar, AnyAngleVariant::LazyThetaStar] {
+			let path = theta_star(&grid, start, goal, variant).unwrap();
+			assert_eq!(path[0], start);
+			assert_eq!(path[path.len() - 1], goal);
+			for w in path.windows(2) {
+				assert!(grid.line_of_sight(grid.cell(w[0]).unwrap(), grid.cell(w[1]).unwrap()));
+			}
+			// shorter than the 8 connected path and only turns either side of the wall ends
+			assert!(length(&path) < grid_length - 0.5);
+			assert!(path.len() <= 6);
+		}
+
+		// open grid is a single straight line
+		let open = Grid::new(20, 20, 1.0, Pos2::new(0.0, 0.0)).unwrap();
+		let path = theta_star(
+			&open,
+			Pos2::new(0.5, 0.5),
+			Pos2::new(17.5, 6.5),
+			AnyAngleVariant::LazyThetaStar,
+		)
+		.unwrap();
+		assert_eq!(path.len(), 2);
+	}
+}
@@ -0,0 +1,429 @@
+use super::curved_paths::{gear, PathSegmentType};
+use crate::prelude::*;
+
+// references:
+// https://doi.org/10.1109/TRO.2018.2819195 (Pham & Pham, TOPP-RA)
+// --------
+// the path is gridded by arc length s and the state at each grid point is
+// x = s'^2 with control u = s'' so x[i + 1] = x[i] + 2 ds u[i]
+//
+// every constraint is linear in (u, x) so the set of x from which the end of
+// the path can be reached (the controllable set) is found backwards with a
+// small LP at each grid point, then a forward pass takes the largest u that
+// stays inside the controllable sets which gives the time optimal profile
+// --------
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ToppError {
+	NotEnoughPoints,
+	InvalidConstraints,
+	Infeasible,
+}
+
+// limits for each wheel of a differential drive robot
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WheelLimits {
+	// distance between the wheels
+	pub track_width: f64,
+	pub max_velocity: f64,
+	pub max_acceleration: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PathConstraints {
+	pub max_velocity: f64,
+	// along the path
+	pub max_acceleration: f64,
+	pub max_lateral_acceleration: f64,
+	pub max_angular_velocity: f64,
+	pub wheels: Option<WheelLimits>,
+}
+
+impl PathConstraints {
+	#[must_use]
+	pub const fn new(max_velocity: f64, max_acceleration: f64) -> Self {
+		Self {
+			max_velocity,
+			max_acceleration,
+			max_lateral_acceleration: f64::INFINITY,
+			max_angular_velocity: f64::INFINITY,
+			wheels: None,
+		}
+	}
+	fn is_valid(&self) -> bool {
+		let wheels_valid = self.wheels.is_none_or(|w| {
+			w.track_width >= 0.0 && w.max_velocity > 0.0 && w.max_acceleration > 0.0
+		});
+		self.max_velocity > 0.0
+			&& self.max_velocity.is_finite()
+			&& self.max_acceleration > 0.0
+			&& self.max_acceleration.is_finite()
+			&& self.max_lateral_acceleration > 0.0
+			&& self.max_angular_velocity > 0.0
+			&& wheels_valid
+	}
+	// largest x at a grid point
+	fn max_x(&self, curvature: f64, gear: f64) -> f64 {
+		let k = curvature.abs();
+		let mut max = self.max_velocity * self.max_velocity;
+		if k > 0.0 {
+			max = max
+				.min(self.max_lateral_acceleration / k)
+				.min(self.max_angular_velocity * self.max_angular_velocity / (k * k));
+		}
+		if let Some(w) = self.wheels {
+			for side in [-1.0, 1.0] {
+				let f = gear + side * curvature * w.track_width / 2.0;
+				if f != 0.0 {
+					max = max.min(w.max_velocity * w.max_velocity / (f * f));
+				}
+			}
+		}
+		max
+	}
+	// constraints a u + b x in [lo, hi]
+	fn acceleration(&self, curvature: f64, curvature_derivative: f64, gear: f64) -> Vec<[f64; 4]> {
+		let mut constraints =
+			Vec::from([[1.0, 0.0, -self.max_acceleration, self.max_acceleration]]);
+		if let Some(w) = self.wheels {
+			// wheel speed is s' (gear + side k w / 2)
+			for side in [-1.0, 1.0] {
+				let half = side * w.track_width / 2.0;
+				constraints.push([
+					gear + half * curvature,
+					half * curvature_derivative,
+					-w.max_acceleration,
+					w.max_acceleration,
+				]);
+			}
+		}
+		constraints
+	}
+}
+
+// maximise objective . (u, x) subject to a u + b x <= c
+// the feasible region is bounded so the optimum is at a vertex
+fn solve_lp(constraints: &[[f64; 3]], objective: [f64; 2]) -> Option<[f64; 2]> {
+	let mut best: Option<([f64; 2], f64)> = None;
+	for (i, p) in constraints.iter().enumerate() {
+		for q in &constraints[i + 1..] {
+			let det = p[0] * q[1] - p[1] * q[0];
+			if det.abs() < 1e-12 {
+				continue;
+			}
+			let u = (p[2] * q[1] - p[1] * q[2]) / det;
+			let x = (p[0] * q[2] - p[2] * q[0]) / det;
+			let feasible = constraints
+				.iter()
+				.all(|c| c[0] * u + c[1] * x <= c[2] + 1e-9 * (1.0 + c[2].abs()));
+			let value = objective[0] * u + objective[1] * x;
+			if feasible && best.is_none_or(|b| value > b.1) {
+				best = Some(([u, x], value));
+			}
+		}
+	}
+	best.map(|b| b.0)
+}
+
+// time optimal motion along a sampled path starting and ending at rest
+#[derive(Debug, Clone)]
+pub struct TimeOptimalPath {
+	poses: Vec<Ray>,
+	distances: Vec<f64>,
+	times: Vec<f64>,
+	// negative when reversing
+	velocities: Vec<f64>,
+	// acceleration along the path and direction of travel over each interval
+	accelerations: Vec<f64>,
+	gears: Vec<f64>,
+}
+
+impl TimeOptimalPath {
+	// samples from Dubins::get_points or ReedsShepp::get_points, stopping at cusps
+	pub fn new(
+		samples: &[(Ray, PathSegmentType)],
+		constraints: PathConstraints,
+	) -> Result<Self, ToppError> {
+		let poses: Vec<Ray> = samples.iter().map(|v| v.0).collect();
+		let gears: Vec<f64> = samples[1.min(samples.len())..]
+			.iter()
+			.map(|v| gear(v.1))
+			.collect();
+		Self::from_poses(&poses, &gears, constraints)
+	}
+	// headings are taken half way between neighbouring segments
+	pub fn from_polyline(points: &[Pos2], constraints: PathConstraints) -> Result<Self, ToppError> {
+		let n = points.len();
+		if n < 2 {
+			return Err(ToppError::NotEnoughPoints);
+		}
+		let headings: Vec<f64> = points
+			.windows(2)
+			.map(|w| (w[1].y - w[0].y).atan2(w[1].x - w[0].x))
+			.collect();
+		let poses: Vec<Ray> = (0..n)
+			.map(|i| {
+				let angle = match i {
+					0 => headings[0],
+					_ if i == n - 1 => headings[n - 2],
+					_ => headings[i - 1] + 0.5 * map_angle(headings[i] - headings[i - 1]),
+				};
+				Ray::new(points[i], angle)
+			})
+			.collect();
+		Self::from_poses(&poses, &[1.0].repeat(n - 1), constraints)
+	}
+	// gears are for each interval between poses
+	fn from_poses(
+		poses: &[Ray],
+		gears: &[f64],
+		constraints: PathConstraints,
+	) -> Result<Self, ToppError> {
+		if !constraints.is_valid() {
+			return Err(ToppError::InvalidConstraints);
+		}
+
+		// drop repeated points
+		let mut grid: Vec<(Ray, f64)> = Vec::with_capacity(poses.len());
+		for (i, &pose) in poses.iter().enumerate() {
+			if grid.last().is_none_or(|g| g.0.pos != pose.pos) {
+				grid.push((pose, gears.get(i.saturating_sub(1)).copied().unwrap_or(1.0)));
+			}
+		}
+		let n = grid.len();
+		if n < 2 {
+			return Err(ToppError::NotEnoughPoints);
+		}
+		let poses: Vec<Ray> = grid.iter().map(|g| g.0).collect();
+		// gear of the interval ending at each point, the first uses the first interval
+		let mut gears: Vec<f64> = grid.iter().map(|g| g.1).collect();
+		gears[0] = gears[1];
+
+		let mut distances = Vec::with_capacity(n);
+		distances.push(0.0);
+		for w in poses.windows(2) {
+			distances.push(distances[distances.len() - 1] + (w[1].pos - w[0].pos).magnitude());
+		}
+
+		// central differences of heading and curvature
+		let central = |values: &[f64], i: usize, angle: bool| {
+			let (a, b) = (i.saturating_sub(1), (i + 1).min(n - 1));
+			let dv = values[b] - values[a];
+			let dv = if angle { map_angle(dv) } else { dv };
+			dv / (distances[b] - distances[a])
+		};
+		let headings: Vec<f64> = poses.iter().map(|p| p.angle).collect();
+		let curvatures: Vec<f64> = (0..n).map(|i| central(&headings, i, true)).collect();
+		let curvature_derivatives: Vec<f64> =
+			(0..n).map(|i| central(&curvatures, i, false)).collect();
+
+		let max_x: Vec<f64> = (0..n)
+			.map(|i| {
+				let cusp = i > 0 && i + 1 < n && gears[i] != gears[i + 1];
+				if cusp || i == 0 || i == n - 1 {
+					0.0
+				} else {
+					constraints.max_x(curvatures[i], gears[i + 1])
+				}
+			})
+			.collect();
+		let acceleration: Vec<Vec<[f64; 4]>> = (0..n)
+			.map(|i| {
+				let gear = gears[(i + 1).min(n - 1)];
+				constraints.acceleration(curvatures[i], curvature_derivatives[i], gear)
+			})
+			.collect();
+
+		// controllable sets backwards from rest at the end
+		let mut controllable = Vec::from([(0.0, 0.0)]).repeat(n);
+		for i in (0..n - 1).rev() {
+			let ds = distances[i + 1] - distances[i];
+			let (lo, hi) = controllable[i + 1];
+			let mut lp = Vec::from([
+				[0.0, -1.0, 0.0],
+				[0.0, 1.0, max_x[i]],
+				[2.0 * ds, 1.0, hi],
+				[-2.0 * ds, -1.0, -lo],
+			]);
+			for &[a, b, lower, upper] in &acceleration[i] {
+				lp.push([a, b, upper]);
+				lp.push([-a, -b, -lower]);
+			}
+			let max = solve_lp(&lp, [0.0, 1.0]).ok_or(ToppError::Infeasible)?;
+			let min = solve_lp(&lp, [0.0, -1.0]).ok_or(ToppError::Infeasible)?;
+			controllable[i] = (min[1].max(0.0), max[1].max(0.0));
+		}
+
+		// greedy forward pass
+		let mut x = Vec::with_capacity(n);
+		x.push(0.0);
+		let mut accelerations = Vec::with_capacity(n - 1);
+		for i in 0..n - 1 {
+			let ds = distances[i + 1] - distances[i];
+			let xi = x[i];
+			let (lo, hi) = controllable[i + 1];
+			let (mut u_min, mut u_max) = ((lo - xi) / (2.0 * ds), (hi - xi) / (2.0 * ds));
+			for &[a, b, lower, upper] in &acceleration[i] {
+				if a.abs() < 1e-12 {
+					continue;
+				}
+				let (p, q) = ((lower - b * xi) / a, (upper - b * xi) / a);
+				u_min = u_min.max(p.min(q));
+				u_max = u_max.min(p.max(q));
+			}
+			// rounding can leave the interval slightly empty when both ends meet
+			let u = u_max.max(u_min);
+			accelerations.push(u);
+			x.push((xi + 2.0 * ds * u).clamp(lo, hi));
+		}
+
+		let mut times = Vec::with_capacity(n);
+		times.push(0.0);
+		for i in 0..n - 1 {
+			let speed_sum = x[i].sqrt() + x[i + 1].sqrt();
+			if speed_sum <= 0.0 {
+				return Err(ToppError::Infeasible);
+			}
+			let dt = 2.0 * (distances[i + 1] - distances[i]) / speed_sum;
+			times.push(times[i] + dt);
+		}
+
+		let velocities = (0..n).map(|i| gears[i.max(1)] * x[i].sqrt()).collect();
+
+		Ok(Self {
+			poses,
+			distances,
+			times,
+			velocities,
+			accelerations,
+			gears: gears[1..].to_vec(),
+		})
+	}
+	#[must_use]
+	pub fn duration(&self) -> f64 {
+		self.times[self.times.len() - 1]
+	}
+	#[must_use]
+	pub fn times(&self) -> &[f64] {
+		&self.times
+	}
+	#[must_use]
+	pub fn distances(&self) -> &[f64] {
+		&self.distances
+	}
+	// velocity at each grid point, negative when reversing
+	#[must_use]
+	pub fn velocities(&self) -> &[f64] {
+		&self.velocities
+	}
+	// pose and velocity at time t with constant acceleration between grid points
+	pub fn sample(&self, t: f64) -> (Ray, f64) {
+		let t = t.clamp(0.0, self.duration());
+		let i = self
+			.times
+			.partition_point(|&v| v <= t)
+			.saturating_sub(1)
+			.min(self.poses.len() - 2);
+		let dt = t - self.times[i];
+		let (v, a) = (self.velocities[i].abs(), self.accelerations[i]);
+		let travelled = v * dt + 0.5 * a * dt * dt;
+		let length = self.distances[i + 1] - self.distances[i];
+		let f = (travelled / length).clamp(0.0, 1.0);
+
+		let (p, q) = (self.poses[i], self.poses[i + 1]);
+		let pose = Ray::new(
+			p.pos + (q.pos - p.pos) * f,
+			p.angle + map_angle(q.angle - p.angle) * f,
+		);
+		(pose, self.gears[i] * (v + a * dt).max(0.0))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::path_planning::{
+		curved_paths::get_points, Dubins, MotionLimits, ProfileShape, ReedsShepp, TimedPath,
+	};
+	use core::f64::consts::PI;
+
+	#[test]
+	fn straight_line() {
+		// accelerate for 2m, cruise at 2m/s then decelerate for 2m
+		let points: Vec<Pos2> = (0..=100).map(|i| Pos2::new(0.1 * i as f64, 0.0)).collect();
+		let path = TimeOptimalPath::from_polyline(&points, PathConstraints::new(2.0, 1.0)).unwrap();
+		assert!((path.duration() - 7.0).abs() < 1e-9);
+		assert!(path.velocities().iter().all(|&v| v <= 2.0 + 1e-9));
+		assert!(path.velocities()[0] == 0.0 && path.velocities()[100] == 0.0);
+
+		let (pose, velocity) = path.sample(3.5);
+		assert!((pose.pos.x - 5.0).abs() < 1e-9);
+		assert!((velocity - 2.0).abs() < 1e-9);
+
+		assert_eq!(
+			TimeOptimalPath::from_polyline(&points, PathConstraints::new(0.0, 1.0)).unwrap_err(),
+			ToppError::InvalidConstraints
+		);
+		assert_eq!(
+			TimeOptimalPath::from_polyline(&points[..1], PathConstraints::new(2.0, 1.0))
+				.unwrap_err(),
+			ToppError::NotEnoughPoints
+		);
+	}
+
+	#[test]
+	fn wheel_limits() {
+		// the outer wheel of a turn of radius 2 moves 1.125 times faster than the centre
+		let samples = get_points(Ray::ZERO, &[PathSegmentType::Left(PI)], 2.0, 0.05);
+		let mut constraints = PathConstraints::new(2.0, 1.0);
+		constraints.wheels = Some(WheelLimits {
+			track_width: 0.5,
+			max_velocity: 1.0,
+			max_acceleration: 0.5,
+		});
+		let path = TimeOptimalPath::new(&samples, constraints).unwrap();
+		let max = path.velocities().iter().fold(0.0, |a: f64, &b| a.max(b));
+		assert!((max - 1.0 / 1.125).abs() < 1e-3);
+
+		// wheel acceleration between grid points
+		let (v, t) = (path.velocities(), path.times());
+		for i in 1..v.len() - 2 {
+			let a = (v[i + 1] - v[i]) / (t[i + 1] - t[i]);
+			assert!((a * 1.125).abs() <= 0.5 + 1e-3);
+		}
+	}
+
+	#[test]
+	fn faster_than_trapezoidal() {
+		let start = Ray::new(Pos2::new(0.0, 0.0), 0.0);
+		let end = Ray::new(Pos2::new(6.0, 4.0), 2.0);
+		let samples = Dubins::new(start, end, 1.5).unwrap().get_points(0.02);
+
+		let mut constraints = PathConstraints::new(2.0, 1.0);
+		constraints.max_lateral_acceleration = 0.8;
+		let path = TimeOptimalPath::new(&samples, constraints).unwrap();
+
+		let mut limits = MotionLimits::new(2.0, 1.0, f64::INFINITY);
+		limits.max_lateral_acceleration = 0.8;
+		let timed = TimedPath::new(&samples, limits, ProfileShape::Trapezoidal).unwrap();
+		assert!(path.duration() <= timed.duration() + 1e-3);
+
+		let (last, velocity) = path.sample(path.duration());
+		assert!((last.pos - end.pos).magnitude() < 1e-9);
+		assert!(velocity.abs() < 1e-9);
+	}
+
+	#[test]
+	fn stops_at_cusps() {
+		let start = Ray::new(Pos2::new(0.0, 0.0), 0.0);
+		let end = Ray::new(Pos2::new(-1.0, 2.0), 0.5);
+		let samples = ReedsShepp::new(start, end, 1.0).unwrap().get_points(0.05);
+		let path = TimeOptimalPath::new(&samples, PathConstraints::new(2.0, 1.0)).unwrap();
+
+		let v = path.velocities();
+		assert!(v.iter().any(|&v| v < 0.0));
+		for w in v.windows(2) {
+			assert!(w[0] * w[1] >= 0.0);
+		}
+	}
+}
@@ -0,0 +1,576 @@
+use super::curved_paths::{gear, PathSegmentType};
+use crate::prelude::*;
+
+// velocity profiles are built from phases that change speed with zero acceleration
+// at both ends (3 segments for an s-curve, 1 for trapezoidal) and cruise phases
+//
+// a path is split at cusps and into stretches with a constant speed limit,
+// speeds at the boundaries are found with forward and backward passes then
+// each stretch gets the fastest speed change - cruise - speed change that fits
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProfileError {
+	InvalidLimits,
+	NotEnoughPoints,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProfileShape {
+	// acceleration limited
+	Trapezoidal,
+	// acceleration and jerk limited (7 segments)
+	SCurve,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MotionLimits {
+	pub max_velocity: f64,
+	pub max_acceleration: f64,
+	// unused by trapezoidal profiles
+	pub max_jerk: f64,
+	// limits speed on curves to sqrt(max_lateral_acceleration / curvature)
+	pub max_lateral_acceleration: f64,
+}
+
+impl MotionLimits {
+	#[must_use]
+	pub const fn new(max_velocity: f64, max_acceleration: f64, max_jerk: f64) -> Self {
+		Self {
+			max_velocity,
+			max_acceleration,
+			max_jerk,
+			max_lateral_acceleration: f64::INFINITY,
+		}
+	}
+	fn validate(&self, shape: ProfileShape) -> Result<(), ProfileError> {
+		let jerk_valid = shape == ProfileShape::Trapezoidal || self.max_jerk > 0.0;
+		if self.max_velocity > 0.0
+			&& self.max_acceleration > 0.0
+			&& self.max_lateral_acceleration > 0.0
+			&& jerk_valid
+		{
+			Ok(())
+		} else {
+			Err(ProfileError::InvalidLimits)
+		}
+	}
+	// speed limit on a curve
+	fn speed_limit(&self, curvature: f64) -> f64 {
+		if curvature == 0.0 {
+			self.max_velocity
+		} else {
+			self.max_velocity
+				.min((self.max_lateral_acceleration / curvature.abs()).sqrt())
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct ProfileState {
+	pub time: f64,
+	pub distance: f64,
+	pub velocity: f64,
+	pub acceleration: f64,
+	pub jerk: f64,
+}
+
+impl ProfileState {
+	// constant jerk for dt
+	fn advance(self, jerk: f64, dt: f64) -> Self {
+		let (v, a) = (self.velocity, self.acceleration);
+		Self {
+			time: self.time + dt,
+			distance: self.distance + dt * (v + dt * (a / 2.0 + dt * jerk / 6.0)),
+			velocity: v + dt * (a + dt * jerk / 2.0),
+			acceleration: a + dt * jerk,
+			jerk,
+		}
+	}
+}
+
+// acceleration and jerk used for speed changes, jerk is infinite for trapezoidal profiles
+#[derive(Debug, Copy, Clone)]
+struct Limits {
+	acceleration: f64,
+	jerk: f64,
+}
+
+impl Limits {
+	// (time at max jerk, time at max acceleration, peak acceleration) to change speed by dv
+	fn change(&self, dv: f64) -> (f64, f64, f64) {
+		let (a, j) = (self.acceleration, self.jerk);
+		let dv = dv.abs();
+		if dv >= a * a / j {
+			(a / j, dv / a - a / j, a)
+		} else {
+			let peak = (dv * j).sqrt();
+			(peak / j, 0.0, peak)
+		}
+	}
+	fn change_duration(&self, dv: f64) -> f64 {
+		let (tj, ta, _) = self.change(dv);
+		2.0 * tj + ta
+	}
+	// the profile is symmetric so the average speed is the mean of the end speeds
+	fn change_distance(&self, v0: f64, v1: f64) -> f64 {
+		0.5 * (v0 + v1) * self.change_duration(v1 - v0)
+	}
+	// fastest speed up to limit reachable from v0 in distance
+	fn reachable(&self, v0: f64, distance: f64, limit: f64) -> f64 {
+		if self.change_distance(v0, limit) <= distance {
+			return limit;
+		}
+		let (mut lo, mut hi) = (v0, limit);
+		for _ in 0..100 {
+			let mid = 0.5 * (lo + hi);
+			if self.change_distance(v0, mid) <= distance {
+				lo = mid;
+			} else {
+				hi = mid;
+			}
+		}
+		lo
+	}
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Phase {
+	start: ProfileState,
+	// (jerk, acceleration at the start, duration)
+	parts: [(f64, f64, f64); 3],
+}
+
+impl Phase {
+	fn change(start: ProfileState, v1: f64, limits: &Limits) -> Self {
+		let (tj, ta, peak) = limits.change(v1 - start.velocity);
+		let sign = if v1 >= start.velocity { 1.0 } else { -1.0 };
+		let (jerk, peak) = (sign * limits.jerk, sign * peak);
+		Self {
+			start,
+			parts: [(jerk, 0.0, tj), (0.0, peak, ta), (-jerk, peak, tj)],
+		}
+	}
+	fn cruise(start: ProfileState, duration: f64) -> Self {
+		Self {
+			start,
+			parts: [(0.0, 0.0, duration), (0.0, 0.0, 0.0), (0.0, 0.0, 0.0)],
+		}
+	}
+	fn duration(&self) -> f64 {
+		self.parts.iter().map(|p| p.2).sum()
+	}
+	fn sample(&self, t: f64) -> ProfileState {
+		let mut state = self.start;
+		let mut remaining = t - self.start.time;
+		for (jerk, acceleration, duration) in self.parts {
+			if duration <= 0.0 {
+				continue;
+			}
+			state.acceleration = acceleration;
+			if remaining <= duration {
+				return state.advance(jerk, remaining.max(0.0));
+			}
+			state = state.advance(jerk, duration);
+			remaining -= duration;
+		}
+		state.acceleration = 0.0;
+		state.jerk = 0.0;
+		state
+	}
+	fn end(&self) -> ProfileState {
+		let mut end = self.sample(self.start.time + self.duration());
+		end.acceleration = 0.0;
+		end.jerk = 0.0;
+		end
+	}
+}
+
+// time parameterised motion along a distance starting and ending at rest
+#[derive(Debug, Clone)]
+pub struct VelocityProfile {
+	phases: Vec<Phase>,
+	duration: f64,
+	distance: f64,
+}
+
+impl VelocityProfile {
+	pub fn new(
+		distance: f64,
+		limits: MotionLimits,
+		shape: ProfileShape,
+	) -> Result<Self, ProfileError> {
+		Self::with_speed_limits(&[(distance, limits.max_velocity)], limits, shape)
+	}
+	// stretches of (length, speed limit)
+	pub fn with_speed_limits(
+		stretches: &[(f64, f64)],
+		limits: MotionLimits,
+		shape: ProfileShape,
+	) -> Result<Self, ProfileError> {
+		limits.validate(shape)?;
+		if stretches.iter().any(|v| v.0 < 0.0 || v.1 <= 0.0) {
+			return Err(ProfileError::InvalidLimits);
+		}
+		let change = Limits {
+			acceleration: limits.max_acceleration,
+			jerk: match shape {
+				ProfileShape::Trapezoidal => f64::INFINITY,
+				ProfileShape::SCurve => limits.max_jerk,
+			},
+		};
+
+		// speed at the boundaries between stretches
+		let n = stretches.len();
+		let mut boundary = Vec::with_capacity(n + 1);
+		boundary.push(0.0);
+		for w in stretches.windows(2) {
+			boundary.push(w[0].1.min(w[1].1));
+		}
+		boundary.push(0.0);
+
+		// lowering a boundary speed can make the previous speed change longer so repeat until stable
+		for _ in 0..100 {
+			let mut changed = false;
+			for k in 0..n {
+				let v = change.reachable(boundary[k], stretches[k].0, stretches[k].1);
+				if v < boundary[k + 1] {
+					boundary[k + 1] = v;
+					changed = true;
+				}
+			}
+			for k in (0..n).rev() {
+				let v = change.reachable(boundary[k + 1], stretches[k].0, stretches[k].1);
+				if v < boundary[k] {
+					boundary[k] = v;
+					changed = true;
+				}
+			}
+			if !changed {
+				break;
+			}
+		}
+
+		let mut phases = Vec::new();
+		let mut state = ProfileState::default();
+		for (k, &(length, limit)) in stretches.iter().enumerate() {
+			let (v0, v1) = (boundary[k], boundary[k + 1]);
+			let distance =
+				|peak: f64| change.change_distance(v0, peak) + change.change_distance(peak, v1);
+
+			// fastest peak speed that fits in the stretch
+			let low = v0.max(v1);
+			let peak = if distance(limit) <= length {
+				limit
+			} else if distance(low) >= length {
+				low
+			} else {
+				let (mut lo, mut hi) = (low, limit);
+				for _ in 0..100 {
+					let mid = 0.5 * (lo + hi);
+					if distance(mid) <= length {
+						lo = mid;
+					} else {
+						hi = mid;
+					}
+				}
+				lo
+			};
+
+			let start_distance = state.distance;
+			for (target, cruise) in [(peak, true), (v1, false)] {
+				if target != state.velocity {
+					let phase = Phase::change(state, target, &change);
+					state = phase.end();
+					phases.push(phase);
+				}
+				let remaining =
+					start_distance + length - state.distance - change.change_distance(peak, v1);
+				if cruise && peak > 0.0 && remaining > 0.0 {
+					let phase = Phase::cruise(state, remaining / peak);
+					state = phase.end();
+					phases.push(phase);
+				}
+			}
+		}
+
+		Ok(Self {
+			duration: state.time,
+			distance: state.distance,
+			phases,
+		})
+	}
+	#[must_use]
+	pub fn duration(&self) -> f64 {
+		self.duration
+	}
+	#[must_use]
+	pub fn distance(&self) -> f64 {
+		self.distance
+	}
+	// state at time t, clamped to the profile
+	#[must_use]
+	pub fn sample(&self, t: f64) -> ProfileState {
+		let t = t.clamp(0.0, self.duration);
+		let i = self
+			.phases
+			.partition_point(|p| p.start.time <= t)
+			.saturating_sub(1);
+		self.phases.get(i).map_or(
+			ProfileState {
+				time: t,
+				..Default::default()
+			},
+			|phase| phase.sample(t),
+		)
+	}
+}
+
+// velocity profile along sampled path from curved_paths::get_points
+// the path is driven in pieces between cusps, stopping at each
+#[derive(Debug, Clone)]
+pub struct TimedPath {
+	samples: Vec<Ray>,
+	// distance along the path at each sample
+	distances: Vec<f64>,
+	// (first sample, gear, start time, profile)
+	pieces: Vec<(usize, f64, f64, VelocityProfile)>,
+	duration: f64,
+}
+
+impl TimedPath {
+	pub fn new(
+		samples: &[(Ray, PathSegmentType)],
+		limits: MotionLimits,
+		shape: ProfileShape,
+	) -> Result<Self, ProfileError> {
+		if samples.len() < 2 {
+			return Err(ProfileError::NotEnoughPoints);
+		}
+
+		// arc length, curvature and gear of each interval
+		let intervals: Vec<(f64, f64, f64)> = samples
+			.windows(2)
+			.map(|w| {
+				let chord = (w[1].0.pos - w[0].0.pos).magnitude();
+				let turn = map_angle(w[1].0.angle - w[0].0.angle);
+				let length = if turn.abs() < 1e-9 {
+					chord
+				} else {
+					chord * 0.5 * turn.abs() / (0.5 * turn).sin().abs()
+				};
+				let curvature = if length > 0.0 { turn / length } else { 0.0 };
+				(length, curvature, gear(w[1].1))
+			})
+			.collect();
+
+		let mut distances = Vec::with_capacity(samples.len());
+		distances.push(0.0);
+		for v in &intervals {
+			distances.push(distances[distances.len() - 1] + v.0);
+		}
+
+		let mut pieces = Vec::new();
+		let mut time = 0.0;
+		let mut start = 0;
+		while start < intervals.len() {
+			let gear = intervals[start].2;
+			let end = intervals[start..]
+				.iter()
+				.position(|v| v.2 != gear)
+				.map_or(intervals.len(), |i| start + i);
+
+			// merge neighbouring intervals with the same speed limit
+			let mut stretches: Vec<(f64, f64)> = Vec::new();
+			for &(length, curvature, _) in &intervals[start..end] {
+				let limit = limits.speed_limit(curvature);
+				match stretches.last_mut() {
+					Some(last) if (last.1 - limit).abs() <= 1e-9 * limit => last.0 += length,
+					_ => stretches.push((length, limit)),
+				}
+			}
+
+			let profile = VelocityProfile::with_speed_limits(&stretches, limits, shape)?;
+			let duration = profile.duration();
+			pieces.push((start, gear, time, profile));
+			time += duration;
+			start = end;
+		}
+
+		Ok(Self {
+			samples: samples.iter().map(|v| v.0).collect(),
+			distances,
+			pieces,
+			duration: time,
+		})
+	}
+	#[must_use]
+	pub fn duration(&self) -> f64 {
+		self.duration
+	}
+	// pose and state at time t, velocity is negative when reversing
+	// distance is the total distance travelled along the path
+	pub fn sample(&self, t: f64) -> (Ray, ProfileState) {
+		let t = t.clamp(0.0, self.duration);
+		let i = self.pieces.partition_point(|p| p.2 <= t).saturating_sub(1);
+		let (first, gear, start_time, profile) = &self.pieces[i];
+
+		let mut state = profile.sample(t - start_time);
+		state.time = t;
+		state.distance += self.distances[*first];
+		state.velocity *= gear;
+		state.acceleration *= gear;
+		state.jerk *= gear;
+
+		(self.pose(state.distance), state)
+	}
+	// samples at a fixed time step including the end
+	#[must_use]
+	pub fn get_points(&self, time_step: f64) -> Vec<(Ray, ProfileState)> {
+		let mut points = Vec::new();
+		if time_step <= 0.0 {
+			return points;
+		}
+		let mut t = 0.0;
+		while t < self.duration {
+			points.push(self.sample(t));
+			t += time_step;
+		}
+		points.push(self.sample(self.duration));
+		points
+	}
+	// interpolate between samples
+	fn pose(&self, distance: f64) -> Ray {
+		let i = self
+			.distances
+			.partition_point(|&d| d <= distance)
+			.saturating_sub(1)
+			.min(self.samples.len() - 2);
+		let (a, b) = (self.samples[i], self.samples[i + 1]);
+		let length = self.distances[i + 1] - self.distances[i];
+		let f = if length > 0.0 {
+			((distance - self.distances[i]) / length).clamp(0.0, 1.0)
+		} else {
+			0.0
+		};
+		Ray::new(
+			a.pos + (b.pos - a.pos) * f,
+			a.angle + map_angle(b.angle - a.angle) * f,
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::path_planning::{curved_paths::get_points, ReedsShepp};
+	use core::f64::consts::PI;
+
+	// limits checked with finite differences of the sampled profile
+	fn check_profile(profile: &VelocityProfile, limits: MotionLimits, jerk_limited: bool) {
+		let dt = 1e-3;
+		let mut t = 0.0;
+		let mut last = profile.sample(0.0);
+		while t < profile.duration() {
+			t += dt;
+			let state = profile.sample(t);
+			assert!(state.velocity >= -1e-9 && state.velocity <= limits.max_velocity + 1e-9);
+			assert!(state.acceleration.abs() <= limits.max_acceleration + 1e-9);
+			if jerk_limited {
+				let jerk =
+					(state.acceleration - last.acceleration) / (state.time - last.time).max(1e-12);
+				assert!(jerk.abs() <= limits.max_jerk * 1.001);
+			}
+			// distance is the integral of velocity
+			let ds = state.distance - last.distance;
+			assert!(
+				(ds - 0.5 * (state.velocity + last.velocity) * (state.time - last.time)).abs()
+					< 1e-6
+			);
+			last = state;
+		}
+		let end = profile.sample(profile.duration());
+		assert!(end.velocity.abs() < 1e-9);
+	}
+
+	#[test]
+	fn rest_to_rest() {
+		let limits = MotionLimits::new(2.0, 1.0, 2.0);
+
+		let trapezoidal = VelocityProfile::new(10.0, limits, ProfileShape::Trapezoidal).unwrap();
+		assert!((trapezoidal.distance() - 10.0).abs() < 1e-9);
+		assert!((trapezoidal.duration() - (10.0 / 2.0 + 2.0 / 1.0)).abs() < 1e-9);
+		check_profile(&trapezoidal, limits, false);
+
+		let s_curve = VelocityProfile::new(10.0, limits, ProfileShape::SCurve).unwrap();
+		assert!((s_curve.distance() - 10.0).abs() < 1e-9);
+		assert!((s_curve.duration() - (10.0 / 2.0 + 2.0 / 1.0 + 1.0 / 2.0)).abs() < 1e-9);
+		check_profile(&s_curve, limits, true);
+
+		// too short to reach the maximum velocity
+		let short = VelocityProfile::new(0.3, limits, ProfileShape::SCurve).unwrap();
+		assert!((short.distance() - 0.3).abs() < 1e-9);
+		check_profile(&short, limits, true);
+
+		assert!(
+			VelocityProfile::new(1.0, MotionLimits::new(1.0, 1.0, 0.0), ProfileShape::SCurve)
+				.is_err()
+		);
+	}
+
+	#[test]
+	fn speed_limits() {
+		let limits = MotionLimits::new(3.0, 1.5, 3.0);
+		let stretches = [(6.0, 3.0), (2.0, 1.0), (8.0, 3.0)];
+		let profile =
+			VelocityProfile::with_speed_limits(&stretches, limits, ProfileShape::SCurve).unwrap();
+		assert!((profile.distance() - 16.0).abs() < 1e-9);
+		check_profile(&profile, limits, true);
+
+		// slow through the middle stretch
+		let mut t = 0.0;
+		while t < profile.duration() {
+			let state = profile.sample(t);
+			if state.distance > 6.0 + 1e-9 && state.distance < 8.0 - 1e-9 {
+				assert!(state.velocity <= 1.0 + 1e-9);
+			}
+			t += 1e-3;
+		}
+	}
+
+	#[test]
+	fn curvature_limit() {
+		// half circle of radius 2 limits the speed to sqrt(0.5 * 2)
+		let samples = get_points(Ray::ZERO, &[PathSegmentType::Left(PI)], 2.0, 0.05);
+		let mut limits = MotionLimits::new(2.0, 1.0, 2.0);
+		limits.max_lateral_acceleration = 0.5;
+		let path = TimedPath::new(&samples, limits, ProfileShape::Trapezoidal).unwrap();
+
+		let points = path.get_points(0.01);
+		assert!(points.iter().all(|v| v.1.velocity <= 1.0 + 1e-9));
+		assert!(points.iter().any(|v| v.1.velocity > 1.0 - 1e-9));
+		let last = points[points.len() - 1];
+		assert!((last.0.pos - Pos2::new(0.0, 4.0)).magnitude() < 1e-9);
+		assert!((last.1.distance - 2.0 * PI).abs() < 1e-6);
+	}
+
+	#[test]
+	fn stops_at_cusps() {
+		let start = Ray::new(Pos2::new(0.0, 0.0), 0.0);
+		let end = Ray::new(Pos2::new(-1.0, 2.0), 0.5);
+		let samples = ReedsShepp::new(start, end, 1.0).unwrap().get_points(0.05);
+		let limits = MotionLimits::new(2.0, 1.0, 2.0);
+
+		let path = TimedPath::new(&samples, limits, ProfileShape::SCurve).unwrap();
+		let points = path.get_points(0.01);
+		let last = points[points.len() - 1].0;
+		assert!((last.pos - end.pos).magnitude() < 1e-9);
+		assert!(points.iter().any(|v| v.1.velocity < 0.0));
+
+		// velocity only changes sign by passing through zero
+		for w in points.windows(2) {
+			if w[0].1.velocity * w[1].1.velocity < 0.0 {
+				assert!(w[0].1.velocity.abs() < 0.01 && w[1].1.velocity.abs() < 0.01);
+			}
+		}
+	}
+}
@@ -0,0 +1,447 @@
+use super::{
+	curved_paths::PathSegmentType,
+	grid::OpenNode,
+	polygon::{area, contains, cross},
+};
+use crate::prelude::*;
+use core::f64::consts::{FRAC_PI_4, FRAC_PI_8, PI};
+
+// references:
+// https://doi.org/10.1145/359156.359164 (Lozano-Pérez & Wesley)
+// https://doi.org/10.1007/978-3-540-77974-2 (de Berg et al., chapter 15)
+// --------
+// shortest paths around polygons only bend at convex vertices and only run
+// along lines tangent to the polygons at both ends, so the reduced graph keeps
+// just those vertices and edges
+// --------
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VisibilityError {
+	InvalidInput,
+	PathNotFound,
+	// the segments either side of a corner are too short for the turning radius
+	CornerTooTight,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibilityGraph {
+	// inflated and counter clockwise
+	polygons: Vec<Vec<Pos2>>,
+	// convex vertices outside every other polygon with their polygon and index
+	vertices: Vec<(Pos2, usize, usize)>,
+	edges: Vec<Vec<(usize, f64)>>,
+}
+
+impl VisibilityGraph {
+	// polygons are grown by the robot radius with arcs approximated from outside
+	pub fn new(polygons: &[Vec<Pos2>], robot_radius: f64) -> Result<Self, VisibilityError> {
+		if robot_radius < 0.0 || polygons.iter().any(|p| p.len() < 3) {
+			return Err(VisibilityError::InvalidInput);
+		}
+		let polygons: Vec<Vec<Pos2>> = polygons.iter().map(|p| inflate(p, robot_radius)).collect();
+
+		let mut vertices = Vec::new();
+		for (i, polygon) in polygons.iter().enumerate() {
+			let n = polygon.len();
+			for (j, &v) in polygon.iter().enumerate() {
+				let (prev, next) = (polygon[(j + n - 1) % n], polygon[(j + 1) % n]);
+				let convex = cross(v - prev, next - v) > 0.0;
+				let hidden = polygons
+					.iter()
+					.enumerate()
+					.any(|(k, other)| k != i && contains(other, v));
+				if convex && !hidden {
+					vertices.push((v, i, j));
+				}
+			}
+		}
+
+		let mut graph = Self {
+			polygons,
+			edges: (0..vertices.len()).map(|_| Vec::new()).collect(),
+			vertices,
+		};
+		for a in 0..graph.vertices.len() {
+			for b in a + 1..graph.vertices.len() {
+				let (pa, pb) = (graph.vertices[a].0, graph.vertices[b].0);
+				if graph.is_tangent(a, pb) && graph.is_tangent(b, pa) && graph.is_visible(pa, pb) {
+					let length = (pb - pa).magnitude();
+					graph.edges[a].push((b, length));
+					graph.edges[b].push((a, length));
+				}
+			}
+		}
+		Ok(graph)
+	}
+	// for paths smoothed by smooth_corners with min_radius, which stay outside
+	// the polygons grown by robot_radius
+	// a path turns at most 45 degrees at each vertex of a rounded corner, where
+	// the arc comes within min_radius (1 - cos(pi / 8)) of the sides, so the
+	// polygons are grown by a little more than that
+	pub fn with_turning_radius(
+		polygons: &[Vec<Pos2>],
+		robot_radius: f64,
+		min_radius: f64,
+	) -> Result<Self, VisibilityError> {
+		if min_radius <= 0.0 {
+			return Err(VisibilityError::InvalidInput);
+		}
+		Self::new(
+			polygons,
+			robot_radius + min_radius * (1.0 / FRAC_PI_8.cos() - 1.0),
+		)
+	}
+	#[must_use]
+	pub fn polygons(&self) -> &[Vec<Pos2>] {
+		&self.polygons
+	}
+	// vertices of the reduced graph
+	pub fn vertices(&self) -> impl Iterator<Item = Pos2> + '_ {
+		self.vertices.iter().map(|v| v.0)
+	}
+	// the segment doesn't pass through the inside of any polygon, touching is allowed
+	#[must_use]
+	pub fn is_visible(&self, a: Pos2, b: Pos2) -> bool {
+		let direction = b - a;
+		let length = direction.magnitude();
+		if length == 0.0 {
+			return !self.polygons.iter().any(|p| contains(p, a));
+		}
+		let direction = direction / length;
+		for polygon in &self.polygons {
+			let n = polygon.len();
+			for i in 0..n {
+				if crosses(a, b, polygon[i], polygon[(i + 1) % n]) {
+					return false;
+				}
+			}
+			if contains(polygon, a + 0.5 * length * direction) {
+				return false;
+			}
+			// passing through a vertex can enter the polygon without crossing an edge
+			for &v in polygon {
+				let t = (v - a).dot(&direction);
+				if t > 1e-9 && t < length - 1e-9 && cross(direction, v - a).abs() < 1e-9 {
+					let offset = 1e-6 * length.min(1.0) * direction;
+					if contains(polygon, v - offset) || contains(polygon, v + offset) {
+						return false;
+					}
+				}
+			}
+		}
+		true
+	}
+	// shortest path from start to goal through the reduced graph
+	pub fn plan(&self, start: Pos2, goal: Pos2) -> Result<Vec<Pos2>, VisibilityError> {
+		if self
+			.polygons
+			.iter()
+			.any(|p| contains(p, start) || contains(p, goal))
+		{
+			return Err(VisibilityError::PathNotFound);
+		}
+		// start and goal are the last two nodes
+		let n = self.vertices.len();
+		let (s, g) = (n, n + 1);
+		let position = |i: usize| match i {
+			_ if i == s => start,
+			_ if i == g => goal,
+			_ => self.vertices[i].0,
+		};
+		let connect = |p: Pos2| -> Vec<(usize, f64)> {
+			(0..n)
+				.filter(|&i| self.is_tangent(i, p) && self.is_visible(p, self.vertices[i].0))
+				.map(|i| (i, (self.vertices[i].0 - p).magnitude()))
+				.collect()
+		};
+		let from_start = connect(start);
+		let to_goal = connect(goal);
+
+		let mut cost = Vec::from([f64::INFINITY]).repeat(n + 2);
+		let mut parent: Vec<Option<usize>> = Vec::from([None]).repeat(n + 2);
+		let mut closed = Vec::from([false]).repeat(n + 2);
+		cost[s] = 0.0;
+		let mut open = BinaryHeap::from([OpenNode {
+			cost: (goal - start).magnitude(),
+			index: s,
+		}]);
+		while let Some(OpenNode { index, .. }) = open.pop() {
+			if closed[index] {
+				continue;
+			}
+			closed[index] = true;
+			if index == g {
+				let mut path = Vec::from([goal]);
+				let mut i = g;
+				while let Some(p) = parent[i] {
+					path.push(position(p));
+					i = p;
+				}
+				path.reverse();
+				return Ok(path);
+			}
+
+			let mut neighbours = if index == s {
+				from_start.clone()
+			} else {
+				self.edges[index].clone()
+			};
+			if index == s && self.is_visible(start, goal) {
+				neighbours.push((g, (goal - start).magnitude()));
+			}
+			if index < n {
+				if let Some(&(_, length)) = to_goal.iter().find(|v| v.0 == index) {
+					neighbours.push((g, length));
+				}
+			}
+			for (next, length) in neighbours {
+				let c = cost[index] + length;
+				if !closed[next] && c < cost[next] {
+					cost[next] = c;
+					parent[next] = Some(index);
+					open.push(OpenNode {
+						cost: c + (goal - position(next)).magnitude(),
+						index: next,
+					});
+				}
+			}
+		}
+		Err(VisibilityError::PathNotFound)
+	}
+	// the line from the vertex towards the point doesn't cut into its polygon
+	fn is_tangent(&self, vertex: usize, towards: Pos2) -> bool {
+		let (v, i, j) = self.vertices[vertex];
+		let polygon = &self.polygons[i];
+		let n = polygon.len();
+		let direction = towards - v;
+		let a = cross(direction, polygon[(j + n - 1) % n] - v);
+		let b = cross(direction, polygon[(j + 1) % n] - v);
+		a * b >= 0.0
+	}
+}
+
+// replace each corner of a path with an arc of min_radius tangent to both sides
+// for sampling with curved_paths::get_points using the same min_radius
+// arcs cut inside a corner turning by angle a by min_radius (sec(a / 2) - 1)
+// so plan on VisibilityGraph::with_turning_radius to keep clear of obstacles
+pub fn smooth_corners(
+	path: &[Pos2],
+	min_radius: f64,
+) -> Result<(Ray, Vec<PathSegmentType>), VisibilityError> {
+	if path.len() < 2 || min_radius <= 0.0 {
+		return Err(VisibilityError::InvalidInput);
+	}
+	let directions: Vec<Vec2> = path.windows(2).map(|w| w[1] - w[0]).collect();
+	if directions.iter().any(|d| d.magnitude() == 0.0) {
+		return Err(VisibilityError::InvalidInput);
+	}
+	// turn at each corner and the length of each side it uses
+	let turns: Vec<f64> = directions
+		.windows(2)
+		.map(|w| cross(w[0], w[1]).atan2(w[0].dot(&w[1])))
+		.collect();
+	let cut: Vec<f64> = turns
+		.iter()
+		.map(|t| min_radius * (0.5 * t.abs()).tan())
+		.collect();
+
+	let start = Ray::new(path[0], directions[0].y.atan2(directions[0].x));
+	let mut segments = Vec::new();
+	for (i, d) in directions.iter().enumerate() {
+		let before = if i > 0 { cut[i - 1] } else { 0.0 };
+		let after = cut.get(i).copied().unwrap_or(0.0);
+		let straight = d.magnitude() - before - after;
+		if straight < -1e-9 || turns.get(i).is_some_and(|t| t.abs() >= PI - 1e-9) {
+			return Err(VisibilityError::CornerTooTight);
+		}
+		if straight > 1e-9 {
+			segments.push(PathSegmentType::Straight(straight / min_radius));
+		}
+		match turns.get(i) {
+			Some(&t) if t > 1e-12 => segments.push(PathSegmentType::Left(t)),
+			Some(&t) if t < -1e-12 => segments.push(PathSegmentType::Right(-t)),
+			_ => {}
+		}
+	}
+	Ok((start, segments))
+}
+
+// the segments cross at a point inside both of them
+fn crosses(a: Pos2, b: Pos2, c: Pos2, d: Pos2) -> bool {
+	let eps = 1e-12 * (1.0 + (b - a).magnitude_squared() + (d - c).magnitude_squared());
+	let sign = |v: f64| {
+		if v > eps {
+			1
+		} else if v < -eps {
+			-1
+		} else {
+			0
+		}
+	};
+	let (o1, o2) = (sign(cross(b - a, c - a)), sign(cross(b - a, d - a)));
+	let (o3, o4) = (sign(cross(d - c, a - c)), sign(cross(d - c, b - c)));
+	o1 * o2 < 0 && o3 * o4 < 0
+}
+
+// counter clockwise copy grown outwards by radius
+// convex corners are rounded with a polygon around the arc
+fn inflate(polygon: &[Pos2], radius: f64) -> Vec<Pos2> {
+	let mut polygon = polygon.to_vec();
+	let n = polygon.len();
+	if area(&polygon) < 0.0 {
+		polygon.reverse();
+	}
+	if radius == 0.0 {
+		return polygon;
+	}
+	// outward normal of the edge starting at each vertex
+	let normal = |i: usize| {
+		let d = (polygon[(i + 1) % n] - polygon[i]).normalize();
+		Vec2::new(d.y, -d.x)
+	};
+	let angle = |v: Vec2| v.y.atan2(v.x);
+
+	let mut inflated = Vec::with_capacity(2 * n);
+	for (i, &v) in polygon.iter().enumerate() {
+		let (n0, n1) = (normal((i + n - 1) % n), normal(i));
+		let turn = cross(n0, n1).atan2(n0.dot(&n1));
+		if turn <= 0.0 {
+			// reflex corner, where the offset edges meet
+			let bisector = (n0 + n1).normalize();
+			inflated.push(v + bisector * radius / (0.5 * turn).cos());
+			continue;
+		}
+		// circumscribe the arc with pieces turning at most 45 degrees
+		let pieces = (turn / FRAC_PI_4).ceil();
+		let step = turn / pieces;
+		for k in 0..pieces as usize {
+			let a = angle(n0) + (k as f64 + 0.5) * step;
+			inflated.push(v + Vec2::new(a.cos(), a.sin()) * radius / (0.5 * step).cos());
+		}
+	}
+	inflated
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::path_planning::curved_paths::get_points;
+
+	fn square(x: f64, y: f64, size: f64) -> Vec<Pos2> {
+		Vec::from([
+			Pos2::new(x, y),
+			Pos2::new(x, y + size),
+			Pos2::new(x + size, y + size),
+			Pos2::new(x + size, y),
+		])
+	}
+
+	fn length(path: &[Pos2]) -> f64 {
+		path.windows(2).map(|w| (w[1] - w[0]).magnitude()).sum()
+	}
+
+	#[test]
+	fn around_polygons() {
+		let polygons = [square(2.0, -1.0, 2.0), square(6.0, 0.5, 1.0)];
+		let graph = VisibilityGraph::new(&polygons, 0.0).unwrap();
+		let (start, goal) = (Pos2::new(0.0, 0.0), Pos2::new(9.0, 0.0));
+		let path = graph.plan(start, goal).unwrap();
+
+		// under the first square then straight to the goal below the second
+		let expected = 5f64.sqrt() + 2.0 + 26f64.sqrt();
+		assert!((length(&path) - expected).abs() < 1e-9);
+		for w in path.windows(2) {
+			assert!(graph.is_visible(w[0], w[1]));
+		}
+
+		// the inflated polygons contain the originals with the robot radius around them
+		let inflated = VisibilityGraph::new(&polygons, 0.5).unwrap();
+		for polygon in inflated.polygons() {
+			for w in polygon.windows(2) {
+				let distance = |p: Pos2| {
+					let d = w[1] - w[0];
+					let t = ((p - w[0]).dot(&d) / d.magnitude_squared()).clamp(0.0, 1.0);
+					(w[0] + d * t - p).magnitude()
+				};
+				assert!(polygons
+					.iter()
+					.flatten()
+					.all(|&v| distance(v) >= 0.5 - 1e-9));
+			}
+		}
+		let inflated_path = inflated.plan(start, goal).unwrap();
+		assert!(length(&inflated_path) > length(&path));
+
+		// start inside an obstacle
+		assert_eq!(
+			graph.plan(Pos2::new(3.0, 0.0), goal).unwrap_err(),
+			VisibilityError::PathNotFound
+		);
+	}
+
+	#[test]
+	fn tangent_arcs() {
+		let graph = VisibilityGraph::new(&[square(2.0, -1.0, 2.0)], 0.5).unwrap();
+		let goal = Pos2::new(8.0, 0.5);
+		let path = graph.plan(Pos2::new(0.0, 0.0), goal).unwrap();
+
+		let min_radius = 0.5;
+		let (start, segments) = smooth_corners(&path, min_radius).unwrap();
+		let points = get_points(start, &segments, min_radius, 0.1);
+		assert!((points[points.len() - 1].0.pos - goal).magnitude() < 1e-9);
+		// the heading is continuous, get_points can leave up to two steps before a segment end
+		for w in points.windows(2) {
+			let turn = (w[1].0.angle - w[0].0.angle).abs();
+			assert!(turn <= 0.2 + 1e-9);
+		}
+
+		assert_eq!(
+			smooth_corners(&path, 50.0).unwrap_err(),
+			VisibilityError::CornerTooTight
+		);
+	}
+
+	#[test]
+	fn arcs_clear_obstacles() {
+		let polygons = [square(2.0, -1.0, 2.0), square(5.0, 0.5, 1.5)];
+		let (robot_radius, min_radius) = (0.5, 1.0);
+		let (start, goal) = (Pos2::new(0.0, 0.0), Pos2::new(9.0, 1.0));
+		// distance from the nearest obstacle, negative inside one
+		let clearance = |p: Pos2| {
+			polygons
+				.iter()
+				.map(|polygon| {
+					let d = (0..polygon.len())
+						.map(|i| {
+							let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+							let t = ((p - a).dot(&(b - a)) / (b - a).magnitude_squared())
+								.clamp(0.0, 1.0);
+							(a + (b - a) * t - p).magnitude()
+						})
+						.fold(f64::INFINITY, f64::min);
+					if contains(polygon, p) {
+						-d
+					} else {
+						d
+					}
+				})
+				.fold(f64::INFINITY, f64::min)
+		};
+		let sample = |graph: &VisibilityGraph| {
+			let path = graph.plan(start, goal).unwrap();
+			let (ray, segments) = smooth_corners(&path, min_radius).unwrap();
+			get_points(ray, &segments, min_radius, 0.01)
+				.iter()
+				.map(|v| clearance(v.0.pos))
+				.fold(f64::INFINITY, f64::min)
+		};
+
+		// arcs around the corners of the robot radius polygons cut into them
+		let graph = VisibilityGraph::new(&polygons, robot_radius).unwrap();
+		assert!(sample(&graph) < robot_radius - 1e-3);
+
+		let graph =
+			VisibilityGraph::with_turning_radius(&polygons, robot_radius, min_radius).unwrap();
+		assert!(sample(&graph) >= robot_radius - 1e-9);
+	}
+}
@@ -0,0 +1,361 @@
+use super::grid::{Cell, Grid, GridError, OpenNode};
+use crate::prelude::*;
+
+// references:
+// https://doi.org/10.1016/j.robot.2012.09.004 (Lau, Sprunk & Burgard)
+// https://doi.org/10.1007/978-1-4615-4022-9 (Latombe, chapter 7 for brushfire)
+// --------
+// a brushfire from every blocked cell, and from the edges of the grid, gives
+// each free cell its nearest obstacle cell (the source)
+// a cell is on the generalised voronoi diagram when a neighbour's source is on
+// the other side of it, i.e. more than 90 degrees away from its own source,
+// which picks out the ridges of the distance transform and ignores neighbours
+// that see different cells of the same wall
+// the diagram of a grid can break into pieces, so each piece is joined to the
+// nearest other piece by the shortest route through free cells
+// paths join the diagram by the shortest route and then stay on it
+// --------
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoronoiRoadmap {
+	grid: Grid,
+	// distance to the nearest obstacle in world units
+	clearance: Vec<f64>,
+	roadmap: Vec<bool>,
+}
+
+impl VoronoiRoadmap {
+	#[must_use]
+	pub fn new(grid: Grid) -> Self {
+		let (w, h) = (grid.width() as i64, grid.height() as i64);
+		let n = grid.width() * grid.height();
+		let mut distance = Vec::from([f64::INFINITY]).repeat(n);
+		let mut source: Vec<(i64, i64)> = Vec::from([(0, 0)]).repeat(n);
+		let mut open = BinaryHeap::new();
+
+		for i in 0..n {
+			let (x, y) = grid.cell_at(i);
+			let (x, y) = (x as i64, y as i64);
+			if !grid.is_free((x as usize, y as usize)) {
+				distance[i] = 0.0;
+				source[i] = (x, y);
+				open.push(OpenNode {
+					cost: 0.0,
+					index: i,
+				});
+			} else if x == 0 || y == 0 || x == w - 1 || y == h - 1 {
+				// just outside the grid counts as blocked
+				let outside = if x == 0 {
+					(-1, y)
+				} else if x == w - 1 {
+					(w, y)
+				} else if y == 0 {
+					(x, -1)
+				} else {
+					(x, h)
+				};
+				distance[i] = 1.0;
+				source[i] = outside;
+				open.push(OpenNode {
+					cost: 1.0,
+					index: i,
+				});
+			}
+		}
+
+		// brushfire, carrying the source so distances are euclidean
+		while let Some(OpenNode { cost, index }) = open.pop() {
+			if cost > distance[index] {
+				continue;
+			}
+			let cell = grid.cell_at(index);
+			let s = source[index];
+			for next in grid.adjacent(cell) {
+				let j = grid.index(next);
+				let d = euclidean(s, (next.0 as i64, next.1 as i64));
+				if d < distance[j] {
+					distance[j] = d;
+					source[j] = s;
+					open.push(OpenNode { cost: d, index: j });
+				}
+			}
+		}
+
+		let mut roadmap = Vec::from([false]).repeat(n);
+		for (i, on_roadmap) in roadmap.iter_mut().enumerate() {
+			let cell = grid.cell_at(i);
+			if !grid.is_free(cell) {
+				continue;
+			}
+			let c = (cell.0 as i64, cell.1 as i64);
+			let own = (source[i].0 - c.0, source[i].1 - c.1);
+			*on_roadmap = [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().any(|&(dx, dy)| {
+				let (nx, ny) = (c.0 + dx, c.1 + dy);
+				// blocked and outside neighbours are their own source
+				let (other, other_distance) = if nx < 0 || ny < 0 || nx >= w || ny >= h {
+					((nx, ny), 0.0)
+				} else {
+					let j = grid.index((nx as usize, ny as usize));
+					(source[j], distance[j])
+				};
+				let theirs = (other.0 - c.0, other.1 - c.1);
+				own.0 * theirs.0 + own.1 * theirs.1 < 0 && distance[i] >= other_distance
+			});
+		}
+
+		let mut voronoi = Self {
+			clearance: distance.iter().map(|d| d * grid.resolution).collect(),
+			grid,
+			roadmap,
+		};
+		voronoi.join_pieces();
+		voronoi
+	}
+	// points are marked as blocked cells of an otherwise free grid
+	pub fn from_points(
+		points: &[Pos2],
+		width: usize,
+		height: usize,
+		resolution: f64,
+		origin: Pos2,
+	) -> Result<Self, GridError> {
+		let mut grid = Grid::new(width, height, resolution, origin)?;
+		for &p in points {
+			if let Some(cell) = grid.cell(p) {
+				grid.set_cost(cell, f64::INFINITY)?;
+			}
+		}
+		Ok(Self::new(grid))
+	}
+	#[must_use]
+	pub fn grid(&self) -> &Grid {
+		&self.grid
+	}
+	// distance from the cell to the nearest obstacle or the edge of the grid
+	#[must_use]
+	pub fn clearance(&self, cell: Cell) -> f64 {
+		if self.grid.contains(cell) {
+			self.clearance[self.grid.index(cell)]
+		} else {
+			0.0
+		}
+	}
+	#[must_use]
+	pub fn is_roadmap(&self, cell: Cell) -> bool {
+		self.grid.contains(cell) && self.roadmap[self.grid.index(cell)]
+	}
+	// start, cell centres onto, along and off the roadmap, then goal
+	pub fn plan(&self, start: Pos2, goal: Pos2) -> Result<Vec<Pos2>, GridError> {
+		let start_cell = self.grid.cell(start).ok_or(GridError::OutOfBounds)?;
+		let goal_cell = self.grid.cell(goal).ok_or(GridError::OutOfBounds)?;
+		if !self.grid.is_free(start_cell) || !self.grid.is_free(goal_cell) {
+			return Err(GridError::PathNotFound);
+		}
+		let to_start = self.search(&[start_cell], |c| self.is_roadmap(c), |_| true)?;
+		let mut from_goal = self.search(&[goal_cell], |c| self.is_roadmap(c), |_| true)?;
+		let entry = to_start[to_start.len() - 1];
+		let exit = from_goal[from_goal.len() - 1];
+		let along = self.search(&[entry], |c| c == exit, |c| self.is_roadmap(c))?;
+
+		from_goal.reverse();
+		let cells = to_start
+			.iter()
+			.chain(&along[1..])
+			.chain(&from_goal[1..])
+			.copied();
+
+		let mut path = Vec::from([start]);
+		path.extend(cells.map(|c| self.grid.centre(c)));
+		path.push(goal);
+		Ok(path)
+	}
+	// link every piece of the roadmap to the nearest other piece it can reach,
+	// growing from one piece until nothing else is reachable from it
+	fn join_pieces(&mut self) {
+		let n = self.grid.width() * self.grid.height();
+		let mut joined = Vec::from([false]).repeat(n);
+		for i in 0..n {
+			if !self.roadmap[i] || joined[i] {
+				continue;
+			}
+			loop {
+				// the piece i is in so far, through roadmap neighbours
+				let mut piece = Vec::from([self.grid.cell_at(i)]);
+				let mut in_piece = Vec::from([false]).repeat(n);
+				in_piece[i] = true;
+				let mut k = 0;
+				while k < piece.len() {
+					for (next, _) in self.grid.neighbours(piece[k]) {
+						let j = self.grid.index(next);
+						if self.roadmap[j] && !in_piece[j] {
+							in_piece[j] = true;
+							piece.push(next);
+						}
+					}
+					k += 1;
+				}
+				for &cell in &piece {
+					joined[self.grid.index(cell)] = true;
+				}
+				let Ok(link) = self.search(
+					&piece,
+					|c| self.is_roadmap(c) && !in_piece[self.grid.index(c)],
+					|_| true,
+				) else {
+					break;
+				};
+				for cell in link {
+					let j = self.grid.index(cell);
+					self.roadmap[j] = true;
+				}
+			}
+		}
+	}
+	// shortest route from any of the cells to the nearest cell passing the goal
+	// test, only moving through cells that pass the filter
+	fn search(
+		&self,
+		from: &[Cell],
+		is_goal: impl Fn(Cell) -> bool,
+		allowed: impl Fn(Cell) -> bool,
+	) -> Result<Vec<Cell>, GridError> {
+		let n = self.grid.width() * self.grid.height();
+		let mut cost = Vec::from([f64::INFINITY]).repeat(n);
+		let mut parent: Vec<Option<usize>> = Vec::from([None]).repeat(n);
+		let mut open = BinaryHeap::new();
+		for &cell in from {
+			let index = self.grid.index(cell);
+			cost[index] = 0.0;
+			open.push(OpenNode { cost: 0.0, index });
+		}
+		while let Some(OpenNode { cost: c, index }) = open.pop() {
+			if c > cost[index] {
+				continue;
+			}
+			let cell = self.grid.cell_at(index);
+			if is_goal(cell) {
+				let mut cells = Vec::from([cell]);
+				let mut i = index;
+				while let Some(p) = parent[i] {
+					cells.push(self.grid.cell_at(p));
+					i = p;
+				}
+				cells.reverse();
+				return Ok(cells);
+			}
+			for (next, step) in self.grid.neighbours(cell) {
+				let j = self.grid.index(next);
+				if allowed(next) && c + step < cost[j] {
+					cost[j] = c + step;
+					parent[j] = Some(index);
+					open.push(OpenNode {
+						cost: c + step,
+						index: j,
+					});
+				}
+			}
+		}
+		Err(GridError::PathNotFound)
+	}
+}
+
+fn euclidean(a: (i64, i64), b: (i64, i64)) -> f64 {
+	let (dx, dy) = ((a.0 - b.0) as f64, (a.1 - b.1) as f64);
+	(dx * dx + dy * dy).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn centred_in_corridor() {
+		// corridor 7 cells wide with a pillar in a room at the end
+		let (w, h) = (40, 20);
+		let occupied: Vec<bool> = (0..w * h)
+			.map(|i| {
+				let (x, y) = (i % w, i / w);
+				(x < 20 && !(6..=12).contains(&y))
+					|| ((28..31).contains(&x) && (8..11).contains(&y))
+			})
+			.collect();
+		let grid = Grid::from_occupancy(&occupied, w, 0.25, Pos2::new(0.0, 0.0)).unwrap();
+		let roadmap = VoronoiRoadmap::new(grid);
+
+		// the middle row of the corridor is on the roadmap
+		for x in 5..16 {
+			assert!(roadmap.is_roadmap((x, 9)));
+			assert!(!roadmap.is_roadmap((x, 7)));
+		}
+		assert!((roadmap.clearance((10, 9)) - 1.0).abs() < 1e-12);
+
+		let start = Pos2::new(0.3, 1.6);
+		let goal = Pos2::new(9.5, 4.5);
+		let path = roadmap.plan(start, goal).unwrap();
+		assert_eq!(path[0], start);
+		assert_eq!(path[path.len() - 1], goal);
+		for p in &path[1..path.len() - 1] {
+			let cell = roadmap.grid().cell(*p).unwrap();
+			assert!(roadmap.grid().is_free(cell));
+			// through the corridor it stays in the middle
+			if cell.0 > 5 && cell.0 < 16 {
+				assert_eq!(cell.1, 9);
+			}
+		}
+
+		let blocked = Pos2::new(2.0, 0.5);
+		assert_eq!(
+			roadmap.plan(blocked, goal).unwrap_err(),
+			GridError::PathNotFound
+		);
+	}
+
+	#[test]
+	fn joins_pieces() {
+		// scattered blocked cells break the diagram into pieces, these seeds left
+		// the start and goal on pieces that didn't touch
+		for seed in [24u64, 26, 28, 33] {
+			let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+			let mut next = || {
+				state ^= state >> 12;
+				state ^= state << 25;
+				state ^= state >> 27;
+				state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 33
+			};
+			let n = 25;
+			let mut occupied: Vec<bool> = (0..n * n).map(|_| next() % 9 == 0).collect();
+			occupied[0] = false;
+			occupied[n * n - 1] = false;
+			let grid = Grid::from_occupancy(&occupied, n, 1.0, Pos2::new(0.0, 0.0)).unwrap();
+			let roadmap = VoronoiRoadmap::new(grid);
+
+			let (start, goal) = (Pos2::new(0.5, 0.5), Pos2::new(24.5, 24.5));
+			let path = roadmap.plan(start, goal).unwrap();
+			for w in path[1..path.len() - 1].windows(2) {
+				let (a, b) = (
+					roadmap.grid().cell(w[0]).unwrap(),
+					roadmap.grid().cell(w[1]).unwrap(),
+				);
+				assert!(roadmap.grid().move_cost(a, b).is_finite());
+			}
+		}
+	}
+
+	#[test]
+	fn from_points() {
+		// two rows of points make a corridor
+		let points: Vec<Pos2> = (0..20)
+			.flat_map(|i| {
+				[
+					Pos2::new(i as f64 * 0.5, 1.0),
+					Pos2::new(i as f64 * 0.5, 3.0),
+				]
+			})
+			.collect();
+		let roadmap =
+			VoronoiRoadmap::from_points(&points, 20, 8, 0.5, Pos2::new(0.0, 0.0)).unwrap();
+		for x in 2..18 {
+			assert!(roadmap.is_roadmap((x, 4)));
+		}
+	}
+}
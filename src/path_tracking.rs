@@ -129,3 +129,418 @@ pub mod pure_pursuit {
 		}
 	}
 }
+
+pub mod orca {
+	use crate::prelude::*;
+
+	// references:
+	// https://doi.org/10.1007/978-3-642-19457-3_1 (van den Berg et al., ORCA)
+	// https://gamma.cs.unc.edu/RVO2/
+	// --------
+	// each neighbour gives a half plane of velocities that stay clear of it
+	// within the time horizon, assuming the neighbour takes half of the avoidance
+	// the new velocity is the closest one to the preferred velocity inside all the
+	// half planes and the max speed circle, found by incremental 2D linear
+	// programming, when the half planes don't overlap (crowded or already
+	// colliding) the velocity that least violates the worst of them is used
+	// this only needs the neighbours' positions and velocities so each robot can
+	// run it on its own
+	// --------
+
+	const EPSILON: f64 = 1e-9;
+
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum OrcaError {
+		InvalidInput,
+	}
+
+	#[derive(Debug, Copy, Clone, PartialEq)]
+	pub struct Agent {
+		pub position: Vec2,
+		pub velocity: Vec2,
+		pub radius: f64,
+		pub preferred_velocity: Vec2,
+	}
+
+	#[derive(Debug, Copy, Clone, PartialEq)]
+	pub struct Orca {
+		// how far ahead collisions are avoided, in seconds
+		pub time_horizon: f64,
+		// control period, used to get out of collisions that have already happened
+		pub time_step: f64,
+		pub max_speed: f64,
+	}
+
+	impl Default for Orca {
+		fn default() -> Self {
+			Self {
+				time_horizon: 2.0,
+				time_step: 0.1,
+				max_speed: 1.0,
+			}
+		}
+	}
+
+	// velocities on the valid side are to the left of the direction
+	#[derive(Debug, Copy, Clone, PartialEq)]
+	struct Line {
+		point: Vec2,
+		direction: Vec2,
+	}
+
+	impl Orca {
+		// velocity for the agent that avoids the neighbours, which shouldn't include
+		// the agent itself
+		pub fn new_velocity(&self, agent: &Agent, neighbours: &[Agent]) -> Result<Vec2, OrcaError> {
+			if self.time_horizon <= 0.0
+				|| self.time_step <= 0.0
+				|| self.max_speed < 0.0
+				|| agent.radius < 0.0
+				|| neighbours.iter().any(|n| n.radius < 0.0)
+			{
+				return Err(OrcaError::InvalidInput);
+			}
+			let lines: Vec<Line> = neighbours
+				.iter()
+				.map(|n| self.half_plane(agent, n))
+				.collect();
+
+			let mut result = Vec2::zeros();
+			let failed = linear_program_2(
+				&lines,
+				self.max_speed,
+				agent.preferred_velocity,
+				false,
+				&mut result,
+			);
+			if failed < lines.len() {
+				linear_program_3(&lines, failed, self.max_speed, &mut result);
+			}
+			Ok(result)
+		}
+		// new velocities for every agent, each avoiding all the others
+		pub fn new_velocities(&self, agents: &[Agent]) -> Result<Vec<Vec2>, OrcaError> {
+			(0..agents.len())
+				.map(|i| {
+					let neighbours: Vec<Agent> = agents
+						.iter()
+						.enumerate()
+						.filter(|&(j, _)| j != i)
+						.map(|(_, a)| *a)
+						.collect();
+					self.new_velocity(&agents[i], &neighbours)
+				})
+				.collect()
+		}
+		fn half_plane(&self, agent: &Agent, other: &Agent) -> Line {
+			let relative_position = other.position - agent.position;
+			let relative_velocity = agent.velocity - other.velocity;
+			let distance_sq = relative_position.magnitude_squared();
+			let radius = agent.radius + other.radius;
+			let radius_sq = radius * radius;
+
+			// smallest change to the relative velocity that gets it out of the
+			// velocity obstacle, and the direction of the obstacle's edge there
+			let (direction, u) = if distance_sq > radius_sq {
+				let w = relative_velocity - relative_position / self.time_horizon;
+				let w_length_sq = w.magnitude_squared();
+				let dot = w.dot(&relative_position);
+				if dot < 0.0 && dot * dot > radius_sq * w_length_sq {
+					// closest to the circle at the end of the time horizon
+					let w_length = w_length_sq.sqrt();
+					let unit_w = w / w_length;
+					(
+						Vec2::new(unit_w.y, -unit_w.x),
+						unit_w * (radius / self.time_horizon - w_length),
+					)
+				} else {
+					// closest to one of the legs of the cone
+					let leg = (distance_sq - radius_sq).sqrt();
+					let direction = if det(relative_position, w) > 0.0 {
+						Vec2::new(
+							relative_position.x * leg - relative_position.y * radius,
+							relative_position.x * radius + relative_position.y * leg,
+						) / distance_sq
+					} else {
+						-Vec2::new(
+							relative_position.x * leg + relative_position.y * radius,
+							-relative_position.x * radius + relative_position.y * leg,
+						) / distance_sq
+					};
+					(
+						direction,
+						direction * relative_velocity.dot(&direction) - relative_velocity,
+					)
+				}
+			} else {
+				// already colliding, get out within one time step
+				let w = relative_velocity - relative_position / self.time_step;
+				let w_length = w.magnitude();
+				let unit_w = w / w_length;
+				(
+					Vec2::new(unit_w.y, -unit_w.x),
+					unit_w * (radius / self.time_step - w_length),
+				)
+			};
+			Line {
+				// each agent takes half of the change
+				point: agent.velocity + u * 0.5,
+				direction,
+			}
+		}
+	}
+
+	// velocity along a nominal command, heading over the time step as the curvature
+	// turns the robot, the curvature as from pure_pursuit::get_curvature
+	#[must_use]
+	pub fn preferred_velocity(pose: &Ray, speed: f64, curvature: f64, time_step: f64) -> Vec2 {
+		// negative curvature is a left turn
+		let heading = pose.angle - 0.5 * curvature * speed * time_step;
+		Vec2::new(heading.cos(), heading.sin()) * speed
+	}
+
+	// speed and curvature that drive along the velocity over the time step, the
+	// inverse of preferred_velocity, velocities behind the robot give no speed
+	#[must_use]
+	pub fn to_command(pose: &Ray, velocity: Vec2, time_step: f64) -> (f64, f64) {
+		let local = Rotation2::new(-pose.angle) * velocity;
+		let speed = local.magnitude();
+		if speed < EPSILON || local.x <= 0.0 {
+			return (0.0, 0.0);
+		}
+		let turn = local.y.atan2(local.x);
+		(speed, -2.0 * turn / (speed * time_step))
+	}
+
+	fn det(a: Vec2, b: Vec2) -> f64 {
+		a.x * b.y - a.y * b.x
+	}
+
+	// best point on one line subject to the lines before it and the speed circle
+	fn linear_program_1(
+		lines: &[Line],
+		line: usize,
+		radius: f64,
+		optimal: Vec2,
+		direction_optimal: bool,
+		result: &mut Vec2,
+	) -> bool {
+		let Line { point, direction } = lines[line];
+		let dot = point.dot(&direction);
+		let discriminant = dot * dot + radius * radius - point.magnitude_squared();
+		if discriminant < 0.0 {
+			// the line misses the speed circle
+			return false;
+		}
+		let mut left = -dot - discriminant.sqrt();
+		let mut right = -dot + discriminant.sqrt();
+
+		for other in &lines[..line] {
+			let denominator = det(direction, other.direction);
+			let numerator = det(other.direction, point - other.point);
+			if denominator.abs() <= EPSILON {
+				// parallel lines
+				if numerator < 0.0 {
+					return false;
+				}
+				continue;
+			}
+			let t = numerator / denominator;
+			if denominator >= 0.0 {
+				right = right.min(t);
+			} else {
+				left = left.max(t);
+			}
+			if left > right {
+				return false;
+			}
+		}
+
+		let t = if direction_optimal {
+			if optimal.dot(&direction) > 0.0 {
+				right
+			} else {
+				left
+			}
+		} else {
+			direction.dot(&(optimal - point)).clamp(left, right)
+		};
+		*result = point + direction * t;
+		true
+	}
+
+	// closest point to optimal, or furthest along it when it's a direction,
+	// satisfying the lines, returns the index of the first line that can't be
+	// satisfied or the number of lines
+	fn linear_program_2(
+		lines: &[Line],
+		radius: f64,
+		optimal: Vec2,
+		direction_optimal: bool,
+		result: &mut Vec2,
+	) -> usize {
+		*result = if direction_optimal {
+			optimal * radius
+		} else if optimal.magnitude_squared() > radius * radius {
+			optimal.normalize() * radius
+		} else {
+			optimal
+		};
+		for (i, line) in lines.iter().enumerate() {
+			if det(line.direction, line.point - *result) > 0.0 {
+				let previous = *result;
+				if !linear_program_1(lines, i, radius, optimal, direction_optimal, result) {
+					*result = previous;
+					return i;
+				}
+			}
+		}
+		lines.len()
+	}
+
+	// minimise the largest violation of the lines from the one that failed onwards
+	fn linear_program_3(lines: &[Line], failed: usize, radius: f64, result: &mut Vec2) {
+		let mut distance = 0.0;
+		for i in failed..lines.len() {
+			let line = lines[i];
+			if det(line.direction, line.point - *result) <= distance {
+				continue;
+			}
+			// lines where moving away from line i is as bad as from the other
+			let mut projected = Vec::new();
+			for other in &lines[..i] {
+				let determinant = det(line.direction, other.direction);
+				let point = if determinant.abs() <= EPSILON {
+					if line.direction.dot(&other.direction) > 0.0 {
+						// same direction
+						continue;
+					}
+					(line.point + other.point) * 0.5
+				} else {
+					line.point
+						+ line.direction
+							* (det(other.direction, line.point - other.point) / determinant)
+				};
+				projected.push(Line {
+					point,
+					direction: (other.direction - line.direction).normalize(),
+				});
+			}
+			let previous = *result;
+			let outward = Vec2::new(-line.direction.y, line.direction.x);
+			if linear_program_2(&projected, radius, outward, true, result) < projected.len() {
+				// only from rounding, the previous result is feasible in principle
+				*result = previous;
+			}
+			distance = det(line.direction, line.point - *result);
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use crate::path_tracking::pure_pursuit::get_curvature;
+
+		#[test]
+		fn agents_avoid_each_other() {
+			let orca = Orca::default();
+			// four agents swapping places across the middle, not quite symmetric as
+			// ORCA can deadlock when it is
+			let starts = [
+				Vec2::new(-3.0, 0.2),
+				Vec2::new(3.0, -0.1),
+				Vec2::new(0.3, -3.0),
+				Vec2::new(-0.2, 3.0),
+			];
+			let goals: Vec<Vec2> = starts.iter().map(|s| -s).collect();
+			let mut agents: Vec<Agent> = starts
+				.iter()
+				.map(|&position| Agent {
+					position,
+					velocity: Vec2::zeros(),
+					radius: 0.3,
+					preferred_velocity: Vec2::zeros(),
+				})
+				.collect();
+
+			for _ in 0..200 {
+				for (agent, goal) in agents.iter_mut().zip(&goals) {
+					let to_goal = goal - agent.position;
+					agent.preferred_velocity =
+						if to_goal.magnitude() > orca.max_speed * orca.time_step {
+							to_goal.normalize() * orca.max_speed
+						} else {
+							to_goal / orca.time_step
+						};
+				}
+				let velocities = orca.new_velocities(&agents).unwrap();
+				for (agent, velocity) in agents.iter_mut().zip(velocities) {
+					assert!(velocity.magnitude() <= orca.max_speed + 1e-9);
+					agent.velocity = velocity;
+					agent.position += velocity * orca.time_step;
+				}
+				for i in 0..agents.len() {
+					for j in i + 1..agents.len() {
+						let gap = (agents[i].position - agents[j].position).magnitude();
+						assert!(gap >= 0.6 - 1e-6);
+					}
+				}
+			}
+			for (agent, goal) in agents.iter().zip(&goals) {
+				assert!((agent.position - goal).magnitude() < 0.05);
+			}
+
+			assert_eq!(
+				orca.new_velocity(
+					&agents[0],
+					&[Agent {
+						radius: -1.0,
+						..agents[1]
+					}]
+				)
+				.unwrap_err(),
+				OrcaError::InvalidInput
+			);
+		}
+
+		#[test]
+		fn follows_pure_pursuit_when_clear() {
+			let orca = Orca::default();
+			let points = [
+				Vec2::new(0.0, 0.0),
+				Vec2::new(1.0, 0.0),
+				Vec2::new(2.0, 1.0),
+				Vec2::new(3.0, 2.0),
+			];
+			let pose = Ray::new(Pos2::new(0.9, 0.1), 0.2);
+			let curvature = get_curvature(&points, &pose, 1.0).unwrap();
+
+			let agent = Agent {
+				position: pose.pos.coords,
+				velocity: Vec2::zeros(),
+				radius: 0.3,
+				preferred_velocity: preferred_velocity(&pose, 0.8, curvature, orca.time_step),
+			};
+			// a neighbour well out of the way changes nothing
+			let far = Agent {
+				position: Vec2::new(-5.0, -5.0),
+				..agent
+			};
+			let velocity = orca.new_velocity(&agent, &[far]).unwrap();
+			assert!((velocity - agent.preferred_velocity).magnitude() < 1e-12);
+			let (speed, new_curvature) = to_command(&pose, velocity, orca.time_step);
+			assert!((speed - 0.8).abs() < 1e-12);
+			assert!((new_curvature - curvature).abs() < 1e-9);
+
+			// one coming straight at it forces a change
+			let oncoming = Agent {
+				position: agent.position + velocity.normalize() * 1.0,
+				velocity: -velocity,
+				preferred_velocity: -velocity,
+				..agent
+			};
+			let avoiding = orca.new_velocity(&agent, &[oncoming]).unwrap();
+			assert!((avoiding - velocity).magnitude() > 0.1);
+		}
+	}
+}
@@ -0,0 +1,374 @@
+use super::{ScanMatch, ScanMatchError};
+use crate::{kd_tree::KdTree, prelude::*};
+
+// references:
+// https://doi.org/10.1109/ROBOT.2009.5152375 (Olson real-time correlative scan matching)
+
+// grid of log likelihoods of a measurement landing in each cell
+#[derive(Debug, Clone)]
+pub struct LikelihoodField {
+	origin: Pos2,
+	resolution: f64,
+	width: usize,
+	height: usize,
+	values: Vec<f64>,
+	// value for cells off the map or further than 3 sigma from an obstacle
+	min_value: f64,
+	// side length in cells of the block each value is the maximum over
+	block: usize,
+}
+
+impl LikelihoodField {
+	// sigma is the standard deviation of the range sensor noise
+	pub fn new(map: &[Pos2], resolution: f64, sigma: f64) -> Result<Self, ScanMatchError> {
+		if resolution <= 0.0 || sigma <= 0.0 {
+			return Err(ScanMatchError::InvalidInput);
+		}
+		if map.is_empty() {
+			return Err(ScanMatchError::NotEnoughPoints);
+		}
+
+		let max_distance = 3.0 * sigma;
+		let min_value = log_likelihood(max_distance, sigma);
+
+		let (min, max) = map.iter().fold(
+			(Vec2::repeat(f64::INFINITY), Vec2::repeat(f64::NEG_INFINITY)),
+			|(min, max), p| (min.inf(&p.coords), max.sup(&p.coords)),
+		);
+		let origin = Pos2::from(min - Vec2::repeat(max_distance));
+		let width = ((max.x - min.x + 2.0 * max_distance) / resolution).ceil() as usize + 1;
+		let height = ((max.y - min.y + 2.0 * max_distance) / resolution).ceil() as usize + 1;
+
+		let tree = KdTree::new(map);
+		let mut values = Vec::with_capacity(width * height);
+		for iy in 0..height {
+			for ix in 0..width {
+				let center = origin + resolution * Vec2::new(ix as f64 + 0.5, iy as f64 + 0.5);
+				let d = tree.nearest(center).map_or(max_distance, |v| v.1.sqrt());
+				values.push(log_likelihood(d.min(max_distance), sigma));
+			}
+		}
+
+		Ok(Self {
+			origin,
+			resolution,
+			width,
+			height,
+			values,
+			min_value,
+			block: 1,
+		})
+	}
+	#[must_use]
+	pub fn resolution(&self) -> f64 {
+		self.resolution
+	}
+	// log likelihood of the cell containing the point
+	#[must_use]
+	pub fn value(&self, p: Pos2) -> f64 {
+		let (ix, iy) = self.index(p);
+		self.get(ix, iy)
+	}
+	// bilinearly interpolated log likelihood
+	#[must_use]
+	pub fn interpolate(&self, p: Pos2) -> f64 {
+		let gx = (p.x - self.origin.x) / self.resolution - 0.5;
+		let gy = (p.y - self.origin.y) / self.resolution - 0.5;
+		let (fx, fy) = (gx.floor(), gy.floor());
+		let (tx, ty) = (gx - fx, gy - fy);
+		let (ix, iy) = (fx as i64, fy as i64);
+
+		let bottom = self.get(ix, iy) * (1.0 - tx) + self.get(ix + 1, iy) * tx;
+		let top = self.get(ix, iy + 1) * (1.0 - tx) + self.get(ix + 1, iy + 1) * tx;
+		bottom * (1.0 - ty) + top * ty
+	}
+	fn index(&self, p: Pos2) -> (i64, i64) {
+		(
+			((p.x - self.origin.x) / self.resolution).floor() as i64,
+			((p.y - self.origin.y) / self.resolution).floor() as i64,
+		)
+	}
+	fn get(&self, ix: i64, iy: i64) -> f64 {
+		// blocks starting off the map can still overlap it, and the block
+		// starting at the edge is a superset of the overlap
+		let block = self.block as i64;
+		let (ix, iy) = (
+			if ix < 0 && ix > -block { 0 } else { ix },
+			if iy < 0 && iy > -block { 0 } else { iy },
+		);
+		if ix < 0 || iy < 0 || ix as usize >= self.width || iy as usize >= self.height {
+			self.min_value
+		} else {
+			self.values[iy as usize * self.width + ix as usize]
+		}
+	}
+	// each cell holds the maximum over the factor x factor block starting at that cell
+	// so that the score of a block of translations can be upper bounded by one lookup
+	fn block_max(&self, factor: usize) -> Self {
+		let mut rows = Vec::with_capacity(self.values.len());
+		for iy in 0..self.height {
+			for ix in 0..self.width {
+				let end = (ix + factor).min(self.width);
+				let max = self.values[iy * self.width + ix..iy * self.width + end]
+					.iter()
+					.fold(self.min_value, |a, &b| a.max(b));
+				rows.push(max);
+			}
+		}
+		let mut values = Vec::with_capacity(self.values.len());
+		for iy in 0..self.height {
+			for ix in 0..self.width {
+				let end = (iy + factor).min(self.height);
+				let max = (iy..end)
+					.map(|y| rows[y * self.width + ix])
+					.fold(self.min_value, f64::max);
+				values.push(max);
+			}
+		}
+		Self {
+			values,
+			block: factor,
+			..self.clone()
+		}
+	}
+}
+
+fn log_likelihood(distance: f64, sigma: f64) -> f64 {
+	-distance * distance / (2.0 * sigma * sigma)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CorrelativeMatcher {
+	// search +-linear_window in x and y around the initial pose
+	pub linear_window: f64,
+	// search +-angular_window around the initial angle
+	pub angular_window: f64,
+	pub angular_step: f64,
+	// number of fine cells per side of a coarse cell
+	pub coarse_factor: usize,
+	// hill climb on the interpolated field after the exhaustive search
+	pub refine: bool,
+}
+
+impl Default for CorrelativeMatcher {
+	fn default() -> Self {
+		Self {
+			linear_window: 0.5,
+			angular_window: 0.3,
+			angular_step: 0.01,
+			coarse_factor: 4,
+			refine: true,
+		}
+	}
+}
+
+impl CorrelativeMatcher {
+	// iterations in the result is the number of full resolution poses evaluated
+	pub fn align(
+		&self,
+		field: &LikelihoodField,
+		scan: &[Pos2],
+		initial: Ray,
+	) -> Result<ScanMatch, ScanMatchError> {
+		if self.linear_window < 0.0
+			|| self.angular_window < 0.0
+			|| self.angular_step <= 0.0
+			|| self.coarse_factor == 0
+		{
+			return Err(ScanMatchError::InvalidInput);
+		}
+		if scan.is_empty() {
+			return Err(ScanMatchError::NotEnoughPoints);
+		}
+
+		let coarse = field.block_max(self.coarse_factor);
+		let k = self.coarse_factor as i64;
+		let w = (self.linear_window / field.resolution).ceil() as i64;
+		let angle_steps = (self.angular_window / self.angular_step).ceil() as i64;
+
+		// cell indices of the scan for each angle
+		let rotated: Vec<(f64, Vec<(i64, i64)>)> = (-angle_steps..=angle_steps)
+			.map(|i| {
+				let angle = initial.angle + i as f64 * self.angular_step;
+				let pose = Ray::new(initial.pos, angle);
+				let cells = scan
+					.iter()
+					.map(|&p| field.index(pose.point_from_local(p)))
+					.collect();
+				(angle, cells)
+			})
+			.collect();
+
+		let score = |grid: &LikelihoodField, cells: &[(i64, i64)], dx: i64, dy: i64| -> f64 {
+			cells.iter().map(|&(x, y)| grid.get(x + dx, y + dy)).sum()
+		};
+
+		// upper bounds for every block of translations at every angle
+		let mut candidates = Vec::new();
+		for (a, (_, cells)) in rotated.iter().enumerate() {
+			let mut dy = -w;
+			while dy <= w {
+				let mut dx = -w;
+				while dx <= w {
+					candidates.push((score(&coarse, cells, dx, dy), a, dx, dy));
+					dx += k;
+				}
+				dy += k;
+			}
+		}
+		candidates.sort_unstable_by(|a, b| float_cmp(b.0, a.0));
+
+		// branch and bound, only expand blocks that could beat the best so far
+		let mut best = (f64::NEG_INFINITY, initial);
+		let mut evaluated = Vec::new();
+		for (bound, a, bx, by) in candidates {
+			if bound < best.0 {
+				break;
+			}
+			let (angle, cells) = &rotated[a];
+			for dy in by..(by + k).min(w + 1) {
+				for dx in bx..(bx + k).min(w + 1) {
+					let s = score(field, cells, dx, dy);
+					let pose = Ray::new(
+						initial.pos + field.resolution * Vec2::new(dx as f64, dy as f64),
+						*angle,
+					);
+					if s > best.0 {
+						best = (s, pose);
+					}
+					evaluated.push((s, pose));
+				}
+			}
+		}
+
+		let covariance = self.covariance(&evaluated, best.0, field.resolution);
+		let (mut best_score, mut transform) = best;
+		if self.refine {
+			(best_score, transform) = self.hill_climb(field, scan, transform);
+		}
+
+		Ok(ScanMatch {
+			transform,
+			covariance,
+			error: -best_score / scan.len() as f64,
+			iterations: evaluated.len(),
+		})
+	}
+	// covariance of the poses weighted by their likelihood (section III.C)
+	fn covariance(&self, evaluated: &[(f64, Ray)], best: f64, resolution: f64) -> Mat3 {
+		let mut k = Mat3::zeros();
+		let mut u = Vec3::zeros();
+		let mut s = 0.0;
+		for (score, pose) in evaluated {
+			let p = (score - best).exp();
+			let x = Vec3::new(pose.pos.x, pose.pos.y, pose.angle);
+			k += p * x * x.transpose();
+			u += p * x;
+			s += p;
+		}
+		// variance of uniform quantisation since the true pose can be anywhere in the cell
+		let quantisation = Mat3::from_diagonal(&Vec3::new(
+			resolution * resolution / 12.0,
+			resolution * resolution / 12.0,
+			self.angular_step * self.angular_step / 12.0,
+		));
+		k / s - u * u.transpose() / (s * s) + quantisation
+	}
+	fn hill_climb(&self, field: &LikelihoodField, scan: &[Pos2], start: Ray) -> (f64, Ray) {
+		let score = |pose: Ray| -> f64 {
+			scan.iter()
+				.map(|&p| field.interpolate(pose.point_from_local(p)))
+				.sum()
+		};
+		let mut best = (score(start), start);
+		let mut linear = 0.5 * field.resolution;
+		let mut angular = 0.5 * self.angular_step;
+
+		for _ in 0..REFINE_LEVELS {
+			let mut improved = true;
+			while improved {
+				improved = false;
+				for (dx, dy, da) in [
+					(linear, 0.0, 0.0),
+					(-linear, 0.0, 0.0),
+					(0.0, linear, 0.0),
+					(0.0, -linear, 0.0),
+					(0.0, 0.0, angular),
+					(0.0, 0.0, -angular),
+				] {
+					let pose = Ray::new(best.1.pos + Vec2::new(dx, dy), best.1.angle + da);
+					let s = score(pose);
+					if s > best.0 {
+						best = (s, pose);
+						improved = true;
+					}
+				}
+			}
+			linear *= 0.5;
+			angular *= 0.5;
+		}
+		best
+	}
+}
+
+const REFINE_LEVELS: usize = 5;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn room() -> Vec<Pos2> {
+		let mut points = Vec::new();
+		for i in 0..=80 {
+			let t = f64::from(i) * 0.05;
+			points.push(Pos2::new(t, 0.0));
+			points.push(Pos2::new(t, 3.0));
+		}
+		for i in 1..60 {
+			let t = f64::from(i) * 0.05;
+			points.push(Pos2::new(0.0, t));
+			points.push(Pos2::new(4.0, t));
+		}
+		for i in 0..10 {
+			let t = f64::from(i) * 0.05;
+			points.push(Pos2::new(2.5 + t, 1.0));
+			points.push(Pos2::new(2.5, 1.0 + t));
+		}
+		points
+	}
+
+	#[test]
+	fn correlative_align() {
+		let map = room();
+		let field = LikelihoodField::new(&map, 0.03, 0.1).unwrap();
+		let truth = Ray::new(Pos2::new(0.22, -0.13), 0.07);
+		let scan: Vec<Pos2> = map.iter().map(|&p| truth.point_to_local(p)).collect();
+
+		let matcher = CorrelativeMatcher::default();
+		let result = matcher.align(&field, &scan, Ray::ZERO).unwrap();
+
+		assert!((result.transform.pos - truth.pos).magnitude() < 0.01);
+		assert!((result.transform.angle - truth.angle).abs() < 0.005);
+		assert!(result.covariance.diagonal().iter().all(|&v| v > 0.0));
+
+		// should only expand a small number of blocks
+		let max = (2 * 17 + 1) * (2 * 17 + 1) * (2 * 30 + 1);
+		assert!(result.iterations < max / 4);
+	}
+
+	#[test]
+	fn block_max_is_upper_bound() {
+		let mut field = LikelihoodField::new(&room(), 0.1, 0.1).unwrap();
+		// the margin keeps the edges at min_value, so put a peak in the corner
+		// to check blocks that start off the map
+		field.values[0] = 0.0;
+		let coarse = field.block_max(3);
+		for iy in -4..field.height as i64 {
+			for ix in -4..field.width as i64 {
+				for (dx, dy) in [(0, 0), (2, 0), (0, 2), (2, 2), (1, 1)] {
+					assert!(coarse.get(ix, iy) >= field.get(ix + dx, iy + dy));
+				}
+			}
+		}
+	}
+}
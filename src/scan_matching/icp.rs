@@ -0,0 +1,308 @@
+use super::{compose, covariance, ScanMatch, ScanMatchError};
+use crate::{kd_tree::KdTree, prelude::*};
+
+// references:
+// https://doi.org/10.1109/34.121791 (Besl & McKay point to point)
+// https://doi.org/10.1109/ROBOT.2008.4543181 (Censi PL-ICP)
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IcpMetric {
+	PointToPoint,
+	// minimises distance to the line through the two closest reference points
+	PointToLine,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Icp {
+	pub metric: IcpMetric,
+	pub max_iterations: usize,
+	// stop once the correction in both translation and angle is below this
+	pub tolerance: f64,
+	// correspondences further apart than this are rejected
+	pub max_correspondence_distance: f64,
+	// fraction of the worst remaining correspondences rejected each iteration (trimmed ICP)
+	pub outlier_ratio: f64,
+}
+
+impl Default for Icp {
+	fn default() -> Self {
+		Self::new(IcpMetric::PointToLine)
+	}
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Correspondence {
+	// scan point transformed into the reference frame
+	source: Pos2,
+	target: Pos2,
+	// only used for point to line
+	normal: Vec2,
+	residual_sq: f64,
+}
+
+impl Icp {
+	pub const fn new(metric: IcpMetric) -> Self {
+		Self {
+			metric,
+			max_iterations: 50,
+			tolerance: 1e-6,
+			max_correspondence_distance: 1.0,
+			outlier_ratio: 0.1,
+		}
+	}
+	pub fn align(
+		&self,
+		reference: &[Pos2],
+		scan: &[Pos2],
+		initial: Ray,
+	) -> Result<ScanMatch, ScanMatchError> {
+		self.align_to_tree(&KdTree::new(reference), scan, initial)
+	}
+	// allows reusing the tree when matching multiple scans against the same reference
+	pub fn align_to_tree(
+		&self,
+		reference: &KdTree,
+		scan: &[Pos2],
+		initial: Ray,
+	) -> Result<ScanMatch, ScanMatchError> {
+		if self.max_iterations == 0
+			|| self.tolerance < 0.0
+			|| self.max_correspondence_distance <= 0.0
+			|| !(0.0..1.0).contains(&self.outlier_ratio)
+		{
+			return Err(ScanMatchError::InvalidInput);
+		}
+		if reference.len() < 2 || scan.len() < 3 {
+			return Err(ScanMatchError::NotEnoughPoints);
+		}
+
+		let mut transform = initial;
+		let mut iterations = 0;
+
+		while iterations < self.max_iterations {
+			iterations += 1;
+			let correspondences = self.correspondences(reference, scan, transform)?;
+			let correction = match self.metric {
+				IcpMetric::PointToPoint => point_to_point_step(&correspondences),
+				IcpMetric::PointToLine => point_to_line_step(&correspondences)?,
+			};
+			transform = compose(correction, transform);
+
+			if correction.pos.coords.magnitude() < self.tolerance
+				&& correction.angle.abs() < self.tolerance
+			{
+				break;
+			}
+		}
+
+		let correspondences = self.correspondences(reference, scan, transform)?;
+		let (jtj, rss, residuals) = match self.metric {
+			IcpMetric::PointToPoint => point_to_point_hessian(&correspondences),
+			IcpMetric::PointToLine => point_to_line_hessian(&correspondences),
+		};
+
+		Ok(ScanMatch {
+			transform,
+			covariance: covariance(jtj, rss, residuals)?,
+			error: rss / correspondences.len() as f64,
+			iterations,
+		})
+	}
+	fn correspondences(
+		&self,
+		reference: &KdTree,
+		scan: &[Pos2],
+		transform: Ray,
+	) -> Result<Vec<Correspondence>, ScanMatchError> {
+		let max_d_sq = self.max_correspondence_distance * self.max_correspondence_distance;
+		let points = reference.points();
+
+		let mut correspondences: Vec<Correspondence> = scan
+			.iter()
+			.filter_map(|&p| {
+				let source = transform.point_from_local(p);
+				match self.metric {
+					IcpMetric::PointToPoint => {
+						let (i, d_sq) = reference.nearest(source)?;
+						(d_sq <= max_d_sq).then_some(Correspondence {
+							source,
+							target: points[i],
+							normal: Vec2::zeros(),
+							residual_sq: d_sq,
+						})
+					}
+					IcpMetric::PointToLine => {
+						let nearest = reference.nearest_k(source, 2);
+						let [(i0, d_sq), (i1, _)] = nearest[..] else {
+							return None;
+						};
+						if d_sq > max_d_sq {
+							return None;
+						}
+						let dir = points[i1] - points[i0];
+						let len = dir.magnitude();
+						if len == 0.0 {
+							return None;
+						}
+						let normal = Vec2::new(-dir.y, dir.x) / len;
+						let r = normal.dot(&(source - points[i0]));
+						Some(Correspondence {
+							source,
+							target: points[i0],
+							normal,
+							residual_sq: r * r,
+						})
+					}
+				}
+			})
+			.collect();
+
+		// trimmed ICP, throw away the worst matches
+		correspondences.sort_by(|a, b| float_cmp(a.residual_sq, b.residual_sq));
+		let keep = ((1.0 - self.outlier_ratio) * correspondences.len() as f64).ceil() as usize;
+		correspondences.truncate(keep);
+
+		if correspondences.len() < 3 {
+			return Err(ScanMatchError::NotEnoughCorrespondences);
+		}
+		Ok(correspondences)
+	}
+}
+
+// closed form least squares rigid transform between matched point sets
+fn point_to_point_step(correspondences: &[Correspondence]) -> Ray {
+	let n = correspondences.len() as f64;
+	let (ps, qs) = correspondences
+		.iter()
+		.fold((Vec2::zeros(), Vec2::zeros()), |(ps, qs), c| {
+			(ps + c.source.coords, qs + c.target.coords)
+		});
+	let p_mean = ps / n;
+	let q_mean = qs / n;
+
+	let (sin, cos) = correspondences.iter().fold((0.0, 0.0), |(s, c), v| {
+		let p = v.source.coords - p_mean;
+		let q = v.target.coords - q_mean;
+		(s + p.x * q.y - p.y * q.x, c + p.x * q.x + p.y * q.y)
+	});
+	let angle = sin.atan2(cos);
+	let t = q_mean - Rotation2::new(angle) * p_mean;
+
+	Ray::new(t.into(), angle)
+}
+
+// single gauss-newton step linearised around a zero correction
+fn point_to_line_step(correspondences: &[Correspondence]) -> Result<Ray, ScanMatchError> {
+	let mut jtj = Mat3::zeros();
+	let mut jtr = Vec3::zeros();
+	for c in correspondences {
+		let j = point_to_line_jacobian(c);
+		let r = c.normal.dot(&(c.source - c.target));
+		jtj += j * j.transpose();
+		jtr += j * r;
+	}
+	let x = -jtj
+		.try_inverse()
+		.ok_or(ScanMatchError::DegenerateGeometry)?
+		* jtr;
+
+	Ok(Ray::new(Pos2::new(x.x, x.y), x.z))
+}
+
+fn point_to_line_jacobian(c: &Correspondence) -> Vec3 {
+	// d(R p)/d(angle) at zero is p rotated by 90 degrees
+	let perp = Vec2::new(-c.source.y, c.source.x);
+	Vec3::new(c.normal.x, c.normal.y, c.normal.dot(&perp))
+}
+
+fn point_to_line_hessian(correspondences: &[Correspondence]) -> (Mat3, f64, usize) {
+	correspondences
+		.iter()
+		.fold((Mat3::zeros(), 0.0, 0), |(jtj, rss, n), c| {
+			let j = point_to_line_jacobian(c);
+			(jtj + j * j.transpose(), rss + c.residual_sq, n + 1)
+		})
+}
+
+fn point_to_point_hessian(correspondences: &[Correspondence]) -> (Mat3, f64, usize) {
+	correspondences
+		.iter()
+		.fold((Mat3::zeros(), 0.0, 0), |(jtj, rss, n), c| {
+			let jx = Vec3::new(1.0, 0.0, -c.source.y);
+			let jy = Vec3::new(0.0, 1.0, c.source.x);
+			(
+				jtj + jx * jx.transpose() + jy * jy.transpose(),
+				rss + c.residual_sq,
+				n + 2,
+			)
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// points along the walls of a room with a pillar so that the match is fully constrained
+	fn room() -> Vec<Pos2> {
+		let mut points = Vec::new();
+		let step = 0.05;
+		for i in 0..=80 {
+			let t = f64::from(i) * step;
+			points.push(Pos2::new(t, 0.0));
+			points.push(Pos2::new(t, 3.0));
+		}
+		for i in 1..60 {
+			let t = f64::from(i) * step;
+			points.push(Pos2::new(0.0, t));
+			points.push(Pos2::new(4.0, t));
+		}
+		for i in 0..10 {
+			let t = f64::from(i) * step;
+			points.push(Pos2::new(2.5 + t, 1.0));
+			points.push(Pos2::new(2.5, 1.0 + t));
+		}
+		points
+	}
+
+	fn check(metric: IcpMetric, truth: Ray) {
+		let reference = room();
+		let mut scan: Vec<Pos2> = reference.iter().map(|&p| truth.point_to_local(p)).collect();
+		// some outliers
+		scan.push(Pos2::new(10.0, 10.0));
+		scan.push(Pos2::new(2.0, 1.7));
+
+		let icp = Icp::new(metric);
+		let result = icp.align(&reference, &scan, Ray::ZERO).unwrap();
+
+		assert!((result.transform.pos - truth.pos).magnitude() < 1e-3);
+		assert!((result.transform.angle - truth.angle).abs() < 1e-3);
+		assert!(result.covariance.diagonal().iter().all(|&v| v >= 0.0));
+	}
+
+	#[test]
+	fn point_to_point() {
+		// point to point can slide into a wrong correspondence on evenly sampled walls
+		// if the initial error is more than half the sample spacing
+		check(
+			IcpMetric::PointToPoint,
+			Ray::new(Pos2::new(0.02, -0.015), 0.005),
+		);
+	}
+
+	#[test]
+	fn point_to_line() {
+		check(
+			IcpMetric::PointToLine,
+			Ray::new(Pos2::new(0.15, -0.1), 0.05),
+		);
+	}
+
+	#[test]
+	fn not_enough_points() {
+		let icp = Icp::default();
+		assert_eq!(
+			icp.align(&room(), &[Pos2::new(0.0, 0.0)], Ray::ZERO),
+			Err(ScanMatchError::NotEnoughPoints)
+		);
+	}
+}
@@ -0,0 +1,49 @@
+pub(crate) mod correlative;
+pub(crate) mod icp;
+pub(crate) mod ndt;
+
+pub use correlative::*;
+pub use icp::*;
+pub use ndt::*;
+
+use crate::prelude::*;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScanMatchError {
+	NotEnoughPoints,
+	NotEnoughCorrespondences,
+	DegenerateGeometry,
+	InvalidInput,
+}
+
+// transform takes points in the frame of the scan into the frame of the reference
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScanMatch {
+	pub transform: Ray,
+	// covariance of (x, y, angle)
+	pub covariance: Mat3,
+	// alignment error of the final estimate, lower is better
+	// ICP: mean squared residual of the final correspondences
+	// NDT: one minus the mean score of each point against its best cell, in [0, 1]
+	// correlative: negated mean log likelihood per point
+	pub error: f64,
+	pub iterations: usize,
+}
+
+// composes a small correction with an existing transform
+// such that the correction is applied after the transform
+pub(crate) fn compose(correction: Ray, transform: Ray) -> Ray {
+	correction.ray_from_local(transform)
+}
+
+// covariance estimate for a least squares problem from the hessian approximation J^T J
+// and the residual sum of squares
+pub(crate) fn covariance(jtj: Mat3, rss: f64, residuals: usize) -> Result<Mat3, ScanMatchError> {
+	if residuals <= 3 {
+		return Err(ScanMatchError::NotEnoughCorrespondences);
+	}
+	let sigma_sq = rss / (residuals - 3) as f64;
+	jtj.try_inverse()
+		.map(|inv| inv * sigma_sq)
+		.ok_or(ScanMatchError::DegenerateGeometry)
+}
@@ -0,0 +1,351 @@
+use super::{ScanMatch, ScanMatchError};
+use crate::prelude::*;
+
+// references:
+// https://doi.org/10.1109/IROS.2003.1249285 (Biber & Straßer)
+
+const MAX_ANGLE_STEP: f64 = 0.2;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct NdtCell {
+	mean: Vec2,
+	inverse_covariance: Mat2,
+}
+
+// grid of gaussians built from a reference scan
+#[derive(Debug, Clone)]
+pub struct NdtGrid {
+	origin: Pos2,
+	resolution: f64,
+	width: usize,
+	height: usize,
+	cells: Vec<Option<NdtCell>>,
+}
+
+impl NdtGrid {
+	// cells with less than 3 points are left empty
+	pub fn new(reference: &[Pos2], resolution: f64) -> Result<Self, ScanMatchError> {
+		if resolution <= 0.0 {
+			return Err(ScanMatchError::InvalidInput);
+		}
+		if reference.is_empty() {
+			return Err(ScanMatchError::NotEnoughPoints);
+		}
+
+		let (min, max) = reference.iter().fold(
+			(Vec2::repeat(f64::INFINITY), Vec2::repeat(f64::NEG_INFINITY)),
+			|(min, max), p| (min.inf(&p.coords), max.sup(&p.coords)),
+		);
+		let origin = Pos2::from(min);
+		let width = ((max.x - min.x) / resolution).floor() as usize + 1;
+		let height = ((max.y - min.y) / resolution).floor() as usize + 1;
+
+		// (count, sum, sum of outer products)
+		let mut sums = Vec::new();
+		sums.resize(width * height, (0usize, Vec2::zeros(), Mat2::zeros()));
+		for p in reference {
+			let ix = ((p.x - origin.x) / resolution).floor() as usize;
+			let iy = ((p.y - origin.y) / resolution).floor() as usize;
+			let sum = &mut sums[iy * width + ix];
+			sum.0 += 1;
+			sum.1 += p.coords;
+			sum.2 += p.coords * p.coords.transpose();
+		}
+
+		let cells: Vec<Option<NdtCell>> = sums
+			.into_iter()
+			.map(|(n, sum, sum_sq)| {
+				if n < 3 {
+					return None;
+				}
+				let n = n as f64;
+				let mean = sum / n;
+				let covariance = (sum_sq - n * mean * mean.transpose()) / (n - 1.0);
+				Some(NdtCell {
+					mean,
+					inverse_covariance: regularised_inverse(covariance)?,
+				})
+			})
+			.collect();
+
+		if cells.iter().all(Option::is_none) {
+			return Err(ScanMatchError::NotEnoughPoints);
+		}
+
+		Ok(Self {
+			origin,
+			resolution,
+			width,
+			height,
+			cells,
+		})
+	}
+	#[must_use]
+	pub fn resolution(&self) -> f64 {
+		self.resolution
+	}
+	fn cell(&self, ix: i64, iy: i64) -> Option<&NdtCell> {
+		if ix < 0 || iy < 0 || ix as usize >= self.width || iy as usize >= self.height {
+			return None;
+		}
+		self.cells[iy as usize * self.width + ix as usize].as_ref()
+	}
+	// the cell containing the point and its neighbours
+	fn nearby_cells(&self, p: Pos2) -> impl Iterator<Item = &NdtCell> {
+		let ix = ((p.x - self.origin.x) / self.resolution).floor() as i64;
+		let iy = ((p.y - self.origin.y) / self.resolution).floor() as i64;
+		(-1..=1)
+			.flat_map(move |dy| (-1..=1).map(move |dx| (ix + dx, iy + dy)))
+			.filter_map(|(x, y)| self.cell(x, y))
+	}
+}
+
+// a line of points has a singular covariance so clamp the smallest eigenvalue
+fn regularised_inverse(covariance: Mat2) -> Option<Mat2> {
+	let eigen = covariance.symmetric_eigen();
+	let max = eigen.eigenvalues.max();
+	if max <= 0.0 {
+		return None;
+	}
+	let clamped = eigen.eigenvalues.map(|v| 1.0 / v.max(0.01 * max));
+	Some(eigen.eigenvectors * Mat2::from_diagonal(&clamped) * eigen.eigenvectors.transpose())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ndt {
+	// side length of each grid cell
+	pub resolution: f64,
+	pub max_iterations: usize,
+	// stop once the newton step in both translation and angle is below this
+	pub tolerance: f64,
+}
+
+impl Default for Ndt {
+	fn default() -> Self {
+		Self {
+			resolution: 0.5,
+			max_iterations: 50,
+			tolerance: 1e-6,
+		}
+	}
+}
+
+impl Ndt {
+	pub fn align(
+		&self,
+		reference: &[Pos2],
+		scan: &[Pos2],
+		initial: Ray,
+	) -> Result<ScanMatch, ScanMatchError> {
+		self.align_to_grid(&NdtGrid::new(reference, self.resolution)?, scan, initial)
+	}
+	// allows reusing the grid when matching multiple scans against the same reference
+	// note that the resolution of the grid is used rather than self.resolution
+	pub fn align_to_grid(
+		&self,
+		grid: &NdtGrid,
+		scan: &[Pos2],
+		initial: Ray,
+	) -> Result<ScanMatch, ScanMatchError> {
+		if self.max_iterations == 0 || self.tolerance < 0.0 {
+			return Err(ScanMatchError::InvalidInput);
+		}
+		if scan.len() < 3 {
+			return Err(ScanMatchError::NotEnoughPoints);
+		}
+
+		let mut transform = initial;
+		let mut iterations = 0;
+
+		while iterations < self.max_iterations {
+			iterations += 1;
+			let (score, gradient, hessian) = evaluate(grid, scan, transform);
+			if score == 0.0 {
+				return Err(ScanMatchError::NotEnoughCorrespondences);
+			}
+
+			let mut step = -positive_definite(hessian)
+				.try_inverse()
+				.ok_or(ScanMatchError::DegenerateGeometry)?
+				* gradient;
+
+			// far from the optimum the newton step can be huge so limit it
+			// to half a cell and a fixed angle
+			let translation = Vec2::new(step.x, step.y).magnitude();
+			let limit = (0.5 * grid.resolution / translation).min(MAX_ANGLE_STEP / step.z.abs());
+			if limit < 1.0 {
+				step *= limit;
+			}
+
+			// backtrack if the step makes things worse
+			let mut scale = 1.0;
+			let mut improved = false;
+			for _ in 0..10 {
+				let next = Ray::new(
+					transform.pos + scale * Vec2::new(step.x, step.y),
+					transform.angle + scale * step.z,
+				);
+				if score_only(grid, scan, next) <= score {
+					transform = next;
+					improved = true;
+					break;
+				}
+				scale *= 0.5;
+			}
+
+			if !improved
+				|| (scale * Vec2::new(step.x, step.y).magnitude() < self.tolerance
+					&& scale * step.z.abs() < self.tolerance)
+			{
+				break;
+			}
+		}
+
+		let (_, _, hessian) = evaluate(grid, scan, transform);
+		let covariance = positive_definite(hessian)
+			.try_inverse()
+			.ok_or(ScanMatchError::DegenerateGeometry)?;
+
+		Ok(ScanMatch {
+			transform,
+			covariance,
+			error: 1.0 - best_cell_scores(grid, scan, transform) / scan.len() as f64,
+			iterations,
+		})
+	}
+}
+
+// returns the negated ndt score and its gradient and hessian with respect to (x, y, angle)
+fn evaluate(grid: &NdtGrid, scan: &[Pos2], transform: Ray) -> (f64, Vec3, Mat3) {
+	let (s, c) = transform.angle.sin_cos();
+
+	let mut score = 0.0;
+	let mut gradient = Vec3::zeros();
+	let mut hessian = Mat3::zeros();
+
+	for p in scan {
+		let x = transform.point_from_local(*p);
+		// derivatives of the transformed point
+		let d_angle = Vec2::new(-p.x * s - p.y * c, p.x * c - p.y * s);
+		let dd_angle = Vec2::new(-p.x * c + p.y * s, -p.x * s - p.y * c);
+		let jacobian = [Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0), d_angle];
+
+		for cell in grid.nearby_cells(x) {
+			let q = x.coords - cell.mean;
+			let a_q = cell.inverse_covariance * q;
+			let e = (-0.5 * q.dot(&a_q)).exp();
+			if e == 0.0 {
+				continue;
+			}
+			score -= e;
+
+			let qa_j = Vec3::new(
+				a_q.dot(&jacobian[0]),
+				a_q.dot(&jacobian[1]),
+				a_q.dot(&jacobian[2]),
+			);
+			gradient += e * qa_j;
+
+			for i in 0..3 {
+				for j in 0..3 {
+					let mut v = -qa_j[i] * qa_j[j]
+						+ jacobian[j].dot(&(cell.inverse_covariance * jacobian[i]));
+					if i == 2 && j == 2 {
+						v += a_q.dot(&dd_angle);
+					}
+					hessian[(i, j)] += e * v;
+				}
+			}
+		}
+	}
+
+	(score, gradient, hessian)
+}
+
+fn score_only(grid: &NdtGrid, scan: &[Pos2], transform: Ray) -> f64 {
+	scan.iter()
+		.map(|p| {
+			let x = transform.point_from_local(*p);
+			grid.nearby_cells(x)
+				.map(|cell| {
+					let q = x.coords - cell.mean;
+					-(-0.5 * q.dot(&(cell.inverse_covariance * q))).exp()
+				})
+				.sum::<f64>()
+		})
+		.sum()
+}
+
+// each point can score against several nearby cells, so only count its best
+// one to keep the total in [0, n] where n is the number of points
+fn best_cell_scores(grid: &NdtGrid, scan: &[Pos2], transform: Ray) -> f64 {
+	scan.iter()
+		.map(|p| {
+			let x = transform.point_from_local(*p);
+			grid.nearby_cells(x)
+				.map(|cell| {
+					let q = x.coords - cell.mean;
+					(-0.5 * q.dot(&(cell.inverse_covariance * q))).exp()
+				})
+				.fold(0.0, f64::max)
+		})
+		.sum()
+}
+
+// newton's method needs a positive definite hessian to descend
+// so shift the eigenvalues up if it isn't
+fn positive_definite(hessian: Mat3) -> Mat3 {
+	let eigenvalues = hessian.symmetric_eigenvalues();
+	let floor = 1e-3 * eigenvalues.amax().max(1e-9);
+	let min = eigenvalues.min();
+	if min >= floor {
+		hessian
+	} else {
+		hessian + Mat3::identity() * (floor - min)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn room() -> Vec<Pos2> {
+		let mut points = Vec::new();
+		for i in 0..=80 {
+			let t = f64::from(i) * 0.05;
+			points.push(Pos2::new(t, 0.0));
+			points.push(Pos2::new(t, 3.0 + 0.2 * (t * 2.0).sin()));
+		}
+		for i in 1..60 {
+			let t = f64::from(i) * 0.05;
+			points.push(Pos2::new(0.0, t));
+			points.push(Pos2::new(4.0 - 0.1 * t, t));
+		}
+		points
+	}
+
+	#[test]
+	fn ndt_align() {
+		let reference = room();
+		let truth = Ray::new(Pos2::new(0.2, -0.1), 0.05);
+		let scan: Vec<Pos2> = reference.iter().map(|&p| truth.point_to_local(p)).collect();
+
+		let result = Ndt::default().align(&reference, &scan, Ray::ZERO).unwrap();
+
+		assert!((result.transform.pos - truth.pos).magnitude() < 1e-3);
+		assert!((result.transform.angle - truth.angle).abs() < 1e-3);
+		assert!(result.error >= 0.0 && result.error < 0.5);
+	}
+
+	#[test]
+	fn invalid_grid() {
+		assert_eq!(
+			NdtGrid::new(&room(), 0.0).unwrap_err(),
+			ScanMatchError::InvalidInput
+		);
+		assert_eq!(
+			NdtGrid::new(&[Pos2::new(0.0, 0.0)], 1.0).unwrap_err(),
+			ScanMatchError::NotEnoughPoints
+		);
+	}
+}
@@ -0,0 +1,242 @@
+use super::SensorError;
+use crate::prelude::*;
+
+// ranges outside of [range_min, range_max] or that aren't finite are invalid
+// and are skipped when converting to points
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaserScan {
+	// angle of the first beam in the sensor frame
+	pub angle_min: f64,
+	// angle between consecutive beams, may be negative for clockwise scans
+	pub angle_increment: f64,
+	pub range_min: f64,
+	pub range_max: f64,
+	pub ranges: Vec<f64>,
+	// either empty or the same length as ranges
+	pub intensities: Vec<f64>,
+}
+
+impl LaserScan {
+	pub fn new(
+		angle_min: f64,
+		angle_increment: f64,
+		range_min: f64,
+		range_max: f64,
+		ranges: Vec<f64>,
+		intensities: Vec<f64>,
+	) -> Result<Self, SensorError> {
+		if !angle_increment.is_finite()
+			|| !angle_min.is_finite()
+			|| !range_min.is_finite()
+			|| range_max.is_nan()
+			|| range_min < 0.0
+			|| range_max <= range_min
+		{
+			return Err(SensorError::InvalidInput);
+		}
+		if !intensities.is_empty() && intensities.len() != ranges.len() {
+			return Err(SensorError::LengthMismatch);
+		}
+		Ok(Self {
+			angle_min,
+			angle_increment,
+			range_min,
+			range_max,
+			ranges,
+			intensities,
+		})
+	}
+	// ray casts against line segments from the sensor pose
+	// beams that don't hit anything within range_max are infinite, hits closer
+	// than range_min keep their range and are invalid as the sensor is blinded
+	pub fn simulate(
+		sensor: Ray,
+		angle_min: f64,
+		angle_increment: f64,
+		beams: usize,
+		(range_min, range_max): (f64, f64),
+		segments: &[(Pos2, Pos2)],
+	) -> Result<Self, SensorError> {
+		let mut scan = Self::new(
+			angle_min,
+			angle_increment,
+			range_min,
+			range_max,
+			Vec::new(),
+			Vec::new(),
+		)?;
+		scan.ranges = (0..beams)
+			.map(|i| {
+				let beam = Ray::new(
+					sensor.pos,
+					sensor.angle + angle_min + i as f64 * angle_increment,
+				);
+				segments
+					.iter()
+					.filter_map(|&(a, b)| ray_segment_intersection(beam, a, b))
+					.min_by(|a, b| float_cmp(*a, *b))
+					.filter(|&t| t <= range_max)
+					.unwrap_or(f64::INFINITY)
+			})
+			.collect();
+		Ok(scan)
+	}
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.ranges.len()
+	}
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+	#[must_use]
+	pub fn angle(&self, index: usize) -> f64 {
+		self.angle_min + index as f64 * self.angle_increment
+	}
+	#[must_use]
+	pub fn angle_max(&self) -> f64 {
+		self.angle(self.len().saturating_sub(1))
+	}
+	#[must_use]
+	pub fn is_valid(&self, range: f64) -> bool {
+		range.is_finite() && range >= self.range_min && range <= self.range_max
+	}
+	// points in the sensor frame, x forwards
+	#[must_use]
+	pub fn to_points(&self) -> Vec<Pos2> {
+		self.to_world_points(Ray::ZERO)
+	}
+	// points transformed by the pose of the sensor
+	#[must_use]
+	pub fn to_world_points(&self, sensor: Ray) -> Vec<Pos2> {
+		self.ranges
+			.iter()
+			.enumerate()
+			.filter(|(_, &r)| self.is_valid(r))
+			.map(|(i, &r)| Ray::new(sensor.pos, sensor.angle + self.angle(i)).at(r))
+			.collect()
+	}
+	// marks ranges outside of [min, max] as invalid, keeps the beam layout
+	#[must_use]
+	pub fn filtered(&self, min: f64, max: f64) -> Self {
+		let mut scan = self.clone();
+		for r in &mut scan.ranges {
+			if !(min..=max).contains(r) {
+				*r = f64::INFINITY;
+			}
+		}
+		scan
+	}
+	// keeps every factor-th beam
+	#[must_use]
+	pub fn downsampled(&self, factor: usize) -> Self {
+		let factor = factor.max(1);
+		Self {
+			angle_min: self.angle_min,
+			angle_increment: self.angle_increment * factor as f64,
+			range_min: self.range_min,
+			range_max: self.range_max,
+			ranges: self.ranges.iter().step_by(factor).copied().collect(),
+			intensities: self.intensities.iter().step_by(factor).copied().collect(),
+		}
+	}
+}
+
+// distance along the ray to the segment if they intersect
+fn ray_segment_intersection(ray: Ray, a: Pos2, b: Pos2) -> Option<f64> {
+	let dir = Vec2::new(ray.angle.cos(), ray.angle.sin());
+	let seg = b - a;
+	let denom = dir.perp(&seg);
+	if denom == 0.0 {
+		return None;
+	}
+	let ao = a - ray.pos;
+	let t = ao.perp(&seg) / denom;
+	let u = ao.perp(&dir) / denom;
+	(t >= 0.0 && (0.0..=1.0).contains(&u)).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use core::f64::consts::{FRAC_PI_2, PI};
+
+	fn square() -> [(Pos2, Pos2); 4] {
+		let c = [
+			Pos2::new(-2.0, -2.0),
+			Pos2::new(2.0, -2.0),
+			Pos2::new(2.0, 2.0),
+			Pos2::new(-2.0, 2.0),
+		];
+		[(c[0], c[1]), (c[1], c[2]), (c[2], c[3]), (c[3], c[0])]
+	}
+
+	#[test]
+	fn simulate_square() {
+		let scan = LaserScan::simulate(
+			Ray::new(Pos2::new(1.0, 0.0), FRAC_PI_2),
+			-PI,
+			FRAC_PI_2,
+			4,
+			(0.1, 10.0),
+			&square(),
+		)
+		.unwrap();
+
+		// beams point -y, +x, +y, -x in the world frame
+		let expected = [2.0, 1.0, 2.0, 3.0];
+		for (r, e) in scan.ranges.iter().zip(expected) {
+			assert!((r - e).abs() < 1e-10);
+		}
+
+		let points = scan.to_world_points(Ray::new(Pos2::new(1.0, 0.0), FRAC_PI_2));
+		assert!((points[0] - Pos2::new(1.0, -2.0)).magnitude() < 1e-10);
+		assert!((points[3] - Pos2::new(-2.0, 0.0)).magnitude() < 1e-10);
+
+		// a wall inside range_min hides the square behind it
+		let mut segments = Vec::from(square());
+		segments.push((Pos2::new(1.05, -1.0), Pos2::new(1.05, 1.0)));
+		let scan = LaserScan::simulate(
+			Ray::new(Pos2::new(1.0, 0.0), 0.0),
+			0.0,
+			PI,
+			2,
+			(0.1, 10.0),
+			&segments,
+		)
+		.unwrap();
+		assert!((scan.ranges[0] - 0.05).abs() < 1e-10);
+		assert!(!scan.is_valid(scan.ranges[0]));
+		assert!((scan.ranges[1] - 3.0).abs() < 1e-10);
+		assert_eq!(scan.to_points().len(), 1);
+
+		for range in [(0.1, f64::NAN), (f64::INFINITY, f64::INFINITY), (1.0, 1.0)] {
+			assert_eq!(
+				LaserScan::simulate(Ray::ZERO, 0.0, PI, 2, range, &segments).unwrap_err(),
+				SensorError::InvalidInput
+			);
+		}
+	}
+
+	#[test]
+	fn filter_and_downsample() {
+		let scan = LaserScan::new(
+			0.0,
+			0.1,
+			0.0,
+			5.0,
+			vec![1.0, 2.0, 6.0, 3.0, f64::NAN, 0.5],
+			vec![1.0; 6],
+		)
+		.unwrap();
+		assert_eq!(scan.to_points().len(), 4);
+		assert_eq!(scan.filtered(0.8, 2.5).to_points().len(), 2);
+
+		let down = scan.downsampled(2);
+		assert_eq!(down.len(), 3);
+		assert_eq!(down.intensities.len(), 3);
+		assert_eq!(down.ranges[..2], [1.0, 6.0]);
+		assert!(down.ranges[2].is_nan());
+		assert!((down.angle(2) - 0.4).abs() < 1e-10);
+	}
+}
@@ -0,0 +1,9 @@
+pub(crate) mod laser_scan;
+
+pub use laser_scan::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SensorError {
+	InvalidInput,
+	LengthMismatch,
+}